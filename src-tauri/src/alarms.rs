@@ -0,0 +1,317 @@
+// Alarms: a saved playlist or a track started at a wall-clock time, once, daily or on
+// weekdays, with the volume faded in. Kept in alarms.json in the config directory. The audio
+// thread checks them each time it wakes; the playback watcher starts the ones that come due.
+
+use chrono::{DateTime, Datelike, Duration as ChronoDuration, Local, NaiveTime, TimeZone, Timelike};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager, State};
+
+use crate::relocate::write_atomic;
+use crate::{
+    cue, get_audio_duration, get_config_dir, load_playlist, play_track, stop_playback, track_name, AppState, AudioCommand,
+    TrackInfo,
+};
+
+const DEFAULT_FADE_SECS: u64 = 60;
+const DEFAULT_SNOOZE_MINUTES: u32 = 9;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Repeat {
+    Once,
+    Daily,
+    Weekdays,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Alarm {
+    id: u32,
+    hour: u32,
+    minute: u32,
+    repeat: Repeat,
+    target: String, // A saved playlist's name when `playlist` is set, else a track's path
+    #[serde(default)]
+    playlist: bool,
+    #[serde(default = "default_fade_secs")]
+    fade_secs: u64,
+    #[serde(default = "default_enabled")]
+    enabled: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    rang_on: Option<String>, // Date it last rang, so it rings once within its minute
+}
+
+fn default_fade_secs() -> u64 {
+    DEFAULT_FADE_SECS
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+impl Alarm {
+    fn rings_on(&self, date: DateTime<Local>) -> bool {
+        self.repeat != Repeat::Weekdays || date.weekday().number_from_monday() <= 5
+    }
+    
+    fn rings_at(&self, now: DateTime<Local>) -> bool {
+        self.enabled
+            && now.hour() == self.hour
+            && now.minute() == self.minute
+            && self.rings_on(now)
+            && self.rang_on.as_deref() != Some(now.format("%Y-%m-%d").to_string().as_str())
+    }
+    
+    // When it rings next, within the coming week
+    fn next_ring(&self, now: DateTime<Local>) -> Option<DateTime<Local>> {
+        if !self.enabled {
+            return None;
+        }
+        let time = NaiveTime::from_hms_opt(self.hour, self.minute, 0)?;
+        (0..=7)
+            .filter_map(|days| {
+                let date = (now + ChronoDuration::days(days)).date_naive();
+                Local.from_local_datetime(&date.and_time(time)).earliest()
+            })
+            .find(|at| *at > now && self.rings_on(*at))
+    }
+    
+    fn name(&self) -> String {
+        if self.playlist {
+            self.target.clone()
+        } else {
+            track_name(&self.target)
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct AlarmInfo {
+    #[serde(flatten)]
+    alarm: Alarm,
+    name: String,
+    next: Option<String>, // e.g. "Mon 07:30"
+}
+
+// Volume ramp for the start of an alarm, applied by the audio thread
+pub struct FadeIn {
+    started: Instant,
+    duration: Duration,
+}
+
+impl FadeIn {
+    pub fn new(secs: u64) -> Self {
+        Self {
+            started: Instant::now(),
+            duration: Duration::from_secs(secs),
+        }
+    }
+    
+    // Share of the full volume to play at now, None once the fade is over
+    pub fn level(&self) -> Option<f32> {
+        let elapsed = self.started.elapsed();
+        (elapsed < self.duration).then(|| elapsed.as_secs_f32() / self.duration.as_secs_f32())
+    }
+}
+
+// Alarms as shared between the audio thread, which checks them, and the commands
+#[derive(Default)]
+pub struct Schedule {
+    alarms: Vec<Alarm>,
+    snoozed: Option<(DateTime<Local>, Alarm)>,
+    due: Option<Alarm>,     // Came due, waiting for the playback watcher to start it
+    ringing: Option<Alarm>, // Started last and still on the track it started, for :snooze
+}
+
+fn get_alarms_path() -> Option<PathBuf> {
+    get_config_dir().map(|p| p.join("alarms.json"))
+}
+
+fn save_alarms(alarms: &[Alarm]) -> Result<(), String> {
+    let path = get_alarms_path().ok_or("Could not determine config directory")?;
+    fs::create_dir_all(path.parent().unwrap()).map_err(|e| e.to_string())?;
+    let content = serde_json::to_string_pretty(alarms).map_err(|e| e.to_string())?;
+    write_atomic(&path, &content)
+}
+
+impl Schedule {
+    pub fn load() -> Self {
+        let alarms = get_alarms_path()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+        Self { alarms, ..Self::default() }
+    }
+    
+    // Called by the audio thread each time it wakes
+    pub fn check(&mut self) {
+        if self.due.is_some() {
+            return;
+        }
+        let now = Local::now();
+        if self.snoozed.as_ref().map(|(at, _)| now >= *at).unwrap_or(false) {
+            self.due = self.snoozed.take().map(|(_, alarm)| alarm);
+            return;
+        }
+        
+        let Some(alarm) = self.alarms.iter_mut().find(|a| a.rings_at(now)) else { return };
+        alarm.rang_on = Some(now.format("%Y-%m-%d").to_string());
+        if alarm.repeat == Repeat::Once {
+            alarm.enabled = false;
+        }
+        self.due = Some(alarm.clone());
+        let _ = save_alarms(&self.alarms);
+    }
+    
+    fn infos(&self) -> Vec<AlarmInfo> {
+        let now = Local::now();
+        self.alarms
+            .iter()
+            .map(|alarm| AlarmInfo {
+                name: alarm.name(),
+                next: alarm.next_ring(now).map(|at| at.format("%a %H:%M").to_string()),
+                alarm: alarm.clone(),
+            })
+            .collect()
+    }
+}
+
+#[derive(Serialize, Clone)]
+struct AlarmStartedPayload {
+    name: String,
+    playlist: bool,
+    tracks: Vec<TrackInfo>,
+    index: usize,
+}
+
+// Load the alarm's playlist or track, play it and fade it in
+fn start(app: &AppHandle, alarm: &Alarm) -> Result<AlarmStartedPayload, String> {
+    let state = app.state::<AppState>();
+    let tracks = if alarm.playlist {
+        load_playlist(alarm.target.clone(), app.state())?
+    } else {
+        if !cue::exists(&alarm.target) {
+            return Err(format!("{} is unavailable", track_name(&alarm.target)));
+        }
+        *state.playlist.lock().unwrap() = vec![alarm.target.clone()];
+        vec![TrackInfo {
+            path: alarm.target.clone(),
+            name: track_name(&alarm.target),
+            index: 0,
+            duration: get_audio_duration(&alarm.target),
+            missing: false,
+        }]
+    };
+    let index = tracks.iter().position(|t| !t.missing).ok_or("Nothing to play")?;
+    
+    // The fade goes first so the track doesn't start at full volume, and is called off by
+    // setting the volume again if the track doesn't start
+    state.player.send(AudioCommand::FadeIn(alarm.fade_secs));
+    if let Err(err) = play_track(index, None, app.state()) {
+        state.player.send(AudioCommand::SetVolume(*state.volume.lock().unwrap()));
+        return Err(err);
+    }
+    state.player.alarms.lock().unwrap().ringing = Some(alarm.clone());
+    Ok(AlarmStartedPayload {
+        name: alarm.name(),
+        playlist: alarm.playlist,
+        tracks,
+        index,
+    })
+}
+
+// The alarm's track was stopped or another one started, so there's nothing left to snooze
+pub fn stop_ringing(state: &AppState) {
+    state.player.alarms.lock().unwrap().ringing = None;
+}
+
+// Runs every playback watcher tick: starts an alarm the audio thread found due
+pub fn tick(app: &AppHandle, state: &AppState) {
+    let due = state.player.alarms.lock().unwrap().due.take();
+    if let Some(alarm) = due {
+        match start(app, &alarm) {
+            Ok(payload) => {
+                let _ = app.emit_all("alarm-started", payload);
+            }
+            Err(err) => {
+                let _ = app.emit_all("alarm-failed", format!("Alarm {}: {}", alarm.name(), err));
+            }
+        }
+    }
+}
+
+fn parse_time(time: &str) -> Result<(u32, u32), String> {
+    let parsed = NaiveTime::parse_from_str(time.trim(), "%H:%M").map_err(|_| format!("Invalid time '{}' (use HH:MM)", time))?;
+    Ok((parsed.hour(), parsed.minute()))
+}
+
+#[tauri::command]
+pub fn list_alarms(state: State<AppState>) -> Vec<AlarmInfo> {
+    state.player.alarms.lock().unwrap().infos()
+}
+
+#[tauri::command]
+pub fn add_alarm(
+    time: String,
+    repeat: Repeat,
+    target: String,
+    playlist: bool,
+    fade_secs: Option<u64>,
+    state: State<AppState>,
+) -> Result<Vec<AlarmInfo>, String> {
+    let (hour, minute) = parse_time(&time)?;
+    if target.trim().is_empty() {
+        return Err("Nothing to play".to_string());
+    }
+    
+    let mut schedule = state.player.alarms.lock().unwrap();
+    let id = schedule.alarms.iter().map(|a| a.id).max().unwrap_or(0) + 1;
+    schedule.alarms.push(Alarm {
+        id,
+        hour,
+        minute,
+        repeat,
+        target,
+        playlist,
+        fade_secs: fade_secs.unwrap_or(DEFAULT_FADE_SECS),
+        enabled: true,
+        rang_on: None,
+    });
+    save_alarms(&schedule.alarms)?;
+    Ok(schedule.infos())
+}
+
+#[tauri::command]
+pub fn remove_alarm(id: u32, state: State<AppState>) -> Result<Vec<AlarmInfo>, String> {
+    let mut schedule = state.player.alarms.lock().unwrap();
+    let count = schedule.alarms.len();
+    schedule.alarms.retain(|a| a.id != id);
+    if schedule.alarms.len() == count {
+        return Err(format!("No alarm {}", id));
+    }
+    save_alarms(&schedule.alarms)?;
+    Ok(schedule.infos())
+}
+
+#[tauri::command]
+pub fn toggle_alarm(id: u32, state: State<AppState>) -> Result<Vec<AlarmInfo>, String> {
+    let mut schedule = state.player.alarms.lock().unwrap();
+    let alarm = schedule.alarms.iter_mut().find(|a| a.id == id).ok_or(format!("No alarm {}", id))?;
+    alarm.enabled = !alarm.enabled;
+    save_alarms(&schedule.alarms)?;
+    Ok(schedule.infos())
+}
+
+// Stop the alarm that rang last and ring it again in a few minutes; returns when
+#[tauri::command]
+pub fn snooze_alarm(minutes: Option<u32>, state: State<AppState>) -> Result<String, String> {
+    let mut schedule = state.player.alarms.lock().unwrap();
+    let alarm = schedule.ringing.take().ok_or("No alarm to snooze")?;
+    let at = Local::now() + ChronoDuration::minutes(minutes.unwrap_or(DEFAULT_SNOOZE_MINUTES).max(1) as i64);
+    schedule.snoozed = Some((at, alarm));
+    drop(schedule);
+    stop_playback(&state);
+    Ok(at.format("%H:%M").to_string())
+}
@@ -0,0 +1,304 @@
+// Tempo and key estimation from decoded audio. Tempo comes from the periodicity of
+// spectral onsets, key from the average pitch-class profile matched against
+// Krumhansl's major and minor key profiles.
+
+use lofty::ItemKey;
+use rustfft::num_complex::Complex;
+use rustfft::FftPlanner;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+
+use tauri::{AppHandle, Manager};
+
+use crate::{
+    cache_missing_tracks, decode, load_metadata_cache, tags, update_metadata_cache, AppState, TrackInfo, TrackMeta,
+};
+
+const SAMPLE_RATE: u32 = 11025;
+// Enough of the track to settle on a tempo and key without decoding all of it
+const LENGTH_MS: u64 = 150_000;
+
+const ONSET_FRAME: usize = 1024;
+const ONSET_HOP: usize = 256;
+const MIN_BPM: f64 = 60.0;
+const MAX_BPM: f64 = 200.0;
+
+const CHROMA_FRAME: usize = 4096;
+const MIN_FREQ: f64 = 55.0;
+const MAX_FREQ: f64 = 2000.0;
+
+const NOTE_NAMES: [&str; 12] = ["C", "C#", "D", "Eb", "E", "F", "F#", "G", "Ab", "A", "Bb", "B"];
+const MAJOR_PROFILE: [f64; 12] = [6.35, 2.23, 3.48, 2.33, 4.38, 4.09, 2.52, 5.19, 2.39, 3.66, 2.29, 2.88];
+const MINOR_PROFILE: [f64; 12] = [6.33, 2.68, 3.52, 5.38, 2.60, 3.53, 2.54, 4.75, 3.98, 2.69, 3.34, 3.17];
+
+pub fn hann(size: usize) -> Vec<f32> {
+    (0..size)
+        .map(|i| 0.5 - 0.5 * (2.0 * std::f32::consts::PI * i as f32 / (size - 1) as f32).cos())
+        .collect()
+}
+
+// Magnitude spectrum of each frame
+fn spectra(samples: &[f32], frame: usize, hop: usize) -> Vec<Vec<f32>> {
+    let window = hann(frame);
+    let fft = FftPlanner::<f32>::new().plan_fft_forward(frame);
+    let mut buffer = vec![Complex::new(0.0, 0.0); frame];
+    let mut frames = Vec::new();
+    let mut start = 0;
+    while start + frame <= samples.len() {
+        for (slot, (sample, w)) in buffer.iter_mut().zip(samples[start..].iter().zip(&window)) {
+            *slot = Complex::new(sample * w, 0.0);
+        }
+        fft.process(&mut buffer);
+        frames.push(buffer[..frame / 2].iter().map(|c| c.norm()).collect());
+        start += hop;
+    }
+    frames
+}
+
+// Onset strength per frame: the rise in log-compressed spectrum, minus its local average
+fn onset_envelope(samples: &[f32]) -> Vec<f64> {
+    let frames = spectra(samples, ONSET_FRAME, ONSET_HOP);
+    let compressed: Vec<Vec<f32>> = frames.iter().map(|f| f.iter().map(|m| (1.0 + 100.0 * m).ln()).collect()).collect();
+    let flux: Vec<f64> = compressed
+        .windows(2)
+        .map(|pair| pair[1].iter().zip(&pair[0]).map(|(b, a)| (b - a).max(0.0) as f64).sum())
+        .collect();
+    
+    let half_window = 8;
+    (0..flux.len())
+        .map(|i| {
+            let window = &flux[i.saturating_sub(half_window)..(i + half_window + 1).min(flux.len())];
+            let mean = window.iter().sum::<f64>() / window.len() as f64;
+            (flux[i] - mean).max(0.0)
+        })
+        .collect()
+}
+
+fn autocorrelation(envelope: &[f64], lag: usize) -> f64 {
+    if lag >= envelope.len() {
+        return 0.0;
+    }
+    let sum: f64 = envelope.iter().zip(&envelope[lag..]).map(|(a, b)| a * b).sum();
+    sum / (envelope.len() - lag) as f64
+}
+
+// Beats per minute, from the strongest onset periodicity between MIN_BPM and MAX_BPM.
+// Periods that line up with their double score higher, and a mild preference for
+// tempos near 120 BPM settles half/double-tempo ambiguity the way listeners usually do.
+fn estimate_bpm(samples: &[f32]) -> Option<f64> {
+    let envelope = onset_envelope(samples);
+    let frame_rate = SAMPLE_RATE as f64 / ONSET_HOP as f64;
+    let min_lag = (frame_rate * 60.0 / MAX_BPM).floor() as usize;
+    let max_lag = (frame_rate * 60.0 / MIN_BPM).ceil() as usize;
+    if envelope.len() < max_lag * 4 {
+        return None;
+    }
+    
+    let correlation: Vec<f64> = (0..=max_lag * 2).map(|lag| autocorrelation(&envelope, lag)).collect();
+    let score = |lag: usize| {
+        let bpm = frame_rate * 60.0 / lag as f64;
+        let preference = (-0.5 * ((bpm / 120.0).log2() / 0.9).powi(2)).exp();
+        (correlation[lag] + 0.5 * correlation[lag * 2]) * preference
+    };
+    let best = (min_lag..=max_lag).max_by(|&a, &b| score(a).total_cmp(&score(b)))?;
+    if correlation[best] <= 0.0 {
+        return None;
+    }
+    
+    // Parabolic interpolation around the peak for a fractional period
+    let (before, peak, after) = (correlation[best - 1], correlation[best], correlation[best + 1]);
+    let curvature = before - 2.0 * peak + after;
+    let shift = if curvature < 0.0 { (0.5 * (before - after) / curvature).clamp(-0.5, 0.5) } else { 0.0 };
+    let bpm = frame_rate * 60.0 / (best as f64 + shift);
+    Some((bpm * 10.0).round() / 10.0)
+}
+
+// Average share of each pitch class (C = 0) over frames with enough energy
+fn pitch_profile(samples: &[f32]) -> [f64; 12] {
+    let bin_freq = SAMPLE_RATE as f64 / CHROMA_FRAME as f64;
+    let min_bin = (MIN_FREQ / bin_freq).ceil() as usize;
+    let max_bin = (MAX_FREQ / bin_freq).floor() as usize;
+    // Pitch class of each bin, rounding to the nearest semitone
+    let classes: Vec<usize> = (min_bin..=max_bin)
+        .map(|bin| {
+            let semitones_above_c = 12.0 * (bin as f64 * bin_freq / 16.351_597_831_287_414).log2();
+            (semitones_above_c.round() as i64).rem_euclid(12) as usize
+        })
+        .collect();
+    
+    let mut profile = [0.0; 12];
+    for spectrum in spectra(samples, CHROMA_FRAME, CHROMA_FRAME) {
+        let mut chroma = [0.0; 12];
+        for (offset, &class) in classes.iter().enumerate() {
+            chroma[class] += (spectrum[min_bin + offset] as f64).powi(2);
+        }
+        let total: f64 = chroma.iter().sum();
+        if total > 1e-6 {
+            for (p, c) in profile.iter_mut().zip(chroma) {
+                *p += c / total;
+            }
+        }
+    }
+    profile
+}
+
+fn correlation(a: &[f64; 12], b: &[f64; 12]) -> f64 {
+    let mean_a = a.iter().sum::<f64>() / 12.0;
+    let mean_b = b.iter().sum::<f64>() / 12.0;
+    let (mut cov, mut var_a, mut var_b) = (0.0, 0.0, 0.0);
+    for i in 0..12 {
+        cov += (a[i] - mean_a) * (b[i] - mean_b);
+        var_a += (a[i] - mean_a).powi(2);
+        var_b += (b[i] - mean_b).powi(2);
+    }
+    if var_a <= 0.0 || var_b <= 0.0 {
+        return 0.0;
+    }
+    cov / (var_a * var_b).sqrt()
+}
+
+// Key name such as "F#" or "Bbm", written the way TKEY expects
+fn estimate_key(samples: &[f32]) -> Option<String> {
+    let profile = pitch_profile(samples);
+    if profile.iter().all(|p| *p == 0.0) {
+        return None;
+    }
+    let mut best: Option<(f64, usize, bool)> = None;
+    for tonic in 0..12 {
+        let rotated: [f64; 12] = std::array::from_fn(|i| profile[(i + tonic) % 12]);
+        for (minor, template) in [(false, &MAJOR_PROFILE), (true, &MINOR_PROFILE)] {
+            let r = correlation(&rotated, template);
+            if best.map(|(score, _, _)| r > score).unwrap_or(true) {
+                best = Some((r, tonic, minor));
+            }
+        }
+    }
+    let (_, tonic, minor) = best?;
+    Some(format!("{}{}", NOTE_NAMES[tonic], if minor { "m" } else { "" }))
+}
+
+fn analyze(path: &str) -> (Option<f64>, Option<String>) {
+    let samples = match decode::decode_mono(path, Some(LENGTH_MS)) {
+        Some(samples) => samples,
+        None => return (None, None),
+    };
+    let data = samples.resampled(SAMPLE_RATE);
+    (estimate_bpm(&data), estimate_key(&data))
+}
+
+// Position on the Camelot wheel as (number, is_major), so keys sort in harmonic-mixing
+// order ("8A" is A minor, "8B" C major). Accepts names like "Am", "F#", "Bb minor" and Camelot codes.
+pub fn camelot(key: &str) -> Option<(u8, bool)> {
+    let key = key.trim();
+    if let Some(code) = key.strip_suffix(['A', 'B', 'a', 'b']).and_then(|n| n.parse::<u8>().ok()) {
+        let major = key.ends_with(['B', 'b']);
+        return (1..=12).contains(&code).then_some((code, major));
+    }
+    
+    let mut chars = key.chars();
+    let letter = chars.next()?.to_ascii_uppercase();
+    let pitch: i32 = match letter {
+        'C' => 0,
+        'D' => 2,
+        'E' => 4,
+        'F' => 5,
+        'G' => 7,
+        'A' => 9,
+        'B' => 11,
+        _ => return None,
+    };
+    let rest = chars.as_str();
+    let (pitch, rest) = if let Some(rest) = rest.strip_prefix(['#', '♯']) {
+        (pitch + 1, rest)
+    } else if let Some(rest) = rest.strip_prefix(['b', '♭']) {
+        (pitch - 1, rest)
+    } else {
+        (pitch, rest)
+    };
+    let rest = rest.trim().to_lowercase();
+    let minor = rest == "m" || rest.starts_with("min");
+    // Minor keys sit with their relative major, three semitones up
+    let major_pitch = (pitch + if minor { 3 } else { 0 }).rem_euclid(12);
+    Some(((7 * major_pitch + 7).rem_euclid(12) as u8 + 1, !minor))
+}
+
+#[derive(Serialize)]
+pub struct AnalysisReport {
+    analyzed: usize,
+    failed: usize,
+    written: usize,
+}
+
+// Estimate tempo and key of `paths` into the metadata cache. Tracks that already have both,
+// from tags or an earlier pass, are skipped unless `force`. With `write_tags`, results also
+// go into the files' BPM (TBPM) and initial key (TKEY) tags.
+#[tauri::command]
+pub async fn analyze_tracks(
+    paths: Vec<String>,
+    write_tags: bool,
+    force: bool,
+    app: AppHandle,
+) -> Result<AnalysisReport, String> {
+    tauri::async_runtime::spawn_blocking(move || analyze_all(&app.state::<AppState>(), &paths, write_tags, force))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+fn analyze_all(state: &AppState, paths: &[String], write_tags: bool, force: bool) -> Result<AnalysisReport, String> {
+    let mut cache = load_metadata_cache();
+    cache_missing_tracks(state, &mut cache, paths);
+    let requested: HashSet<&String> = paths.iter().collect();
+    let pending: Vec<TrackMeta> = cache
+        .tracks
+        .into_iter()
+        .filter(|m| requested.contains(&m.path) && (force || m.bpm.is_none() || m.key.is_none()))
+        .collect();
+    
+    let mut report = AnalysisReport { analyzed: 0, failed: 0, written: 0 };
+    let mut results = HashMap::new();
+    for mut meta in pending {
+        let (bpm, key) = analyze(&meta.path);
+        if bpm.is_none() && key.is_none() {
+            report.failed += 1;
+            continue;
+        }
+        meta.bpm = bpm.or(meta.bpm);
+        meta.key = key.or(meta.key.take());
+        report.analyzed += 1;
+        
+        // Cue sheet tracks share one file, so their results stay in the cache only
+        if write_tags && crate::cue::segment(&meta.path).is_none() {
+            let fields = [
+                (ItemKey::Bpm, meta.bpm.map(|b| format!("{}", b.round()))),
+                (ItemKey::InitialKey, meta.key.clone()),
+            ];
+            if tags::write_fields(&meta.path, &fields).is_ok() {
+                report.written += 1;
+            }
+        }
+        results.insert(meta.path, (meta.bpm, meta.key));
+    }
+    
+    // Only tempo and key are set, so edits made to the tracks while they were analyzed stay
+    update_metadata_cache(state, |cache| {
+        for meta in cache.tracks.iter_mut() {
+            if let Some((bpm, key)) = results.remove(&meta.path) {
+                meta.bpm = bpm;
+                meta.key = key;
+            }
+        }
+        report.analyzed > 0
+    })?;
+    Ok(report)
+}
+
+// Listed tracks with a tempo between `min` and `max` BPM, for tempo-based smart lists
+#[tauri::command]
+pub fn tracks_in_tempo(tracks: Vec<TrackInfo>, min: f64, max: f64) -> Vec<TrackInfo> {
+    let cache = load_metadata_cache();
+    let bpms: HashMap<&String, f64> = cache.tracks.iter().filter_map(|m| Some((&m.path, m.bpm?))).collect();
+    tracks
+        .into_iter()
+        .filter(|t| bpms.get(&t.path).map(|bpm| (min..=max).contains(bpm)).unwrap_or(false))
+        .collect()
+}
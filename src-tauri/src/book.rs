@@ -0,0 +1,185 @@
+// Audiobooks: a folder of parts played as one continuous track. Positions, seeking and
+// resume cover the whole book; the audio thread queues each part behind the one playing.
+
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::PathBuf;
+use tauri::State;
+
+use crate::{
+    cache_missing_tracks, get_audio_duration_ms, is_audio_file, load_metadata_cache, paths, sortkey, track_name,
+    update_metadata_cache, AppState, AudioCommand, TrackInfo,
+};
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Part {
+    pub path: String,
+    pub start_ms: u64, // Position of the part within the book
+    pub duration_ms: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Book {
+    pub folder: String,
+    pub parts: Vec<Part>,
+    pub duration_ms: u64,
+}
+
+impl Book {
+    // Index of the part holding a book position, and the offset into that part
+    pub fn locate(&self, position_ms: u64) -> (usize, u64) {
+        let index = self
+            .parts
+            .partition_point(|p| p.start_ms <= position_ms)
+            .saturating_sub(1);
+        let part = &self.parts[index];
+        (index, position_ms.saturating_sub(part.start_ms).min(part.duration_ms))
+    }
+}
+
+// What a book keeps besides its position, in its metadata cache entry
+#[derive(Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct BookSettings {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub speed: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sleep_minutes: Option<u32>,
+}
+
+// Playlist entries that are folders are books
+pub fn is_book(path: &str) -> bool {
+    paths::from_string(path).is_dir()
+}
+
+fn get_books_dir() -> Option<PathBuf> {
+    dirs::cache_dir().map(|p| p.join("vi-music").join("books"))
+}
+
+// Audio files directly inside the folder, in natural order
+fn part_files(folder: &str) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = fs::read_dir(paths::from_string(folder))
+        .map(|entries| entries.filter_map(|e| e.ok()).map(|e| e.path()).collect())
+        .unwrap_or_default();
+    files.retain(|p| p.is_file() && is_audio_file(p));
+    let sorter = sortkey::Sorter::from_config();
+    files.sort_by_cached_key(|p| sorter.key(&p.file_name().unwrap_or_default().to_string_lossy()));
+    files
+}
+
+// The first part stands in for the book where one file is needed, e.g. for cover art
+pub fn first_part(folder: &str) -> Option<String> {
+    part_files(folder).first().map(|p| paths::to_string(p))
+}
+
+// Part lengths take a while to measure for long MP3s, so they're cached by the parts' paths,
+// sizes and mtimes
+fn cache_file(folder: &str, files: &[PathBuf]) -> Option<PathBuf> {
+    let mut hasher = DefaultHasher::new();
+    folder.hash(&mut hasher);
+    for file in files {
+        let metadata = fs::metadata(file).ok()?;
+        file.hash(&mut hasher);
+        metadata.len().hash(&mut hasher);
+        metadata.modified().ok().hash(&mut hasher);
+    }
+    Some(get_books_dir()?.join(format!("{:016x}.json", hasher.finish())))
+}
+
+// The book in `folder`, or None when it holds no playable parts
+pub fn load(folder: &str) -> Option<Book> {
+    let files = part_files(folder);
+    let cached = cache_file(folder, &files);
+    if let Some(book) = cached
+        .as_ref()
+        .and_then(|file| fs::read_to_string(file).ok())
+        .and_then(|content| serde_json::from_str(&content).ok())
+    {
+        return Some(book);
+    }
+    
+    let mut parts = Vec::new();
+    let mut start_ms = 0;
+    for file in &files {
+        let path = paths::to_string(file);
+        // Parts whose length can't be read can't be placed in the book, so they're left out
+        if let Some(duration_ms) = get_audio_duration_ms(&path).filter(|d| *d > 0) {
+            parts.push(Part { path, start_ms, duration_ms });
+            start_ms += duration_ms;
+        }
+    }
+    if parts.is_empty() {
+        return None;
+    }
+    
+    let book = Book { folder: folder.to_string(), parts, duration_ms: start_ms };
+    if let (Some(file), Ok(content)) = (cached, serde_json::to_string(&book)) {
+        let _ = fs::create_dir_all(file.parent().unwrap());
+        let _ = fs::write(file, content);
+    }
+    Some(book)
+}
+
+pub fn settings(folder: &str) -> BookSettings {
+    load_metadata_cache()
+        .tracks
+        .into_iter()
+        .find(|m| m.path == folder)
+        .and_then(|m| m.book)
+        .unwrap_or_default()
+}
+
+fn update_settings(state: &AppState, folder: &str, update: impl FnOnce(&mut BookSettings)) -> Result<(), String> {
+    cache_missing_tracks(state, &mut load_metadata_cache(), &[folder.to_string()]);
+    update_metadata_cache(state, |cache| {
+        let Some(meta) = cache.tracks.iter_mut().find(|m| m.path == folder) else { return false };
+        let mut settings = meta.book.clone().unwrap_or_default();
+        update(&mut settings);
+        let changed = meta.book.as_ref() != Some(&settings);
+        meta.book = Some(settings);
+        changed
+    })
+}
+
+// Switch to a book's own speed when it starts, and back to the earlier speed after it
+pub fn apply_speed(state: &AppState, path: &str) {
+    let mut outside = state.speed_outside_books.lock().unwrap();
+    if is_book(path) {
+        outside.get_or_insert(state.player.get_speed());
+        if let Some(speed) = settings(path).speed {
+            state.player.send(AudioCommand::SetSpeed(speed));
+        }
+    } else if let Some(speed) = outside.take() {
+        state.player.send(AudioCommand::SetSpeed(speed));
+    }
+}
+
+// Keep a speed change made while a book plays with that book
+pub fn remember_speed(state: &AppState, speed: f32) {
+    if let Some(path) = state.player.get_current_path().filter(|p| is_book(p)) {
+        let _ = update_settings(state, &path, |s| s.speed = Some(speed));
+    }
+}
+
+// Likewise the sleep timer, started again whenever the book plays; 0 forgets it
+pub fn remember_sleep(state: &AppState, minutes: u32) {
+    if let Some(path) = state.player.get_current_path().filter(|p| is_book(p)) {
+        let _ = update_settings(state, &path, |s| s.sleep_minutes = Some(minutes).filter(|m| *m > 0));
+    }
+}
+
+// Replace the playlist with the book in `path`
+#[tauri::command]
+pub fn load_book(path: String, state: State<AppState>) -> Result<Vec<TrackInfo>, String> {
+    let book = load(&path).ok_or("No playable audio files in this folder")?;
+    *state.playlist.lock().unwrap() = vec![path.clone()];
+    *state.current_index.lock().unwrap() = 0;
+    Ok(vec![TrackInfo {
+        name: track_name(&path),
+        path,
+        index: 0,
+        duration: Some(book.duration_ms / 1000),
+        missing: false,
+    }])
+}
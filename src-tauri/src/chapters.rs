@@ -0,0 +1,530 @@
+// Chapter markers from ID3 CHAP/CTOC, MP4 (Nero chpl and QuickTime chapter tracks)
+// and Vorbis CHAPTERxx comments
+
+use lofty::{ItemKey, TaggedFileExt};
+use serde::Serialize;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+use tauri::{AppHandle, Manager, State};
+
+use crate::book;
+use crate::id3;
+use crate::paths;
+use crate::{AppState, AudioCommand};
+
+#[derive(Serialize, Clone)]
+pub struct Chapter {
+    title: String,
+    start_ms: u64,
+    end_ms: Option<u64>,
+}
+
+// Chapters of the loaded track, read once when it starts playing
+pub struct LoadedChapters {
+    path: String,
+    chapters: Vec<Chapter>,
+}
+
+#[derive(Serialize, Clone)]
+struct ChapterPayload {
+    path: String,
+    index: Option<usize>,
+    title: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct ChapterJump {
+    index: usize,
+    title: String,
+    position: u64,
+}
+
+fn id3_chapters(path: &Path) -> Vec<Chapter> {
+    let (version, body) = match id3::read_tag(path) {
+        Some(tag) => tag,
+        None => return Vec::new(),
+    };
+    
+    let mut chapters = Vec::new();
+    let mut order: Vec<String> = Vec::new();
+    for (id, data) in id3::frames(version, &body) {
+        match id.as_str() {
+            "CHAP" => {
+                let (element_id, rest) = id3::read_element_id(data);
+                if rest.len() < 16 {
+                    continue;
+                }
+                let start = u32::from_be_bytes([rest[0], rest[1], rest[2], rest[3]]) as u64;
+                let end = u32::from_be_bytes([rest[4], rest[5], rest[6], rest[7]]) as u64;
+                let title = id3::frames(version, &rest[16..])
+                    .into_iter()
+                    .find(|(sub_id, _)| sub_id == "TIT2")
+                    .and_then(|(_, sub)| sub.split_first().map(|(&enc, text)| id3::read_text(enc, text).0))
+                    .unwrap_or_default();
+                chapters.push((element_id, Chapter { title, start_ms: start, end_ms: Some(end) }));
+            }
+            "CTOC" => {
+                // Only the top-level table of contents decides the order
+                let (_, rest) = id3::read_element_id(data);
+                if rest.len() < 2 || rest[0] & 0x02 == 0 {
+                    continue;
+                }
+                let mut entries = &rest[2..];
+                for _ in 0..rest[1] {
+                    let (child, after) = id3::read_element_id(entries);
+                    order.push(child);
+                    entries = after;
+                }
+            }
+            _ => {}
+        }
+    }
+    
+    if !order.is_empty() {
+        chapters.sort_by_key(|(id, c)| (order.iter().position(|o| o == id).unwrap_or(usize::MAX), c.start_ms));
+    } else {
+        chapters.sort_by_key(|(_, c)| c.start_ms);
+    }
+    chapters.into_iter().map(|(_, c)| c).collect()
+}
+
+// Atom (box) children of an in-memory MP4 container: (type, payload)
+fn atoms(mut data: &[u8]) -> Vec<(&[u8], &[u8])> {
+    let mut atoms = Vec::new();
+    while data.len() >= 8 {
+        let mut size = u32::from_be_bytes([data[0], data[1], data[2], data[3]]) as usize;
+        let mut header = 8;
+        if size == 1 && data.len() >= 16 {
+            size = u64::from_be_bytes(data[8..16].try_into().unwrap()) as usize;
+            header = 16;
+        } else if size == 0 {
+            size = data.len();
+        }
+        if size < header || size > data.len() {
+            break;
+        }
+        atoms.push((&data[4..8], &data[header..size]));
+        data = &data[size..];
+    }
+    atoms
+}
+
+fn child<'a>(data: &'a [u8], name: &[u8]) -> Option<&'a [u8]> {
+    atoms(data).into_iter().find(|(t, _)| *t == name).map(|(_, d)| d)
+}
+
+fn path_to<'a>(data: &'a [u8], names: &[&[u8]]) -> Option<&'a [u8]> {
+    names.iter().try_fold(data, |d, name| child(d, name))
+}
+
+fn read_u32(data: &[u8], at: usize) -> Option<u64> {
+    data.get(at..at + 4).map(|b| u32::from_be_bytes(b.try_into().unwrap()) as u64)
+}
+
+fn read_u64(data: &[u8], at: usize) -> Option<u64> {
+    data.get(at..at + 8).map(|b| u64::from_be_bytes(b.try_into().unwrap()))
+}
+
+// Entry count of a sample table whose count sits at `at`, capped by how many entries of
+// `entry_size` bytes the table actually holds
+fn entry_count(table: &[u8], at: usize, entry_size: usize) -> Option<usize> {
+    let count = read_u32(table, at)? as usize;
+    Some(count.min(table.len().saturating_sub(at + 4) / entry_size))
+}
+
+// The moov atom, found by skipping over top-level atoms such as mdat
+fn read_moov(file: &mut File) -> Option<Vec<u8>> {
+    let file_len = file.metadata().ok()?.len();
+    let mut position = 0;
+    while position + 8 <= file_len {
+        file.seek(SeekFrom::Start(position)).ok()?;
+        let mut header = [0u8; 16];
+        file.read_exact(&mut header[..8]).ok()?;
+        let mut size = u32::from_be_bytes(header[..4].try_into().unwrap()) as u64;
+        let mut header_len = 8;
+        if size == 1 {
+            file.read_exact(&mut header[8..16]).ok()?;
+            size = u64::from_be_bytes(header[8..16].try_into().unwrap());
+            header_len = 16;
+        } else if size == 0 {
+            size = file_len - position;
+        }
+        if size < header_len || size > file_len - position {
+            return None;
+        }
+        if &header[4..8] == b"moov" {
+            let mut moov = vec![0u8; (size - header_len) as usize];
+            file.read_exact(&mut moov).ok()?;
+            return Some(moov);
+        }
+        position += size;
+    }
+    None
+}
+
+// Nero chapters: version, flags, [reserved], count, then (start in 100ns, title) entries
+fn nero_chapters(moov: &[u8]) -> Vec<Chapter> {
+    let chpl = match path_to(moov, &[b"udta", b"chpl"]) {
+        Some(chpl) if !chpl.is_empty() => chpl,
+        _ => return Vec::new(),
+    };
+    let mut at = if chpl[0] == 1 { 8 } else { 4 };
+    let count = chpl.get(at).copied().unwrap_or(0);
+    at += 1;
+    
+    let mut chapters = Vec::new();
+    for _ in 0..count {
+        let start = match read_u64(chpl, at) {
+            Some(start) => start,
+            None => break,
+        };
+        let len = chpl.get(at + 8).copied().unwrap_or(0) as usize;
+        let title = match chpl.get(at + 9..at + 9 + len) {
+            Some(title) => String::from_utf8_lossy(title).to_string(),
+            None => break,
+        };
+        chapters.push(Chapter { title, start_ms: start / 10_000, end_ms: None });
+        at += 9 + len;
+    }
+    chapters
+}
+
+fn track_id(trak: &[u8]) -> Option<u64> {
+    let tkhd = child(trak, b"tkhd")?;
+    if tkhd.first() == Some(&1) {
+        read_u32(tkhd, 20)
+    } else {
+        read_u32(tkhd, 12)
+    }
+}
+
+// QuickTime chapter track: a text track referenced from another track's tref/chap,
+// whose samples are (u16 length, title) at times given by stts
+fn chapter_track(file: &mut File, moov: &[u8]) -> Vec<Chapter> {
+    let traks: Vec<&[u8]> = atoms(moov)
+        .into_iter()
+        .filter(|(t, _)| *t == b"trak")
+        .map(|(_, d)| d)
+        .collect();
+    let chapter_id = traks
+        .iter()
+        .find_map(|trak| path_to(trak, &[b"tref", b"chap"]).and_then(|chap| read_u32(chap, 0)));
+    let trak = match chapter_id.and_then(|id| traks.iter().find(|t| track_id(t) == Some(id))) {
+        Some(trak) => *trak,
+        None => return Vec::new(),
+    };
+    read_chapter_track(file, trak).unwrap_or_default()
+}
+
+fn read_chapter_track(file: &mut File, trak: &[u8]) -> Option<Vec<Chapter>> {
+    let mdhd = path_to(trak, &[b"mdia", b"mdhd"])?;
+    let timescale = if mdhd.first() == Some(&1) {
+        read_u32(mdhd, 20)?
+    } else {
+        read_u32(mdhd, 12)?
+    };
+    if timescale == 0 {
+        return None;
+    }
+    let stbl = path_to(trak, &[b"mdia", b"minf", b"stbl"])?;
+    // Every count below comes from the file, so each is capped by what the file can hold
+    let file_len = file.metadata().ok()?.len();
+    
+    // Sample sizes
+    let stsz = child(stbl, b"stsz")?;
+    let fixed_size = read_u32(stsz, 4)?;
+    // A size of 0 means each sample's size is listed
+    let sizes: Vec<u64> = match file_len.checked_div(fixed_size) {
+        Some(most) => vec![fixed_size; read_u32(stsz, 8)?.min(most) as usize],
+        None => (0..entry_count(stsz, 8, 4)?).map(|i| read_u32(stsz, 12 + i * 4)).collect::<Option<_>>()?,
+    };
+    
+    // Sample start times
+    let stts = child(stbl, b"stts")?;
+    let mut starts = Vec::new();
+    let mut time: u64 = 0;
+    for entry in 0..entry_count(stts, 4, 8)? {
+        let count = read_u32(stts, 8 + entry * 8)?;
+        let delta = read_u32(stts, 12 + entry * 8)?;
+        for _ in 0..count.min((sizes.len() - starts.len()) as u64) {
+            starts.push(time.saturating_mul(1000) / timescale);
+            time = time.saturating_add(delta);
+        }
+    }
+    
+    // Chunk offsets
+    let offsets: Vec<u64> = if let Some(stco) = child(stbl, b"stco") {
+        (0..entry_count(stco, 4, 4)?).map(|i| read_u32(stco, 8 + i * 4)).collect::<Option<_>>()?
+    } else {
+        let co64 = child(stbl, b"co64")?;
+        (0..entry_count(co64, 4, 8)?).map(|i| read_u64(co64, 8 + i * 8)).collect::<Option<_>>()?
+    };
+    
+    // Samples per chunk, as runs starting at a 1-based chunk number
+    let stsc = child(stbl, b"stsc")?;
+    let runs: Vec<(u64, u64)> = (0..entry_count(stsc, 4, 12)?)
+        .map(|i| Some((read_u32(stsc, 8 + i * 12)?, read_u32(stsc, 12 + i * 12)?)))
+        .collect::<Option<_>>()?;
+    
+    let mut chapters = Vec::new();
+    let mut sample = 0;
+    for (chunk, &chunk_offset) in offsets.iter().enumerate() {
+        let per_chunk = runs
+            .iter()
+            .take_while(|(first, _)| *first as usize <= chunk + 1)
+            .last()
+            .map(|(_, n)| *n)
+            .unwrap_or(1);
+        let mut offset = chunk_offset;
+        for _ in 0..per_chunk {
+            if sample >= sizes.len() || sample >= starts.len() {
+                break;
+            }
+            if offset >= file_len || sizes[sample] > file_len - offset {
+                return None;
+            }
+            let mut data = vec![0u8; sizes[sample] as usize];
+            file.seek(SeekFrom::Start(offset)).ok()?;
+            file.read_exact(&mut data).ok()?;
+            
+            let len = data.get(..2).map(|b| u16::from_be_bytes([b[0], b[1]]) as usize).unwrap_or(0);
+            let text = data.get(2..2 + len).unwrap_or(&[]);
+            let title = if text.starts_with(&[0xFE, 0xFF]) || text.starts_with(&[0xFF, 0xFE]) {
+                id3::read_text(1, text).0
+            } else {
+                String::from_utf8_lossy(text).to_string()
+            };
+            chapters.push(Chapter { title, start_ms: starts[sample], end_ms: None });
+            
+            offset += sizes[sample];
+            sample += 1;
+        }
+    }
+    Some(chapters)
+}
+
+fn mp4_chapters(path: &Path) -> Vec<Chapter> {
+    let mut file = match File::open(path) {
+        Ok(file) => file,
+        Err(_) => return Vec::new(),
+    };
+    let moov = match read_moov(&mut file) {
+        Some(moov) => moov,
+        None => return Vec::new(),
+    };
+    let chapters = nero_chapters(&moov);
+    if !chapters.is_empty() {
+        return chapters;
+    }
+    chapter_track(&mut file, &moov)
+}
+
+// "HH:MM:SS.mmm" as used by CHAPTERxx comments
+fn parse_chapter_time(value: &str) -> Option<u64> {
+    let (clock, fraction) = value.trim().split_once('.').unwrap_or((value.trim(), ""));
+    let parts: Vec<u64> = clock.split(':').map(|p| p.parse().ok()).collect::<Option<_>>()?;
+    let seconds = parts.iter().fold(0, |acc, p| acc * 60 + p);
+    let fraction: String = fraction.chars().chain("000".chars()).take(3).collect();
+    Some(seconds * 1000 + fraction.parse::<u64>().ok()?)
+}
+
+fn vorbis_chapters(path: &Path) -> Vec<Chapter> {
+    let tagged_file = match lofty::read_from_path(path) {
+        Ok(tagged_file) => tagged_file,
+        Err(_) => return Vec::new(),
+    };
+    
+    let mut starts: Vec<(String, u64)> = Vec::new();
+    let mut names: Vec<(String, String)> = Vec::new();
+    for tag in tagged_file.tags() {
+        for item in tag.items() {
+            let key = match item.key() {
+                ItemKey::Unknown(key) => key.to_uppercase(),
+                _ => continue,
+            };
+            let value = match item.value().text() {
+                Some(value) => value,
+                None => continue,
+            };
+            if let Some(number) = key.strip_prefix("CHAPTER") {
+                if let Some(number) = number.strip_suffix("NAME") {
+                    names.push((number.to_string(), value.to_string()));
+                } else if number.chars().all(|c| c.is_ascii_digit()) {
+                    if let Some(start) = parse_chapter_time(value) {
+                        starts.push((number.to_string(), start));
+                    }
+                }
+            }
+        }
+    }
+    
+    let mut chapters: Vec<Chapter> = starts
+        .into_iter()
+        .map(|(number, start_ms)| Chapter {
+            title: names
+                .iter()
+                .find(|(n, _)| *n == number)
+                .map(|(_, name)| name.clone())
+                .unwrap_or_default(),
+            start_ms,
+            end_ms: None,
+        })
+        .collect();
+    chapters.sort_by_key(|c| c.start_ms);
+    chapters
+}
+
+pub fn read_chapters(path: &str) -> Vec<Chapter> {
+    // A book's parts are its chapters
+    if let Some(book) = Some(path).filter(|p| book::is_book(p)).and_then(book::load) {
+        return book
+            .parts
+            .iter()
+            .map(|part| Chapter {
+                title: paths::from_string(&part.path)
+                    .file_stem()
+                    .map(|s| s.to_string_lossy().to_string())
+                    .unwrap_or_default(),
+                start_ms: part.start_ms,
+                end_ms: Some(part.start_ms + part.duration_ms),
+            })
+            .collect();
+    }
+    let path = &paths::from_string(path);
+    let ext = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    
+    let mut chapters = match ext.as_str() {
+        "mp3" => id3_chapters(path),
+        "m4a" | "m4b" | "mp4" => mp4_chapters(path),
+        _ => vorbis_chapters(path),
+    };
+    
+    // Fill in missing ends and titles
+    for i in 0..chapters.len() {
+        let next_start = chapters.get(i + 1).map(|c| c.start_ms);
+        let chapter = &mut chapters[i];
+        if chapter.end_ms.is_none() || chapter.end_ms == Some(u32::MAX as u64) {
+            chapter.end_ms = next_start;
+        }
+        if chapter.title.trim().is_empty() {
+            chapter.title = format!("Chapter {}", i + 1);
+        }
+    }
+    chapters
+}
+
+// Index of the chapter containing the given position
+fn chapter_at(chapters: &[Chapter], elapsed_ms: u64) -> Option<usize> {
+    chapters
+        .partition_point(|c| c.start_ms <= elapsed_ms)
+        .checked_sub(1)
+}
+
+// Chapters for the track at `path`, reading them if another track was loaded before
+pub fn load_for(state: &AppState, path: &str) -> Vec<Chapter> {
+    let mut loaded = state.chapters.lock().unwrap();
+    if let Some(l) = loaded.as_ref().filter(|l| l.path == path) {
+        return l.chapters.clone();
+    }
+    let chapters = read_chapters(path);
+    *loaded = Some(LoadedChapters {
+        path: path.to_string(),
+        chapters: chapters.clone(),
+    });
+    chapters
+}
+
+// (index, title) of the current chapter of the playing track, for get_status
+pub fn current_chapter(state: &AppState) -> Option<(usize, String)> {
+    let path = state.player.get_current_path()?;
+    let chapters = load_for(state, &path);
+    let index = chapter_at(&chapters, state.player.get_elapsed_millis())?;
+    Some((index, chapters[index].title.clone()))
+}
+
+#[tauri::command]
+pub fn get_chapters(path: String, state: State<AppState>) -> Vec<Chapter> {
+    load_for(&state, &path)
+}
+
+fn jump_to_chapter(state: &AppState, target: impl Fn(&[Chapter], Option<usize>, u64) -> Option<usize>) -> Result<ChapterJump, String> {
+    let path = state.player.get_current_path().ok_or("No track is playing")?;
+    let chapters = load_for(state, &path);
+    if chapters.is_empty() {
+        return Err("Track has no chapters".to_string());
+    }
+    
+    let elapsed_ms = state.player.get_elapsed_millis();
+    let index = target(&chapters, chapter_at(&chapters, elapsed_ms), elapsed_ms).ok_or("No more chapters")?;
+    // Seeking works in whole seconds; round up so the position lands inside the chapter
+    let position = chapters[index].start_ms.div_ceil(1000);
+    state.player.send(AudioCommand::Seek(position));
+    
+    Ok(ChapterJump {
+        index,
+        title: chapters[index].title.clone(),
+        position,
+    })
+}
+
+#[tauri::command]
+pub fn next_chapter(state: State<AppState>) -> Result<ChapterJump, String> {
+    jump_to_chapter(&state, |chapters, current, _| {
+        let next = current.map(|i| i + 1).unwrap_or(0);
+        if next < chapters.len() {
+            Some(next)
+        } else {
+            None
+        }
+    })
+}
+
+// Like a CD player: restart the current chapter unless it only just began
+#[tauri::command]
+pub fn prev_chapter(state: State<AppState>) -> Result<ChapterJump, String> {
+    jump_to_chapter(&state, |chapters, current, elapsed_ms| {
+        let current = current?;
+        if elapsed_ms.saturating_sub(chapters[current].start_ms) > 3000 || current == 0 {
+            Some(current)
+        } else {
+            Some(current - 1)
+        }
+    })
+}
+
+#[tauri::command]
+pub fn play_chapter(index: usize, state: State<AppState>) -> Result<ChapterJump, String> {
+    jump_to_chapter(&state, |chapters, _, _| if index < chapters.len() { Some(index) } else { None })
+}
+
+// Emits "chapter-changed" when playback crosses into another chapter
+#[derive(Default)]
+pub struct ChapterWatch {
+    last_sent: Option<(String, Option<usize>)>,
+}
+
+impl ChapterWatch {
+    pub fn tick(&mut self, app: &AppHandle, state: &AppState, path: &str, elapsed_ms: u64) {
+        let (index, title) = if path.is_empty() {
+            (None, None)
+        } else {
+            let chapters = load_for(state, path);
+            let index = chapter_at(&chapters, elapsed_ms);
+            (index, index.map(|i| chapters[i].title.clone()))
+        };
+        
+        let current = (path.to_string(), index);
+        if self.last_sent.as_ref() != Some(&current) {
+            let _ = app.emit_all("chapter-changed", ChapterPayload {
+                path: current.0.clone(),
+                index,
+                title,
+            });
+            self.last_sent = Some(current);
+        }
+    }
+}
@@ -0,0 +1,169 @@
+// Cover art lookup and thumbnail cache
+
+use lofty::{PictureType, TaggedFileExt};
+use std::collections::hash_map::DefaultHasher;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use tauri::http::{Request, Response, ResponseBuilder};
+use tauri::{AppHandle, State};
+
+use crate::book;
+use crate::cue;
+use crate::paths;
+use crate::AppState;
+
+const THUMBNAIL_SIZE: u32 = 300;
+
+// Preferred folder image names, checked in order with any image extension
+const FOLDER_IMAGE_NAMES: [&str; 4] = ["folder", "cover", "front", "album"];
+const IMAGE_EXTENSIONS: [&str; 3] = ["jpg", "jpeg", "png"];
+
+fn get_covers_dir() -> Option<PathBuf> {
+    dirs::cache_dir().map(|p| p.join("vi-music").join("covers"))
+}
+
+// Front cover if tagged as such, otherwise the first embedded picture
+fn embedded_picture(path: &Path) -> Option<Vec<u8>> {
+    let tagged_file = lofty::read_from_path(path).ok()?;
+    let mut fallback = None;
+    for tag in tagged_file.tags() {
+        for picture in tag.pictures() {
+            if picture.pic_type() == PictureType::CoverFront {
+                return Some(picture.data().to_vec());
+            }
+            if fallback.is_none() {
+                fallback = Some(picture.data().to_vec());
+            }
+        }
+    }
+    fallback
+}
+
+fn folder_image(path: &Path) -> Option<PathBuf> {
+    let dir = path.parent()?;
+    let images: Vec<PathBuf> = fs::read_dir(dir)
+        .ok()?
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| {
+            p.extension()
+                .map(|e| IMAGE_EXTENSIONS.contains(&e.to_string_lossy().to_lowercase().as_str()))
+                .unwrap_or(false)
+        })
+        .collect();
+    
+    FOLDER_IMAGE_NAMES.iter().find_map(|name| {
+        images
+            .iter()
+            .find(|p| {
+                p.file_stem()
+                    .map(|s| s.to_string_lossy().to_lowercase() == *name)
+                    .unwrap_or(false)
+            })
+            .cloned()
+    })
+}
+
+fn write_thumbnail(data: &[u8], target: &Path) -> Result<(), String> {
+    let image = image::load_from_memory(data).map_err(|e| e.to_string())?;
+    let thumbnail = image.thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE).to_rgb8();
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    thumbnail
+        .save_with_format(target, image::ImageFormat::Jpeg)
+        .map_err(|e| e.to_string())
+}
+
+// Cached thumbnail for a track, creating it on first use.
+// Thumbnails are keyed by image content (embedded) or image path and mtime (folder),
+// so every track of an album shares one file.
+pub fn cover_thumbnail(path: &Path) -> Option<PathBuf> {
+    let covers_dir = get_covers_dir()?;
+    let mut hasher = DefaultHasher::new();
+    
+    let data = match embedded_picture(path) {
+        Some(data) => {
+            data.hash(&mut hasher);
+            data
+        }
+        None => {
+            let image_path = folder_image(path)?;
+            let modified = fs::metadata(&image_path).and_then(|m| m.modified()).ok();
+            image_path.hash(&mut hasher);
+            modified.hash(&mut hasher);
+            
+            let target = covers_dir.join(format!("{:016x}.jpg", hasher.finish()));
+            if target.exists() {
+                return Some(target);
+            }
+            fs::read(&image_path).ok()?
+        }
+    };
+    
+    let target = covers_dir.join(format!("{:016x}.jpg", hasher.finish()));
+    if !target.exists() {
+        write_thumbnail(&data, &target).ok()?;
+    }
+    Some(target)
+}
+
+// file:// URL understood by the platform media controls
+pub fn file_url(path: &Path) -> String {
+    let path = path.to_string_lossy().replace('\\', "/");
+    if path.starts_with('/') {
+        format!("file://{}", path)
+    } else {
+        format!("file:///{}", path)
+    }
+}
+
+// Returns the thumbnail file name, to be loaded through the cover:// protocol
+#[tauri::command]
+pub async fn get_cover_art(path: String, state: State<'_, AppState>) -> Result<Option<String>, String> {
+    let file = if book::is_book(&path) {
+        book::first_part(&path).unwrap_or_default()
+    } else {
+        cue::backing_file(&path)
+    };
+    let thumbnail = match cover_thumbnail(&paths::from_string(&file)) {
+        Some(t) => t,
+        None => return Ok(None),
+    };
+    
+    // Media controls get the cover once it exists, if the track is still playing
+    let current_path = {
+        let playlist = state.playlist.lock().unwrap();
+        playlist.get(*state.current_index.lock().unwrap()).cloned()
+    };
+    if current_path.as_deref() == Some(path.as_str()) {
+        state.update_media_cover(&file_url(&thumbnail));
+    }
+    
+    Ok(thumbnail.file_name().map(|n| n.to_string_lossy().to_string()))
+}
+
+// Serve cached thumbnails to the webview; only bare file names inside the cache are accepted
+pub fn handle_cover_protocol(_app: &AppHandle, request: &Request) -> Result<Response, Box<dyn std::error::Error>> {
+    let name = request
+        .uri()
+        .split(['?', '#'])
+        .next()
+        .and_then(|uri| uri.rsplit('/').next())
+        .unwrap_or("");
+    let valid = name.ends_with(".jpg")
+        && name.trim_end_matches(".jpg").chars().all(|c| c.is_ascii_hexdigit());
+    
+    let data = match get_covers_dir() {
+        Some(dir) if valid => fs::read(dir.join(name)).ok(),
+        _ => None,
+    };
+    match data {
+        Some(data) => ResponseBuilder::new()
+            .mimetype("image/jpeg")
+            .header("Cache-Control", "max-age=31536000, immutable")
+            .body(data),
+        None => ResponseBuilder::new().status(404).body(Vec::new()),
+    }
+}
@@ -0,0 +1,277 @@
+// CUE sheets: single-file album images split into virtual tracks.
+// A virtual track is addressed as "<sheet>#NN", where the sheet is a .cue file
+// or an audio file carrying an embedded CUESHEET tag, and NN is the track number.
+
+use lofty::{ItemKey, TaggedFileExt};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::formats;
+use crate::lyrics;
+use crate::paths;
+
+// Cue times are mm:ss:ff with 75 frames per second
+const FRAMES_PER_SECOND: u64 = 75;
+
+pub struct CueTrack {
+    pub number: u32,
+    pub title: Option<String>,
+    pub performer: Option<String>,
+    pub file: PathBuf,
+    pub start_ms: u64,
+    pub end_ms: Option<u64>, // None for the last track of its file
+}
+
+pub struct CueSheet {
+    pub title: Option<String>,
+    pub performer: Option<String>,
+    pub tracks: Vec<CueTrack>,
+}
+
+// The part of an audio file a track plays
+#[derive(Clone, PartialEq)]
+pub struct Segment {
+    pub file: String,
+    pub start_ms: u64,
+    pub end_ms: Option<u64>,
+}
+
+impl Segment {
+    // Whether `next` starts exactly where this segment ends, in the same file
+    pub fn continues_into(&self, next: &Segment) -> bool {
+        self.file == next.file && self.end_ms == Some(next.start_ms)
+    }
+}
+
+pub fn is_cue_file(path: &Path) -> bool {
+    path.extension()
+        .map(|e| e.to_string_lossy().eq_ignore_ascii_case("cue"))
+        .unwrap_or(false)
+}
+
+// "mm:ss:ff" -> milliseconds
+fn parse_time(s: &str) -> Option<u64> {
+    let mut parts = s.trim().split(':');
+    let minutes: u64 = parts.next()?.parse().ok()?;
+    let seconds: u64 = parts.next()?.parse().ok()?;
+    let frames: u64 = parts.next()?.parse().ok()?;
+    Some(minutes * 60_000 + seconds * 1000 + frames * 1000 / FRAMES_PER_SECOND)
+}
+
+// Quoted or bare value of a command line
+fn unquote(s: &str) -> String {
+    let s = s.trim();
+    match s.strip_prefix('"') {
+        Some(rest) => rest.split('"').next().unwrap_or(rest).to_string(),
+        None => s.to_string(),
+    }
+}
+
+// FILE "name" TYPE, where the name itself may contain spaces
+fn file_name(args: &str) -> String {
+    let args = args.trim();
+    if args.starts_with('"') {
+        unquote(args)
+    } else {
+        args.rsplit_once(' ').map(|(name, _)| name).unwrap_or(args).to_string()
+    }
+}
+
+// The file named in the sheet, or an audio file with the same stem when the rip was
+// re-encoded without updating the sheet (e.g. FILE "Album.wav" next to Album.flac)
+fn resolve_file(dir: &Path, name: &str) -> PathBuf {
+    let named = dir.join(name);
+    if named.exists() {
+        return named;
+    }
+    let stem = Path::new(name).file_stem().map(|s| s.to_os_string());
+    fs::read_dir(dir)
+        .ok()
+        .and_then(|entries| {
+            entries
+                .filter_map(|e| e.ok())
+                .map(|e| e.path())
+                .find(|p| p.file_stem().map(|s| s.to_os_string()) == stem && crate::is_audio_file(p))
+        })
+        .unwrap_or(named)
+}
+
+// Parse a sheet. `image` overrides FILE lines for sheets embedded in the audio file.
+fn parse_cue(content: &str, dir: &Path, image: Option<&Path>) -> CueSheet {
+    let mut sheet = CueSheet { title: None, performer: None, tracks: Vec::new() };
+    let mut file = image.map(|p| p.to_path_buf());
+    let mut current: Option<CueTrack> = None;
+    
+    for line in content.lines() {
+        let line = line.trim();
+        let (command, args) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        match command.to_uppercase().as_str() {
+            "FILE" if image.is_none() => file = Some(resolve_file(dir, &file_name(args))),
+            "TRACK" => {
+                sheet.tracks.extend(current.take());
+                let number = args.split_whitespace().next().and_then(|n| n.parse().ok());
+                if let (Some(number), Some(file)) = (number, file.clone()) {
+                    current = Some(CueTrack {
+                        number,
+                        title: None,
+                        performer: None,
+                        file,
+                        start_ms: 0,
+                        end_ms: None,
+                    });
+                }
+            }
+            "TITLE" => match current.as_mut() {
+                Some(track) => track.title = Some(unquote(args)),
+                None => sheet.title = Some(unquote(args)),
+            },
+            "PERFORMER" => match current.as_mut() {
+                Some(track) => track.performer = Some(unquote(args)),
+                None => sheet.performer = Some(unquote(args)),
+            },
+            "INDEX" => {
+                let mut parts = args.split_whitespace();
+                if let (Some("01"), Some(time), Some(track)) = (parts.next(), parts.next(), current.as_mut()) {
+                    track.start_ms = parse_time(time).unwrap_or(0);
+                }
+            }
+            _ => {}
+        }
+    }
+    sheet.tracks.extend(current);
+    
+    // A track ends where the next one in the same file starts, so pregaps stay
+    // with the previous track and consecutive tracks join without a gap
+    for i in 1..sheet.tracks.len() {
+        if sheet.tracks[i].file == sheet.tracks[i - 1].file {
+            sheet.tracks[i - 1].end_ms = Some(sheet.tracks[i].start_ms);
+        }
+    }
+    sheet
+}
+
+fn read_cue(path: &Path) -> Option<CueSheet> {
+    let content = lyrics::read_text(path)?;
+    let sheet = parse_cue(&content, path.parent()?, None);
+    if sheet.tracks.is_empty() {
+        None
+    } else {
+        Some(sheet)
+    }
+}
+
+fn embedded_cue(path: &Path) -> Option<CueSheet> {
+    if !formats::lookup(path)?.embedded_cue {
+        return None;
+    }
+    let tagged_file = lofty::read_from_path(path).ok()?;
+    let key = ItemKey::Unknown("CUESHEET".to_string());
+    let content = tagged_file.tags().iter().find_map(|tag| tag.get_string(&key))?;
+    let sheet = parse_cue(content, path.parent()?, Some(path));
+    if sheet.tracks.is_empty() {
+        None
+    } else {
+        Some(sheet)
+    }
+}
+
+fn virtual_path(sheet: &Path, number: u32) -> String {
+    format!("{}#{:02}", paths::to_string(sheet), number)
+}
+
+// Split "<sheet>#NN" into the sheet path and track number
+fn split_virtual(path: &str) -> Option<(PathBuf, u32)> {
+    let (base, number) = path.rsplit_once('#')?;
+    if number.is_empty() || !number.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let base = paths::from_string(base);
+    if !(is_cue_file(&base) || crate::is_audio_file(&base)) {
+        return None;
+    }
+    Some((base, number.parse().ok()?))
+}
+
+// The sheet and track a virtual path points to
+pub fn lookup(path: &str) -> Option<(CueSheet, usize)> {
+    let (base, number) = split_virtual(path)?;
+    let sheet = if is_cue_file(&base) { read_cue(&base)? } else { embedded_cue(&base)? };
+    let index = sheet.tracks.iter().position(|t| t.number == number)?;
+    Some((sheet, index))
+}
+
+pub fn segment(path: &str) -> Option<Segment> {
+    let (sheet, index) = lookup(path)?;
+    let track = &sheet.tracks[index];
+    Some(Segment {
+        file: paths::to_string(&track.file),
+        start_ms: track.start_ms,
+        end_ms: track.end_ms,
+    })
+}
+
+// "NN. Title" for display, in place of the file name
+pub fn track_name(path: &str) -> Option<String> {
+    let (sheet, index) = lookup(path)?;
+    let track = &sheet.tracks[index];
+    Some(match &track.title {
+        Some(title) => format!("{:02}. {}", track.number, title),
+        None => format!("Track {:02}", track.number),
+    })
+}
+
+// The file on disk behind a path: the audio image for virtual tracks, otherwise the path itself
+pub fn backing_file(path: &str) -> String {
+    segment(path).map(|s| s.file).unwrap_or_else(|| path.to_string())
+}
+
+// The file a virtual track is listed from: its .cue file, or the audio file embedding the sheet
+pub fn sheet_file(path: &str) -> Option<String> {
+    split_virtual(path).map(|(base, _)| paths::to_string(&base))
+}
+
+pub fn exists(path: &str) -> bool {
+    paths::from_string(&backing_file(path)).exists()
+}
+
+// Virtual tracks of a .cue file, for playlists that list the sheet itself
+pub fn sheet_tracks(sheet: &Path) -> Vec<String> {
+    read_cue(sheet)
+        .map(|s| s.tracks.iter().map(|t| virtual_path(sheet, t.number)).collect())
+        .unwrap_or_default()
+}
+
+// Replace audio images described by a cue sheet with their virtual tracks.
+// Takes a sorted listing of audio and .cue files; the sheets themselves are dropped.
+pub fn expand(paths: Vec<String>) -> Vec<String> {
+    let mut by_file: HashMap<PathBuf, Vec<String>> = HashMap::new();
+    for path in paths.iter().map(|p| paths::from_string(p)).filter(|p| is_cue_file(p)) {
+        if let Some(sheet) = read_cue(&path) {
+            let mut tracks: HashMap<&PathBuf, Vec<String>> = HashMap::new();
+            for track in &sheet.tracks {
+                tracks.entry(&track.file).or_default().push(virtual_path(&path, track.number));
+            }
+            // When two sheets describe the same image, the first one wins
+            for (file, entries) in tracks {
+                by_file.entry(file.clone()).or_insert(entries);
+            }
+        }
+    }
+    
+    let mut expanded = Vec::new();
+    for path in paths {
+        let file = paths::from_string(&path);
+        if is_cue_file(&file) {
+            continue;
+        }
+        if let Some(entries) = by_file.remove(&file) {
+            expanded.extend(entries);
+        } else if let Some(sheet) = embedded_cue(&file) {
+            expanded.extend(sheet.tracks.iter().map(|t| virtual_path(&file, t.number)));
+        } else {
+            expanded.push(path);
+        }
+    }
+    expanded
+}
@@ -0,0 +1,102 @@
+// Decoding tracks to mono samples for analysis, separate from the playback thread
+
+use rodio::{Decoder, Source};
+use std::fs::File;
+use std::io::BufReader;
+use std::time::Duration;
+
+use crate::{cue, formats, paths, SymphoniaSource};
+
+pub struct Samples {
+    pub data: Vec<f32>, // Mono, -1.0..1.0
+    pub sample_rate: u32,
+}
+
+impl Samples {
+    // Box-filtered downsampling to `rate`, enough for analysis that looks below a few kHz
+    pub fn resampled(&self, rate: u32) -> Vec<f32> {
+        let ratio = self.sample_rate as f64 / rate as f64;
+        if ratio <= 1.0 {
+            return self.data.clone();
+        }
+        let count = (self.data.len() as f64 / ratio) as usize;
+        (0..count)
+            .map(|i| {
+                let start = (i as f64 * ratio) as usize;
+                let end = (((i + 1) as f64 * ratio) as usize).clamp(start + 1, self.data.len());
+                self.data[start..end].iter().sum::<f32>() / (end - start) as f32
+            })
+            .collect()
+    }
+}
+
+// Mono frames of a track as they decode, so long files need not fit in memory
+pub struct MonoFrames {
+    source: Box<dyn Source<Item = i16> + Send>,
+    channels: usize,
+    remaining: Option<usize>,
+    pub sample_rate: u32,
+}
+
+impl Iterator for MonoFrames {
+    type Item = f32;
+    
+    // Average of the channels of the next frame
+    fn next(&mut self) -> Option<f32> {
+        if self.remaining == Some(0) {
+            return None;
+        }
+        let mut sum = 0.0;
+        for _ in 0..self.channels {
+            sum += self.source.next()? as f32 / 32768.0;
+        }
+        if let Some(remaining) = self.remaining.as_mut() {
+            *remaining -= 1;
+        }
+        Some(sum / self.channels as f32)
+    }
+}
+
+fn mono_frames(source: Box<dyn Source<Item = i16> + Send>, limit_ms: Option<u64>) -> MonoFrames {
+    let sample_rate = source.sample_rate();
+    MonoFrames {
+        channels: source.channels().max(1) as usize,
+        remaining: limit_ms.map(|ms| (ms * sample_rate as u64 / 1000) as usize),
+        sample_rate,
+        source,
+    }
+}
+
+// Mono frames of a track or cue sheet track, the first `limit_ms` only when given
+pub fn stream_mono(path: &str, limit_ms: Option<u64>) -> Option<MonoFrames> {
+    let (file, seek_ms, limit_ms) = match cue::segment(path) {
+        Some(segment) => {
+            let length = segment.end_ms.map(|end| end.saturating_sub(segment.start_ms));
+            let limit = match (limit_ms, length) {
+                (Some(limit), Some(length)) => Some(limit.min(length)),
+                (limit, length) => limit.or(length),
+            };
+            (segment.file, segment.start_ms, limit)
+        }
+        None => (path.to_string(), 0, limit_ms),
+    };
+    
+    match formats::lookup(&paths::from_string(&file))?.backend {
+        formats::Backend::Symphonia => Some(mono_frames(Box::new(SymphoniaSource::new(&file, seek_ms)?), limit_ms)),
+        formats::Backend::Rodio => {
+            let reader = BufReader::new(File::open(paths::from_string(&file)).ok()?);
+            let mut source = Decoder::new(reader).ok()?;
+            if seek_ms > 0 {
+                source.try_seek(Duration::from_millis(seek_ms)).ok()?;
+            }
+            Some(mono_frames(Box::new(source), limit_ms))
+        }
+    }
+}
+
+// Mono samples of a track or cue sheet track, the first `limit_ms` only when given
+pub fn decode_mono(path: &str, limit_ms: Option<u64>) -> Option<Samples> {
+    let frames = stream_mono(path, limit_ms)?;
+    let sample_rate = frames.sample_rate;
+    Some(Samples { data: frames.collect(), sample_rate })
+}
@@ -0,0 +1,327 @@
+// Finding duplicate tracks in the library and trashing the extra copies
+
+use lofty::{Accessor, AudioFile, TaggedFileExt};
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::hash::Hasher;
+use std::io::Read;
+use tauri::{AppHandle, Manager, State};
+
+use crate::relocate::rewrite_track_paths;
+use crate::{cue, fingerprint, formats, paths, sortkey, update_metadata_cache, AppState};
+
+// Durations within this many seconds count as the same recording
+pub const DURATION_TOLERANCE: u64 = 2;
+
+#[derive(Serialize, Clone)]
+pub struct DuplicateCopy {
+    path: String,
+    format: String,
+    lossless: bool,
+    bitrate: Option<u32>, // kbps
+    sample_rate: Option<u32>,
+    bit_depth: Option<u8>,
+    size: u64,
+    duration: Option<u64>,
+}
+
+#[derive(Serialize)]
+pub struct DuplicateGroup {
+    reason: String, // "identical", "tags" or "audio"
+    copies: Vec<DuplicateCopy>,
+    keep: usize, // Index of the best copy
+}
+
+#[derive(Deserialize)]
+pub struct DuplicateChoice {
+    keep: String,
+    remove: Vec<String>,
+}
+
+#[derive(Serialize)]
+pub struct DuplicateCleanup {
+    trashed: Vec<String>,
+    failed: Vec<String>,
+    playlists_updated: usize,
+}
+
+struct Candidate {
+    path: String,
+    size: u64,
+    duration: Option<u64>,
+    format: &'static str,
+}
+
+fn quality(candidate: &Candidate) -> DuplicateCopy {
+    let mut copy = DuplicateCopy {
+        path: candidate.path.clone(),
+        format: candidate.format.to_string(),
+        lossless: false,
+        bitrate: None,
+        sample_rate: None,
+        bit_depth: None,
+        size: candidate.size,
+        duration: candidate.duration,
+    };
+    if let Ok(tagged_file) = lofty::read_from_path(paths::from_string(&candidate.path)) {
+        let properties = tagged_file.properties();
+        copy.bitrate = properties.audio_bitrate().or(properties.overall_bitrate());
+        copy.sample_rate = properties.sample_rate();
+        copy.bit_depth = properties.bit_depth();
+        // Only PCM-based formats report a bit depth
+        copy.lossless = copy.bit_depth.is_some();
+    }
+    copy
+}
+
+fn group(reason: &str, members: &[usize], candidates: &[Candidate]) -> DuplicateGroup {
+    let copies: Vec<DuplicateCopy> = members.iter().map(|&i| quality(&candidates[i])).collect();
+    let keep = copies
+        .iter()
+        .enumerate()
+        .max_by_key(|(_, c)| (c.lossless, c.bitrate.unwrap_or(0), c.sample_rate.unwrap_or(0), c.bit_depth.unwrap_or(0), c.size))
+        .map(|(i, _)| i)
+        .unwrap_or(0);
+    DuplicateGroup { reason: reason.to_string(), copies, keep }
+}
+
+fn content_hash(path: &str) -> Option<u64> {
+    let mut file = File::open(paths::from_string(path)).ok()?;
+    let mut hasher = DefaultHasher::new();
+    let mut buffer = vec![0u8; 64 * 1024];
+    loop {
+        let read = file.read(&mut buffer).ok()?;
+        if read == 0 {
+            break;
+        }
+        hasher.write(&buffer[..read]);
+    }
+    Some(hasher.finish())
+}
+
+// Same bytes on disk. Only files of equal size are read.
+fn identical_groups(candidates: &[Candidate]) -> Vec<Vec<usize>> {
+    let mut by_size: HashMap<u64, Vec<usize>> = HashMap::new();
+    for (i, candidate) in candidates.iter().enumerate() {
+        by_size.entry(candidate.size).or_default().push(i);
+    }
+    
+    let mut groups = Vec::new();
+    for members in by_size.into_values().filter(|m| m.len() > 1) {
+        let mut by_hash: HashMap<u64, Vec<usize>> = HashMap::new();
+        for i in members {
+            if let Some(hash) = content_hash(&candidates[i].path) {
+                by_hash.entry(hash).or_default().push(i);
+            }
+        }
+        groups.extend(by_hash.into_values().filter(|m| m.len() > 1));
+    }
+    groups
+}
+
+// Artist and title folded for comparison: case, accents and punctuation ignored
+pub fn normalize(s: &str) -> String {
+    sortkey::fold(s)
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn tag_key(path: &str) -> Option<String> {
+    let tagged_file = lofty::read_from_path(paths::from_string(path)).ok()?;
+    let tag = tagged_file.primary_tag().or_else(|| tagged_file.first_tag())?;
+    let artist = normalize(&tag.artist()?);
+    let title = normalize(&tag.title()?);
+    if artist.is_empty() || title.is_empty() {
+        return None;
+    }
+    Some(format!("{}\n{}", artist, title))
+}
+
+// Runs of members whose durations are each within the tolerance of the previous one
+fn split_by_duration(mut members: Vec<usize>, candidates: &[Candidate]) -> Vec<Vec<usize>> {
+    members.sort_by_key(|&i| candidates[i].duration);
+    let mut runs: Vec<Vec<usize>> = Vec::new();
+    let mut previous: Option<u64> = None;
+    for i in members {
+        let duration = match candidates[i].duration {
+            Some(d) => d,
+            None => continue,
+        };
+        match (runs.last_mut(), previous) {
+            (Some(run), Some(p)) if duration - p <= DURATION_TOLERANCE => run.push(i),
+            _ => runs.push(vec![i]),
+        }
+        previous = Some(duration);
+    }
+    runs.into_iter().filter(|r| r.len() > 1).collect()
+}
+
+// Same normalized artist and title with about the same length
+fn tag_groups(candidates: &[Candidate]) -> Vec<Vec<usize>> {
+    let mut by_key: HashMap<String, Vec<usize>> = HashMap::new();
+    for (i, candidate) in candidates.iter().enumerate() {
+        if let Some(key) = tag_key(&candidate.path) {
+            by_key.entry(key).or_default().push(i);
+        }
+    }
+    by_key
+        .into_values()
+        .filter(|m| m.len() > 1)
+        .flat_map(|members| split_by_duration(members, candidates))
+        .collect()
+}
+
+fn find_root(parents: &mut [usize], i: usize) -> usize {
+    let mut root = i;
+    while parents[root] != root {
+        root = parents[root];
+    }
+    parents[i] = root;
+    root
+}
+
+// The same recording, compared by acoustic fingerprint. Only tracks of about the same
+// length that no other strategy grouped together are fingerprinted.
+fn audio_groups(state: &AppState, candidates: &[Candidate], known: &[Vec<usize>]) -> Vec<Vec<usize>> {
+    let already_grouped: HashSet<(usize, usize)> = known
+        .iter()
+        .flat_map(|g| g.iter().flat_map(move |&a| g.iter().map(move |&b| (a, b))))
+        .collect();
+    
+    let mut order: Vec<usize> = (0..candidates.len()).filter(|&i| candidates[i].duration.is_some()).collect();
+    order.sort_by_key(|&i| candidates[i].duration);
+    
+    let mut pairs = Vec::new();
+    for (n, &a) in order.iter().enumerate() {
+        for &b in &order[n + 1..] {
+            let (da, db) = (candidates[a].duration.unwrap_or(0), candidates[b].duration.unwrap_or(0));
+            if db - da > DURATION_TOLERANCE {
+                break;
+            }
+            if !already_grouped.contains(&(a, b)) {
+                pairs.push((a, b));
+            }
+        }
+    }
+    
+    let needed: Vec<String> = pairs
+        .iter()
+        .flat_map(|&(a, b)| [a, b])
+        .collect::<HashSet<usize>>()
+        .into_iter()
+        .map(|i| candidates[i].path.clone())
+        .collect();
+    let fingerprints: HashMap<String, Vec<u32>> = fingerprint::ensure(state, &needed)
+        .into_iter()
+        .filter_map(|(path, f)| Some((path, fingerprint::raw(&f)?)))
+        .collect();
+    
+    let mut parents: Vec<usize> = (0..candidates.len()).collect();
+    for (a, b) in pairs {
+        if let (Some(fa), Some(fb)) = (fingerprints.get(&candidates[a].path), fingerprints.get(&candidates[b].path)) {
+            if fingerprint::similarity(fa, fb) >= fingerprint::MATCH_THRESHOLD {
+                let (ra, rb) = (find_root(&mut parents, a), find_root(&mut parents, b));
+                parents[rb] = ra;
+            }
+        }
+    }
+    
+    let mut groups: HashMap<usize, Vec<usize>> = HashMap::new();
+    for &i in &order {
+        let root = find_root(&mut parents, i);
+        groups.entry(root).or_default().push(i);
+    }
+    groups.into_values().filter(|m| m.len() > 1).collect()
+}
+
+// Every track in the library folders
+pub fn library_paths() -> Vec<String> {
+    let mut tracks = Vec::new();
+    for folder in crate::get_library_folders().unwrap_or_default() {
+        crate::scan_folder_recursive(&paths::from_string(&folder), &mut tracks);
+    }
+    tracks.into_iter().map(|t| t.path).collect()
+}
+
+// Group likely duplicates among `paths`, or the whole library when empty
+#[tauri::command]
+pub async fn find_duplicates(paths: Vec<String>, app: AppHandle) -> Result<Vec<DuplicateGroup>, String> {
+    tauri::async_runtime::spawn_blocking(move || Ok(group_duplicates(&app.state::<AppState>(), paths)))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+fn group_duplicates(state: &AppState, paths: Vec<String>) -> Vec<DuplicateGroup> {
+    let paths = if paths.is_empty() { library_paths() } else { paths };
+    
+    // Cue sheet tracks share their image file, so only real files are compared
+    let mut seen = HashSet::new();
+    let candidates: Vec<Candidate> = paths
+        .into_iter()
+        .filter(|p| cue::segment(p).is_none() && seen.insert(p.clone()))
+        .filter_map(|path| {
+            let file = paths::from_string(&path);
+            let format = formats::lookup(&file)?.name;
+            let size = fs::metadata(&file).ok()?.len();
+            let duration = crate::get_audio_duration(&path);
+            Some(Candidate { path, size, duration, format })
+        })
+        .collect();
+    
+    let identical = identical_groups(&candidates);
+    let tags = tag_groups(&candidates);
+    let known: Vec<Vec<usize>> = identical.iter().chain(&tags).cloned().collect();
+    let audio = audio_groups(state, &candidates, &known);
+    
+    // A group adds nothing when an earlier one already holds all of its tracks
+    let mut groups: Vec<DuplicateGroup> = Vec::new();
+    let mut reported: Vec<HashSet<usize>> = Vec::new();
+    let strategies = [("identical", identical), ("tags", tags), ("audio", audio)];
+    for (reason, found) in strategies {
+        for members in found {
+            let set: HashSet<usize> = members.iter().copied().collect();
+            if reported.iter().any(|r| set.is_subset(r)) {
+                continue;
+            }
+            groups.push(group(reason, &members, &candidates));
+            reported.push(set);
+        }
+    }
+    groups
+}
+
+// Send the extra copies to the trash and point playlists at the copy that was kept
+#[tauri::command]
+pub fn trash_duplicates(choices: Vec<DuplicateChoice>, state: State<AppState>) -> Result<DuplicateCleanup, String> {
+    let mut moves = HashMap::new();
+    let mut failed = Vec::new();
+    for choice in &choices {
+        for path in choice.remove.iter().filter(|p| **p != choice.keep) {
+            match trash::delete(paths::from_string(path)) {
+                Ok(()) => {
+                    moves.insert(path.clone(), choice.keep.clone());
+                }
+                Err(e) => failed.push(format!("{}: {}", path, e)),
+            }
+        }
+    }
+    
+    // Trashed copies' cache entries go first, so the rewrite below can't turn them into
+    // second entries for the kept files
+    update_metadata_cache(&state, |cache| {
+        let count = cache.tracks.len();
+        cache.tracks.retain(|meta| !moves.contains_key(&meta.path));
+        cache.tracks.len() != count
+    })?;
+    let playlists_updated = rewrite_track_paths(&moves, &state)?;
+    
+    Ok(DuplicateCleanup {
+        trashed: moves.into_keys().collect(),
+        failed,
+        playlists_updated,
+    })
+}
@@ -0,0 +1,464 @@
+// Acoustic fingerprints in Chromaprint's format (algorithm 2, the fpcalc default): chroma
+// features of 11025 Hz audio turned into 32-bit sub-fingerprints by 16 fixed classifiers.
+// Stored compressed and base64-encoded, exactly as AcoustID expects them.
+
+use rustfft::num_complex::Complex;
+use rustfft::FftPlanner;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fs;
+use tauri::{AppHandle, Manager};
+
+use crate::decode::{self, Samples};
+use crate::{cache_missing_tracks, load_metadata_cache, paths, track_name, update_metadata_cache, AppState};
+
+const SAMPLE_RATE: usize = 11025;
+const FRAME_SIZE: usize = 4096;
+const HOP: usize = FRAME_SIZE / 3;
+const MIN_FREQ: f64 = 28.0;
+const MAX_FREQ: f64 = 3520.0;
+const BANDS: usize = 12;
+// Like fpcalc, only the first two minutes are fingerprinted
+const LENGTH_MS: u64 = 120_000;
+const ALGORITHM: u8 = 1; // Chromaprint's id for algorithm 2
+const CHROMA_FILTER: [f64; 5] = [0.25, 0.75, 1.0, 0.75, 0.25];
+
+// Sub-fingerprints of offset tried when lining two fingerprints up, about 10 s
+const MAX_OFFSET: usize = 80;
+// Share of equal bits above which two fingerprints are the same recording; unrelated audio sits near 0.5
+pub const MATCH_THRESHOLD: f32 = 0.75;
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Fingerprint {
+    pub duration: u64, // Seconds, of the whole track
+    pub value: String,
+}
+
+struct Classifier {
+    filter: u8,
+    y: usize,
+    height: usize,
+    width: usize,
+    thresholds: [f64; 3],
+}
+
+const fn classifier(filter: u8, y: usize, height: usize, width: usize, thresholds: [f64; 3]) -> Classifier {
+    Classifier { filter, y, height, width, thresholds }
+}
+
+const CLASSIFIERS: [Classifier; 16] = [
+    classifier(0, 4, 3, 15, [1.98215, 2.35817, 2.63523]),
+    classifier(4, 4, 6, 15, [-1.03809, -0.651211, -0.282167]),
+    classifier(1, 0, 4, 16, [-0.298702, 0.119262, 0.558497]),
+    classifier(3, 8, 2, 12, [-0.105439, 0.0153946, 0.135898]),
+    classifier(3, 4, 4, 8, [-0.142891, 0.0258736, 0.200632]),
+    classifier(4, 0, 3, 5, [-0.826319, -0.590612, -0.368214]),
+    classifier(1, 2, 2, 9, [-0.557409, -0.233035, 0.0534525]),
+    classifier(2, 7, 3, 4, [-0.0646826, 0.00620476, 0.0784847]),
+    classifier(2, 6, 2, 16, [-0.192387, -0.029699, 0.215855]),
+    classifier(2, 1, 3, 2, [-0.0397818, -0.00568076, 0.0292026]),
+    classifier(5, 10, 1, 15, [-0.53823, -0.369934, -0.190235]),
+    classifier(3, 6, 2, 10, [-0.124877, 0.0296483, 0.139239]),
+    classifier(2, 1, 1, 14, [-0.101475, 0.0225617, 0.231971]),
+    classifier(3, 5, 6, 4, [-0.0799915, -0.00729616, 0.063262]),
+    classifier(1, 9, 2, 12, [-0.272556, 0.019424, 0.302559]),
+    classifier(3, 4, 2, 14, [-0.164292, -0.0321188, 0.08463]),
+];
+
+const GRAY_CODE: [u32; 4] = [0, 1, 3, 2];
+
+// Chroma band of each FFT bin between MIN_FREQ and MAX_FREQ
+fn chroma_notes() -> (usize, Vec<usize>) {
+    let bin = |freq: f64| (FRAME_SIZE as f64 * freq / SAMPLE_RATE as f64).round() as usize;
+    let min_index = bin(MIN_FREQ).max(1);
+    let max_index = bin(MAX_FREQ).min(FRAME_SIZE / 2);
+    let notes = (min_index..max_index)
+        .map(|i| {
+            let freq = i as f64 * SAMPLE_RATE as f64 / FRAME_SIZE as f64;
+            let octave = (freq / (440.0 / 16.0)).log2();
+            (BANDS as f64 * (octave - octave.floor())) as usize
+        })
+        .collect();
+    (min_index, notes)
+}
+
+// Smoothed, normalized chroma vector per frame
+fn chroma_image(samples: &[f32]) -> Vec<[f64; BANDS]> {
+    let window: Vec<f32> = (0..FRAME_SIZE)
+        .map(|i| 0.54 - 0.46 * (2.0 * std::f32::consts::PI * i as f32 / (FRAME_SIZE - 1) as f32).cos())
+        .collect();
+    let fft = FftPlanner::<f32>::new().plan_fft_forward(FRAME_SIZE);
+    let (min_index, notes) = chroma_notes();
+    
+    let mut recent: VecDeque<[f64; BANDS]> = VecDeque::new();
+    let mut image = Vec::new();
+    let mut buffer = vec![Complex::new(0.0, 0.0); FRAME_SIZE];
+    let mut start = 0;
+    while start + FRAME_SIZE <= samples.len() {
+        for (slot, (sample, w)) in buffer.iter_mut().zip(samples[start..].iter().zip(&window)) {
+            *slot = Complex::new(sample * w, 0.0);
+        }
+        fft.process(&mut buffer);
+        start += HOP;
+        
+        let mut chroma = [0.0; BANDS];
+        for (offset, &note) in notes.iter().enumerate() {
+            chroma[note] += buffer[min_index + offset].norm_sqr() as f64;
+        }
+        recent.push_back(chroma);
+        if recent.len() > CHROMA_FILTER.len() {
+            recent.pop_front();
+        }
+        if recent.len() < CHROMA_FILTER.len() {
+            continue;
+        }
+        
+        let mut filtered = [0.0; BANDS];
+        for (coefficient, row) in CHROMA_FILTER.iter().zip(&recent) {
+            for band in 0..BANDS {
+                filtered[band] += coefficient * row[band];
+            }
+        }
+        let norm = filtered.iter().map(|v| v * v).sum::<f64>().sqrt();
+        if norm < 0.01 {
+            filtered = [0.0; BANDS];
+        } else {
+            filtered.iter_mut().for_each(|v| *v /= norm);
+        }
+        image.push(filtered);
+    }
+    image
+}
+
+// Summed-area table with a zero first row and column
+struct IntegralImage {
+    sums: Vec<[f64; BANDS + 1]>,
+}
+
+impl IntegralImage {
+    fn new(image: &[[f64; BANDS]]) -> Self {
+        let mut sums = vec![[0.0; BANDS + 1]];
+        for row in image {
+            let above = *sums.last().unwrap();
+            let mut next = [0.0; BANDS + 1];
+            let mut running = 0.0;
+            for band in 0..BANDS {
+                running += row[band];
+                next[band + 1] = above[band + 1] + running;
+            }
+            sums.push(next);
+        }
+        Self { sums }
+    }
+    
+    // Sum over frames x1..x2 and bands y1..y2
+    fn area(&self, x1: usize, y1: usize, x2: usize, y2: usize) -> f64 {
+        self.sums[x2][y2] - self.sums[x1][y2] - self.sums[x2][y1] + self.sums[x1][y1]
+    }
+}
+
+fn subtract_log(a: f64, b: f64) -> f64 {
+    ((1.0 + a) / (1.0 + b)).ln()
+}
+
+impl Classifier {
+    fn apply(&self, image: &IntegralImage, x: usize) -> u32 {
+        let (y, w, h) = (self.y, self.width, self.height);
+        let area = |x1, y1, x2, y2| image.area(x + x1, y + y1, x + x2, y + y2);
+        let value = match self.filter {
+            0 => subtract_log(area(0, 0, w, h), 0.0),
+            1 => subtract_log(area(0, h / 2, w, h), area(0, 0, w, h / 2)),
+            2 => subtract_log(area(w / 2, 0, w, h), area(0, 0, w / 2, h)),
+            3 => subtract_log(
+                area(0, h / 2, w / 2, h) + area(w / 2, 0, w, h / 2),
+                area(0, 0, w / 2, h / 2) + area(w / 2, h / 2, w, h),
+            ),
+            4 => subtract_log(area(0, h / 3, w, 2 * (h / 3)), area(0, 0, w, h / 3) + area(0, 2 * (h / 3), w, h)),
+            _ => subtract_log(area(w / 3, 0, 2 * (w / 3), h), area(0, 0, w / 3, h) + area(2 * (w / 3), 0, w, h)),
+        };
+        let [t0, t1, t2] = self.thresholds;
+        let level = if value < t1 {
+            usize::from(value >= t0)
+        } else if value < t2 {
+            2
+        } else {
+            3
+        };
+        GRAY_CODE[level]
+    }
+}
+
+// Raw sub-fingerprints of decoded audio
+fn calculate(samples: &Samples) -> Vec<u32> {
+    let image = chroma_image(&samples.resampled(SAMPLE_RATE as u32));
+    let max_width = CLASSIFIERS.iter().map(|c| c.width).max().unwrap_or(1);
+    if image.len() < max_width {
+        return Vec::new();
+    }
+    let integral = IntegralImage::new(&image);
+    (0..=image.len() - max_width)
+        .map(|x| CLASSIFIERS.iter().fold(0, |bits, c| (bits << 2) | c.apply(&integral, x)))
+        .collect()
+}
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+// URL-safe base64 without padding, as Chromaprint writes it
+fn base64_encode(bytes: &[u8]) -> String {
+    let mut out = String::new();
+    for chunk in bytes.chunks(3) {
+        let n = chunk.iter().enumerate().fold(0u32, |n, (i, &b)| n | (b as u32) << (16 - 8 * i));
+        for i in 0..=chunk.len() {
+            out.push(BASE64[(n >> (18 - 6 * i) & 63) as usize] as char);
+        }
+    }
+    out
+}
+
+fn base64_decode(text: &str) -> Option<Vec<u8>> {
+    let values: Vec<u32> = text
+        .bytes()
+        .map(|c| BASE64.iter().position(|&b| b == c).map(|v| v as u32))
+        .collect::<Option<_>>()?;
+    let mut out = Vec::new();
+    for chunk in values.chunks(4) {
+        let n = chunk.iter().enumerate().fold(0u32, |n, (i, &v)| n | v << (18 - 6 * i));
+        for i in 0..chunk.len().saturating_sub(1) {
+            out.push((n >> (16 - 8 * i)) as u8);
+        }
+    }
+    Some(out)
+}
+
+// Little-endian bit packing used for the 3-bit and 5-bit streams
+#[derive(Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    bits: usize,
+}
+
+impl BitWriter {
+    fn write(&mut self, value: u8, width: usize) {
+        for i in 0..width {
+            if self.bits == self.bytes.len() * 8 {
+                self.bytes.push(0);
+            }
+            if value >> i & 1 != 0 {
+                *self.bytes.last_mut().unwrap() |= 1 << (self.bits % 8);
+            }
+            self.bits += 1;
+        }
+    }
+}
+
+fn read_bits(bytes: &[u8], bit: usize, width: usize) -> Option<u8> {
+    let mut value = 0;
+    for i in 0..width {
+        let position = bit + i;
+        if bytes.get(position / 8)? >> (position % 8) & 1 != 0 {
+            value |= 1 << i;
+        }
+    }
+    Some(value)
+}
+
+// Each sub-fingerprint is XORed with the previous one and written as the gaps between its set
+// bits, ending in 0. Gaps go in 3-bit fields; gaps of 7 or more continue in a 5-bit stream.
+fn compress(fingerprint: &[u32]) -> String {
+    let mut gaps = Vec::new();
+    let mut previous = 0;
+    for &value in fingerprint {
+        let mut x = value ^ previous;
+        previous = value;
+        let (mut bit, mut last_bit) = (1u8, 0u8);
+        while x != 0 {
+            if x & 1 != 0 {
+                gaps.push(bit - last_bit);
+                last_bit = bit;
+            }
+            x >>= 1;
+            bit += 1;
+        }
+        gaps.push(0);
+    }
+    
+    let count = fingerprint.len();
+    let mut bytes = vec![ALGORITHM, (count >> 16) as u8, (count >> 8) as u8, count as u8];
+    let mut normal = BitWriter::default();
+    let mut exceptional = BitWriter::default();
+    for &gap in &gaps {
+        normal.write(gap.min(7), 3);
+        if gap >= 7 {
+            exceptional.write(gap - 7, 5);
+        }
+    }
+    bytes.extend(normal.bytes);
+    bytes.extend(exceptional.bytes);
+    base64_encode(&bytes)
+}
+
+fn decompress(text: &str) -> Option<Vec<u32>> {
+    let bytes = base64_decode(text)?;
+    let count = (*bytes.get(1)? as usize) << 16 | (*bytes.get(2)? as usize) << 8 | *bytes.get(3)? as usize;
+    let body = &bytes[4..];
+    
+    let mut gaps = Vec::new();
+    let mut ends = 0;
+    while ends < count {
+        let gap = read_bits(body, gaps.len() * 3, 3)?;
+        ends += usize::from(gap == 0);
+        gaps.push(gap);
+    }
+    let exceptions = &body[(gaps.len() * 3).div_ceil(8)..];
+    let mut exception_bit = 0;
+    for gap in gaps.iter_mut().filter(|g| **g == 7) {
+        *gap += read_bits(exceptions, exception_bit, 5)?;
+        exception_bit += 5;
+    }
+    
+    let mut fingerprint = Vec::with_capacity(count);
+    let (mut value, mut bit, mut previous) = (0u32, 0u32, 0u32);
+    for gap in gaps {
+        if gap == 0 {
+            previous ^= value;
+            fingerprint.push(previous);
+            value = 0;
+            bit = 0;
+        } else {
+            bit += gap as u32;
+            value |= 1u32.checked_shl(bit - 1)?;
+        }
+    }
+    Some(fingerprint)
+}
+
+fn compute(path: &str) -> Option<Fingerprint> {
+    let samples = decode::decode_mono(path, Some(LENGTH_MS))?;
+    let raw = calculate(&samples);
+    if raw.is_empty() {
+        return None;
+    }
+    Some(Fingerprint {
+        duration: crate::get_audio_duration(path).unwrap_or(samples.data.len() as u64 / samples.sample_rate.max(1) as u64),
+        value: compress(&raw),
+    })
+}
+
+// Fingerprints of `paths`, computing and storing the ones the metadata cache doesn't have yet
+pub fn ensure(state: &AppState, paths: &[String]) -> HashMap<String, Fingerprint> {
+    let mut cache = load_metadata_cache();
+    cache_missing_tracks(state, &mut cache, paths);
+    
+    let requested: HashSet<&String> = paths.iter().collect();
+    let mut found = HashMap::new();
+    let mut computed = HashMap::new();
+    for meta in cache.tracks.iter().filter(|m| requested.contains(&m.path)) {
+        if let Some(fingerprint) = &meta.fingerprint {
+            found.insert(meta.path.clone(), fingerprint.clone());
+        } else if let Some(fingerprint) = compute(&meta.path) {
+            computed.insert(meta.path.clone(), fingerprint.clone());
+            found.insert(meta.path.clone(), fingerprint);
+        }
+    }
+    
+    // Tracks that couldn't be decoded stay without one, so the next lookup tries them again
+    if !computed.is_empty() {
+        let _ = update_metadata_cache(state, |cache| {
+            for meta in cache.tracks.iter_mut() {
+                if let Some(fingerprint) = computed.remove(&meta.path) {
+                    meta.fingerprint = Some(fingerprint);
+                }
+            }
+            true
+        });
+    }
+    found
+}
+
+pub fn raw(fingerprint: &Fingerprint) -> Option<Vec<u32>> {
+    decompress(&fingerprint.value)
+}
+
+// Share of equal bits at the best alignment of two raw fingerprints
+pub fn similarity(a: &[u32], b: &[u32]) -> f32 {
+    let mut best = 0.0f32;
+    for offset in 0..=MAX_OFFSET {
+        for (x, y) in [(a, b), (b, a)] {
+            let x = &x[offset.min(x.len())..];
+            let len = x.len().min(y.len());
+            if len < MAX_OFFSET {
+                continue;
+            }
+            let errors: u32 = x[..len].iter().zip(&y[..len]).map(|(p, q)| (p ^ q).count_ones()).sum();
+            best = best.max(1.0 - errors as f32 / (32 * len) as f32);
+        }
+    }
+    best
+}
+
+#[derive(Serialize)]
+pub struct RecordingMatch {
+    path: String,
+    name: String,
+    similarity: f32,
+}
+
+// Other library tracks with the same recording as `path`, best match first
+#[tauri::command]
+pub async fn find_recording(path: String, app: AppHandle) -> Result<Vec<RecordingMatch>, String> {
+    tauri::async_runtime::spawn_blocking(move || recordings_of(&app.state::<AppState>(), path))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+fn recordings_of(state: &AppState, path: String) -> Result<Vec<RecordingMatch>, String> {
+    let mut library = crate::duplicates::library_paths();
+    library.retain(|p| *p != path);
+    library.push(path.clone());
+    
+    let fingerprints = ensure(state, &library);
+    let target = fingerprints
+        .get(&path)
+        .and_then(raw)
+        .ok_or_else(|| format!("Could not fingerprint {}", track_name(&path)))?;
+    
+    let mut matches: Vec<RecordingMatch> = fingerprints
+        .iter()
+        .filter(|(p, _)| **p != path)
+        .filter_map(|(p, fingerprint)| {
+            let similarity = similarity(&target, &raw(fingerprint)?);
+            (similarity >= MATCH_THRESHOLD).then(|| RecordingMatch {
+                path: p.clone(),
+                name: track_name(p),
+                similarity,
+            })
+        })
+        .collect();
+    matches.sort_by(|a, b| b.similarity.total_cmp(&a.similarity));
+    Ok(matches)
+}
+
+// Write fingerprints of `paths` to fingerprints.txt in `folder`, in fpcalc's output
+// format, for AcoustID lookups. Returns how many tracks were written.
+#[tauri::command]
+pub async fn export_fingerprints(paths: Vec<String>, folder: String, app: AppHandle) -> Result<usize, String> {
+    tauri::async_runtime::spawn_blocking(move || write_fingerprints(&app.state::<AppState>(), &paths, &folder))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+fn write_fingerprints(state: &AppState, paths: &[String], folder: &str) -> Result<usize, String> {
+    let fingerprints = ensure(state, paths);
+    let mut content = String::new();
+    for path in paths.iter().filter(|p| fingerprints.contains_key(*p)) {
+        let fingerprint = &fingerprints[path];
+        content.push_str(&format!(
+            "FILE={}\nDURATION={}\nFINGERPRINT={}\n\n",
+            paths::from_string(path).display(),
+            fingerprint.duration,
+            fingerprint.value
+        ));
+    }
+    fs::write(paths::from_string(folder).join("fingerprints.txt"), content).map_err(|e| e.to_string())?;
+    Ok(fingerprints.len())
+}
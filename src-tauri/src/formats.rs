@@ -0,0 +1,123 @@
+// Registry of the audio formats the player lists and how each one is decoded
+
+use std::path::Path;
+use symphonia::core::codecs::{self, CodecType};
+use symphonia::core::formats::{FormatOptions, FormatReader, Track};
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+
+use crate::paths;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Backend {
+    Rodio,     // rodio's decoder, which can seek the running sink in place
+    Symphonia, // Demuxed and decoded directly, picking the first decodable audio track
+}
+
+pub struct AudioFormat {
+    pub name: &'static str,
+    pub extensions: &'static [&'static str],
+    pub backend: Backend,
+    pub embedded_cue: bool, // Images of this format may carry a CUESHEET tag
+}
+
+// To support another format, add it here; decoding follows from the backend
+pub const FORMATS: &[AudioFormat] = &[
+    AudioFormat { name: "MP3", extensions: &["mp3"], backend: Backend::Rodio, embedded_cue: false },
+    AudioFormat { name: "WAV", extensions: &["wav"], backend: Backend::Rodio, embedded_cue: false },
+    AudioFormat { name: "AIFF", extensions: &["aif", "aiff"], backend: Backend::Rodio, embedded_cue: false },
+    AudioFormat { name: "Ogg Vorbis", extensions: &["ogg"], backend: Backend::Rodio, embedded_cue: false },
+    AudioFormat { name: "FLAC", extensions: &["flac"], backend: Backend::Symphonia, embedded_cue: true },
+    AudioFormat { name: "Ogg audio", extensions: &["oga"], backend: Backend::Symphonia, embedded_cue: false },
+    AudioFormat { name: "Opus", extensions: &["opus"], backend: Backend::Symphonia, embedded_cue: false },
+    AudioFormat { name: "AAC", extensions: &["aac"], backend: Backend::Symphonia, embedded_cue: false },
+    // AAC or ALAC, including the audio track of MP4 videos
+    AudioFormat { name: "MPEG-4", extensions: &["m4a", "mp4"], backend: Backend::Symphonia, embedded_cue: false },
+    // Matroska and WebM audio, including the audio track of MKV videos
+    AudioFormat { name: "Matroska", extensions: &["mka", "mkv", "webm"], backend: Backend::Symphonia, embedded_cue: false },
+    AudioFormat { name: "WavPack", extensions: &["wv"], backend: Backend::Symphonia, embedded_cue: true },
+    AudioFormat { name: "Monkey's Audio", extensions: &["ape"], backend: Backend::Symphonia, embedded_cue: true },
+];
+
+pub fn lookup(path: &Path) -> Option<&'static AudioFormat> {
+    let ext = path.extension()?.to_string_lossy().to_lowercase();
+    FORMATS.iter().find(|f| f.extensions.contains(&ext.as_str()))
+}
+
+pub fn is_supported(path: &Path) -> bool {
+    lookup(path).is_some()
+}
+
+// Codecs that show up in registered containers without a decoder being available
+fn codec_name(codec: CodecType) -> &'static str {
+    match codec {
+        codecs::CODEC_TYPE_OPUS => "Opus",
+        codecs::CODEC_TYPE_WAVPACK => "WavPack",
+        codecs::CODEC_TYPE_MONKEYS_AUDIO => "Monkey's Audio",
+        codecs::CODEC_TYPE_ALAC => "ALAC",
+        codecs::CODEC_TYPE_AAC => "AAC",
+        codecs::CODEC_TYPE_EAC3 => "E-AC-3",
+        codecs::CODEC_TYPE_DCA => "DTS",
+        _ => symphonia::default::get_codecs()
+            .get_codec(codec)
+            .map(|d| d.short_name)
+            .unwrap_or("unknown"),
+    }
+}
+
+fn unsupported(path: &Path, codec: &str) -> String {
+    let name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    format!("Unsupported codec: {} ({})", codec, name)
+}
+
+// Demux a file with symphonia and pick the first audio track that has a decoder.
+// Video and subtitle tracks of MP4/MKV files are skipped.
+pub fn open(path: &str) -> Result<(Box<dyn FormatReader>, Track), String> {
+    let path_buf = paths::from_string(path);
+    let path_ref = path_buf.as_path();
+    let format_name = lookup(path_ref).map(|f| f.name).unwrap_or("unknown");
+    let file = std::fs::File::open(path_ref).map_err(|e| e.to_string())?;
+    let mss = MediaSourceStream::new(Box::new(file), Default::default());
+    
+    let mut hint = Hint::new();
+    if let Some(ext) = path_ref.extension() {
+        hint.with_extension(&ext.to_string_lossy());
+    }
+    
+    let probed = symphonia::default::get_probe()
+        .format(&hint, mss, &FormatOptions::default(), &MetadataOptions::default())
+        .map_err(|_| unsupported(path_ref, format_name))?;
+    
+    let registry = symphonia::default::get_codecs();
+    let audio_tracks: Vec<&Track> = probed
+        .format
+        .tracks()
+        .iter()
+        .filter(|t| t.codec_params.codec != codecs::CODEC_TYPE_NULL && t.codec_params.sample_rate.is_some())
+        .collect();
+    let track = match audio_tracks.iter().find(|t| registry.get_codec(t.codec_params.codec).is_some()) {
+        Some(track) => (*track).clone(),
+        None => {
+            let codec = audio_tracks.first().map(|t| codec_name(t.codec_params.codec)).unwrap_or(format_name);
+            return Err(unsupported(path_ref, codec));
+        }
+    };
+    
+    Ok((probed.format, track))
+}
+
+// Fails with an "unsupported codec" message when the file can't be decoded
+pub fn check_playable(path: &str) -> Result<(), String> {
+    match lookup(&paths::from_string(path)).map(|f| f.backend) {
+        Some(Backend::Symphonia) => open(path).map(|_| ()),
+        _ => Ok(()),
+    }
+}
+
+pub fn symphonia_duration_ms(path: &str) -> Option<u64> {
+    let (_, track) = open(path).ok()?;
+    let params = track.codec_params;
+    let frames = params.n_frames?;
+    Some(frames * 1000 / params.sample_rate? as u64)
+}
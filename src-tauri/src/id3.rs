@@ -0,0 +1,102 @@
+// Minimal ID3v2 reading for frames lofty does not expose (SYLT, CHAP, CTOC)
+
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+// Decode a text field of an ID3v2 frame, returning it and the bytes after its terminator
+pub fn read_text(encoding: u8, data: &[u8]) -> (String, &[u8]) {
+    if encoding == 1 || encoding == 2 {
+        let mut end = 0;
+        while end + 1 < data.len() && !(data[end] == 0 && data[end + 1] == 0) {
+            end += 2;
+        }
+        let mut units: Vec<u16> = data[..end.min(data.len())]
+            .chunks_exact(2)
+            .map(|c| u16::from_be_bytes([c[0], c[1]]))
+            .collect();
+        // UTF-16 with BOM (encoding 1) may be little-endian
+        if encoding == 1 {
+            match units.first() {
+                Some(0xFFFE) => {
+                    units = units.iter().map(|u| u.swap_bytes()).collect();
+                    units.remove(0);
+                }
+                Some(0xFEFF) => {
+                    units.remove(0);
+                }
+                _ => {}
+            }
+        }
+        let rest = data.get(end + 2..).unwrap_or(&[]);
+        (String::from_utf16_lossy(&units), rest)
+    } else {
+        let end = data.iter().position(|&b| b == 0).unwrap_or(data.len());
+        let text = if encoding == 3 {
+            String::from_utf8_lossy(&data[..end]).to_string()
+        } else {
+            data[..end].iter().map(|&b| b as char).collect()
+        };
+        (text, data.get(end + 1..).unwrap_or(&[]))
+    }
+}
+
+fn syncsafe(bytes: &[u8]) -> usize {
+    bytes.iter().fold(0, |acc, &b| (acc << 7) | (b & 0x7f) as usize)
+}
+
+fn be_u32(bytes: &[u8]) -> usize {
+    bytes.iter().fold(0, |acc, &b| (acc << 8) | b as usize)
+}
+
+// Raw ID3v2.3/2.4 tag at the start of a file: (major version, frame data)
+pub fn read_tag(path: &Path) -> Option<(u8, Vec<u8>)> {
+    let mut file = File::open(path).ok()?;
+    let mut header = [0u8; 10];
+    file.read_exact(&mut header).ok()?;
+    if &header[..3] != b"ID3" || !(3..=4).contains(&header[3]) {
+        return None;
+    }
+    // Whole-tag unsynchronisation is rare and not worth undoing here
+    let flags = header[5];
+    if flags & 0x80 != 0 {
+        return None;
+    }
+    
+    let mut body = vec![0u8; syncsafe(&header[6..10])];
+    file.read_exact(&mut body).ok()?;
+    
+    if flags & 0x40 != 0 && body.len() >= 4 {
+        let extended = if header[3] == 4 {
+            syncsafe(&body[..4])
+        } else {
+            be_u32(&body[..4]) + 4
+        };
+        body.drain(..extended.min(body.len()));
+    }
+    Some((header[3], body))
+}
+
+// (frame id, frame body) pairs; also used for sub-frames inside CHAP and CTOC
+pub fn frames(version: u8, mut data: &[u8]) -> Vec<(String, &[u8])> {
+    let mut frames = Vec::new();
+    while data.len() >= 10 && data[0] != 0 {
+        let id = String::from_utf8_lossy(&data[..4]).to_string();
+        let size = if version == 4 {
+            syncsafe(&data[4..8])
+        } else {
+            be_u32(&data[4..8])
+        };
+        if 10 + size > data.len() {
+            break;
+        }
+        frames.push((id, &data[10..10 + size]));
+        data = &data[10 + size..];
+    }
+    frames
+}
+
+// Null-terminated Latin-1 string, as used for CHAP/CTOC element ids
+pub fn read_element_id(data: &[u8]) -> (String, &[u8]) {
+    read_text(0, data)
+}
@@ -0,0 +1,354 @@
+// Lyrics from sidecar .lrc files and embedded tags, with synced line tracking
+
+use lofty::{ItemKey, TaggedFileExt};
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager, State};
+
+use crate::id3;
+use crate::paths;
+use crate::relocate::write_atomic;
+use crate::AppState;
+
+#[derive(Serialize, Clone)]
+pub struct LyricLine {
+    time_ms: Option<u64>,
+    text: String,
+}
+
+#[derive(Serialize, Clone)]
+pub struct Lyrics {
+    source: String, // "lrc" | "embedded"
+    lrc_path: String, // Sidecar file that edits are saved to
+    synced: bool,
+    offset_ms: i64,
+    lines: Vec<LyricLine>,
+    raw: String,
+}
+
+impl Lyrics {
+    // Index of the line showing at the given playback position.
+    // A positive offset shows lines earlier, as in the LRC spec.
+    fn line_at(&self, elapsed_ms: u64) -> Option<usize> {
+        if !self.synced {
+            return None;
+        }
+        let position = elapsed_ms as i64 + self.offset_ms;
+        let count = self
+            .lines
+            .partition_point(|l| l.time_ms.map(|t| t as i64 <= position).unwrap_or(true));
+        count.checked_sub(1)
+    }
+}
+
+// Lyrics of the track the watcher last looked at, kept so edits apply immediately
+pub struct LoadedLyrics {
+    path: String,
+    lyrics: Option<Lyrics>,
+}
+
+#[derive(Serialize, Clone)]
+struct LyricsLinePayload {
+    path: String,
+    index: Option<usize>,
+}
+
+fn sidecar_path(path: &Path) -> PathBuf {
+    let upper = path.with_extension("LRC");
+    if !path.with_extension("lrc").exists() && upper.exists() {
+        upper
+    } else {
+        path.with_extension("lrc")
+    }
+}
+
+// LRC and CUE files in the wild are UTF-8 (often with a BOM) or Latin-1
+pub fn read_text(path: &Path) -> Option<String> {
+    let bytes = fs::read(path).ok()?;
+    let text = match String::from_utf8(bytes) {
+        Ok(text) => text,
+        Err(e) => e.into_bytes().iter().map(|&b| b as char).collect(),
+    };
+    Some(text.trim_start_matches('\u{feff}').to_string())
+}
+
+// "mm:ss", "mm:ss.xx" or "mm:ss:xx" -> milliseconds
+fn parse_timestamp(s: &str) -> Option<u64> {
+    let (minutes, rest) = s.split_once(':')?;
+    let (seconds, fraction) = match rest.find(['.', ':']) {
+        Some(i) => (&rest[..i], &rest[i + 1..]),
+        None => (rest, ""),
+    };
+    let minutes: u64 = minutes.trim().parse().ok()?;
+    let seconds: u64 = seconds.trim().parse().ok()?;
+    if !fraction.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let millis = match fraction.len() {
+        0 => 0,
+        1 => fraction.parse::<u64>().ok()? * 100,
+        2 => fraction.parse::<u64>().ok()? * 10,
+        _ => fraction[..3].parse::<u64>().ok()?,
+    };
+    minutes.checked_mul(60_000)?.checked_add(seconds.checked_mul(1000)?)?.checked_add(millis)
+}
+
+// Remove enhanced LRC word timings such as "<00:12.34>"
+fn strip_word_timings(text: &str) -> String {
+    let mut out = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find('<') {
+        match rest[start..].find('>') {
+            Some(end) if parse_timestamp(&rest[start + 1..start + end]).is_some() => {
+                out.push_str(&rest[..start]);
+                rest = &rest[start + end + 1..];
+            }
+            _ => {
+                out.push_str(&rest[..start + 1]);
+                rest = &rest[start + 1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out.trim().to_string()
+}
+
+// Parse LRC text; plain text without timestamps becomes unsynced lyrics
+fn parse_lrc(content: &str, source: &str, lrc_path: &Path) -> Lyrics {
+    let mut offset_ms = 0;
+    let mut timed = Vec::new();
+    let mut plain = Vec::new();
+    
+    for line in content.lines() {
+        let mut rest = line.trim();
+        let mut times = Vec::new();
+        let mut is_tag = false;
+        
+        // A line may carry several timestamps: "[00:12.00][01:30.50]Chorus"
+        while rest.starts_with('[') {
+            let end = match rest.find(']') {
+                Some(end) => end,
+                None => break,
+            };
+            let inner = &rest[1..end];
+            if let Some(time) = parse_timestamp(inner) {
+                times.push(time);
+            } else if let Some((key, value)) = inner.split_once(':') {
+                if times.is_empty() {
+                    is_tag = true;
+                    if key.trim().eq_ignore_ascii_case("offset") {
+                        offset_ms = value.trim().trim_start_matches('+').parse().unwrap_or(0);
+                    }
+                }
+            } else {
+                break;
+            }
+            rest = rest[end + 1..].trim_start();
+        }
+        
+        let text = strip_word_timings(rest);
+        if !times.is_empty() {
+            for time in times {
+                timed.push(LyricLine { time_ms: Some(time), text: text.clone() });
+            }
+        } else if !is_tag {
+            plain.push(LyricLine { time_ms: None, text });
+        }
+    }
+    
+    let synced = !timed.is_empty();
+    let lines = if synced {
+        timed.sort_by_key(|l| l.time_ms);
+        timed
+    } else {
+        // Drop blank lines at either end of plain lyrics
+        while plain.last().map(|l| l.text.is_empty()).unwrap_or(false) {
+            plain.pop();
+        }
+        let first = plain.iter().position(|l| !l.text.is_empty()).unwrap_or(plain.len());
+        plain.split_off(first)
+    };
+    
+    Lyrics {
+        source: source.to_string(),
+        lrc_path: lrc_path.to_string_lossy().to_string(),
+        synced,
+        offset_ms,
+        lines,
+        raw: content.to_string(),
+    }
+}
+
+// SYLT frame body: encoding, language, timestamp format, content type, descriptor,
+// then (text, 32-bit timestamp) pairs. Only millisecond timestamps are supported.
+fn parse_sylt(data: &[u8]) -> Option<Vec<LyricLine>> {
+    if data.len() < 6 || data[4] != 2 {
+        return None;
+    }
+    let encoding = data[0];
+    let (_, mut rest) = id3::read_text(encoding, &data[6..]);
+    
+    let mut lines = Vec::new();
+    while !rest.is_empty() {
+        let (text, after) = id3::read_text(encoding, rest);
+        if after.len() < 4 {
+            break;
+        }
+        let time = u32::from_be_bytes([after[0], after[1], after[2], after[3]]) as u64;
+        // Lines often start with a newline marker
+        lines.push(LyricLine { time_ms: Some(time), text: text.trim().to_string() });
+        rest = &after[4..];
+    }
+    
+    lines.sort_by_key(|l| l.time_ms);
+    if lines.is_empty() {
+        None
+    } else {
+        Some(lines)
+    }
+}
+
+fn format_timestamp(ms: u64) -> String {
+    format!("{:02}:{:02}.{:02}", ms / 60_000, (ms / 1000) % 60, (ms % 1000) / 10)
+}
+
+// Serialize lines back to LRC so embedded synced lyrics can be saved as a sidecar
+fn to_lrc(lines: &[LyricLine]) -> String {
+    lines
+        .iter()
+        .map(|l| match l.time_ms {
+            Some(time) => format!("[{}]{}", format_timestamp(time), l.text),
+            None => l.text.clone(),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn embedded_lyrics(path: &Path, lrc_path: &Path) -> Option<Lyrics> {
+    let tagged_file = lofty::read_from_path(path).ok()?;
+    
+    // SYLT is not mapped to a generic key, it comes through as a raw frame
+    let sylt = ItemKey::Unknown("SYLT".to_string());
+    for tag in tagged_file.tags() {
+        if let Some(lines) = tag.get_binary(&sylt, false).and_then(parse_sylt) {
+            return Some(parse_lrc(&to_lrc(&lines), "embedded", lrc_path));
+        }
+    }
+    
+    // USLT, Vorbis LYRICS and MP4 ©lyr; some taggers store LRC text here
+    tagged_file
+        .tags()
+        .iter()
+        .find_map(|tag| tag.get_string(&ItemKey::Lyrics))
+        .filter(|text| !text.trim().is_empty())
+        .map(|text| parse_lrc(text, "embedded", lrc_path))
+}
+
+// Sidecar first, since it is what the user edits, then embedded tags
+pub fn load_lyrics(path: &str) -> Option<Lyrics> {
+    let path = &paths::from_string(path);
+    let lrc_path = sidecar_path(path);
+    if let Some(content) = read_text(&lrc_path) {
+        return Some(parse_lrc(&content, "lrc", &lrc_path));
+    }
+    embedded_lyrics(path, &lrc_path)
+}
+
+// Set or insert the [offset:] tag, keeping every other line as written
+fn with_offset(raw: &str, offset_ms: i64) -> String {
+    let offset_line = format!("[offset:{:+}]", offset_ms);
+    let mut lines: Vec<String> = raw.lines().map(|l| l.to_string()).collect();
+    
+    let existing = lines.iter().position(|l| {
+        l.trim()
+            .strip_prefix('[')
+            .map(|rest| rest.to_lowercase().starts_with("offset:"))
+            .unwrap_or(false)
+    });
+    match existing {
+        Some(i) => lines[i] = offset_line,
+        None => {
+            // After the leading ID tags such as [ar:] and [ti:]
+            let insert_at = lines
+                .iter()
+                .position(|l| {
+                    let l = l.trim();
+                    !(l.starts_with('[') && l.find(']').map(|e| parse_timestamp(&l[1..e]).is_none()).unwrap_or(false))
+                })
+                .unwrap_or(lines.len());
+            lines.insert(insert_at, offset_line);
+        }
+    }
+    
+    lines.join("\n") + "\n"
+}
+
+fn remember(state: &AppState, path: &str, lyrics: &Lyrics) {
+    *state.lyrics.lock().unwrap() = Some(LoadedLyrics {
+        path: path.to_string(),
+        lyrics: Some(lyrics.clone()),
+    });
+}
+
+#[tauri::command]
+pub fn get_lyrics(path: String) -> Option<Lyrics> {
+    load_lyrics(&path)
+}
+
+// Write edited LRC text to the track's sidecar file
+#[tauri::command]
+pub fn save_lyrics(path: String, content: String, state: State<AppState>) -> Result<Lyrics, String> {
+    let lrc_path = sidecar_path(&paths::from_string(&path));
+    let content = if content.ends_with('\n') { content } else { content + "\n" };
+    write_atomic(&lrc_path, &content)?;
+    
+    let lyrics = parse_lrc(&content, "lrc", &lrc_path);
+    remember(&state, &path, &lyrics);
+    Ok(lyrics)
+}
+
+// Store a new offset in the sidecar, creating it from embedded lyrics if needed
+#[tauri::command]
+pub fn set_lyrics_offset(path: String, offset_ms: i64, state: State<AppState>) -> Result<Lyrics, String> {
+    let lyrics = load_lyrics(&path).ok_or("No lyrics for this track")?;
+    if !lyrics.synced {
+        return Err("Lyrics are not synced".to_string());
+    }
+    save_lyrics(path, with_offset(&lyrics.raw, offset_ms), state)
+}
+
+// Emits "lyrics-line" whenever the synced line at the playback position changes
+#[derive(Default)]
+pub struct LyricsWatch {
+    last_sent: Option<(String, Option<usize>)>,
+}
+
+impl LyricsWatch {
+    pub fn tick(&mut self, app: &AppHandle, state: &AppState, path: &str, elapsed_ms: u64) {
+        let index = if path.is_empty() {
+            None
+        } else {
+            let mut loaded = state.lyrics.lock().unwrap();
+            if loaded.as_ref().map(|l| l.path != path).unwrap_or(true) {
+                *loaded = Some(LoadedLyrics {
+                    path: path.to_string(),
+                    lyrics: load_lyrics(path),
+                });
+            }
+            loaded
+                .as_ref()
+                .and_then(|l| l.lyrics.as_ref())
+                .and_then(|l| l.line_at(elapsed_ms))
+        };
+        
+        let current = (path.to_string(), index);
+        if self.last_sent.as_ref() != Some(&current) {
+            let _ = app.emit_all("lyrics-line", LyricsLinePayload {
+                path: current.0.clone(),
+                index: current.1,
+            });
+            self.last_sent = Some(current);
+        }
+    }
+}
@@ -7,27 +7,58 @@ use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::PathBuf;
 use std::sync::mpsc::{channel, Sender};
+use std::sync::atomic::AtomicU64;
 use std::sync::{Arc, Mutex};
 use std::thread;
 use std::time::{Duration, Instant};
 use tauri::{Manager, State};
-use walkdir::WalkDir;
 use souvlaki::{MediaControlEvent, MediaControls, MediaMetadata, MediaPlayback, MediaPosition, PlatformConfig, SeekDirection};
 
-// Symphonia imports for fast FLAC seeking
+// Symphonia imports for fast FLAC seeking; probing lives in formats.rs
 use symphonia::core::audio::SampleBuffer;
-use symphonia::core::codecs::{DecoderOptions, CODEC_TYPE_NULL};
-use symphonia::core::formats::{FormatOptions, SeekMode, SeekTo};
-use symphonia::core::io::MediaSourceStream;
-use symphonia::core::meta::MetadataOptions;
-use symphonia::core::probe::Hint;
+use symphonia::core::codecs::DecoderOptions;
+use symphonia::core::formats::{SeekMode, SeekTo};
+
+mod alarms;
+mod analysis;
+mod book;
+mod chapters;
+mod cover;
+mod cue;
+mod decode;
+mod duplicates;
+mod fingerprint;
+mod formats;
+mod id3;
+mod lyrics;
+mod marks;
+mod organize;
+mod paths;
+mod playlistfile;
+mod relocate;
+mod repair;
+mod resume;
+mod scanfilter;
+mod session;
+mod sleep;
+mod sortkey;
+mod spectrum;
+mod tags;
+mod waveform;
 
 #[derive(Serialize, Deserialize, Default)]
 struct AppConfig {
     default_folder: Option<String>,
+    #[serde(default)]
+    infer_patterns: Vec<String>, // Path patterns used to fill missing tags, e.g. "%artist%/%album%/%track% - %title%"
+    #[serde(default)]
+    write_inferred_tags: bool, // Write inferred values back into the files while scanning
+    #[serde(default)]
+    sort_ignore_articles: bool, // Sort "The Beatles" under B, "A Tribe Called Quest" under T
 }
 
 #[derive(Serialize, Deserialize, Clone)]
+#[serde(from = "relocate::StoredPlaylist", into = "relocate::StoredPlaylist")]
 struct SavedPlaylist {
     name: String,
     tracks: Vec<String>, // File paths
@@ -43,6 +74,7 @@ struct PlaylistInfo {
 struct ArtistInfo {
     name: String,
     track_count: usize,
+    inferred: bool, // Name comes only from path patterns, not from tags
 }
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -50,6 +82,22 @@ struct TrackMeta {
     path: String,
     artist: Option<String>,
     title: Option<String>,
+    album: Option<String>,
+    track_number: Option<u32>,
+    #[serde(default)]
+    inferred: Vec<String>, // Fields filled from the path instead of tags
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    fingerprint: Option<fingerprint::Fingerprint>, // Computed on demand, see fingerprint.rs
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    bpm: Option<f64>, // From tags or analysis.rs
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    key: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    resume_ms: Option<u64>, // Where a long track was left off, see resume.rs
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    played: bool, // Listened past 95% at least once
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    book: Option<book::BookSettings>, // For folders played as audiobooks
 }
 
 #[derive(Serialize, Deserialize, Clone, Default)]
@@ -107,33 +155,104 @@ fn save_metadata_cache(cache: &MetadataCache) -> Result<(), String> {
         fs::create_dir_all(parent).map_err(|e| e.to_string())?;
     }
     let content = serde_json::to_string_pretty(cache).map_err(|e| e.to_string())?;
-    fs::write(&path, content).map_err(|e| e.to_string())?;
+    // Write to a temp file and rename so readers never see a half-written cache
+    let tmp_path = path.with_extension("json.tmp");
+    fs::write(&tmp_path, content).map_err(|e| e.to_string())?;
+    fs::rename(&tmp_path, &path).map_err(|e| e.to_string())?;
     Ok(())
 }
 
-fn extract_track_meta(path: &str) -> TrackMeta {
-    use lofty::{Accessor, TaggedFileExt};
+// Load the metadata cache, change it and save it under the cache lock, so a writer that
+// loaded its copy earlier can't save over the change. `update` returns whether it changed
+// anything; slow work (decoding, tag reads) belongs before the call, not inside it.
+fn update_metadata_cache(state: &AppState, update: impl FnOnce(&mut MetadataCache) -> bool) -> Result<(), String> {
+    let _lock = state.metadata_cache.lock().unwrap();
+    let mut cache = load_metadata_cache();
+    if update(&mut cache) {
+        save_metadata_cache(&cache)?;
+    }
+    Ok(())
+}
+
+fn extract_track_meta(path: &str, infer_patterns: &[String]) -> TrackMeta {
+    use lofty::{Accessor, ItemKey, TaggedFileExt};
     
     let mut artist = None;
     let mut title = None;
-    
-    if let Ok(tagged_file) = lofty::read_from_path(path) {
+    let mut album = None;
+    let mut track_number = None;
+    let mut bpm = None;
+    let mut key = None;
+    
+    if let Some((sheet, index)) = cue::lookup(path) {
+        let track = &sheet.tracks[index];
+        artist = track.performer.clone().or(sheet.performer.clone());
+        title = track.title.clone();
+        album = sheet.title.clone();
+        track_number = Some(track.number);
+    } else if let Ok(tagged_file) = lofty::read_from_path(paths::from_string(path)) {
         if let Some(tag) = tagged_file.primary_tag().or_else(|| tagged_file.first_tag()) {
             artist = tag.artist().map(|s| s.to_string());
             title = tag.title().map(|s| s.to_string());
+            album = tag.album().map(|s| s.to_string());
+            track_number = tag.track();
+            bpm = tag
+                .get_string(&ItemKey::Bpm)
+                .or_else(|| tag.get_string(&ItemKey::IntegerBpm))
+                .and_then(|s| s.trim().parse::<f64>().ok())
+                .filter(|b| *b > 0.0);
+            key = tag.get_string(&ItemKey::InitialKey).map(|s| s.trim().to_string()).filter(|s| !s.is_empty());
         }
     }
     
-    TrackMeta {
+    let mut meta = TrackMeta {
         path: path.to_string(),
         artist,
         title,
+        album,
+        track_number,
+        inferred: Vec::new(),
+        fingerprint: None,
+        bpm,
+        key,
+        resume_ms: None,
+        played: false,
+        book: None,
+    };
+    tags::infer_missing(&mut meta, infer_patterns);
+    meta
+}
+
+// Scan tags for requested tracks that aren't cached yet and persist the additions
+fn cache_missing_tracks(state: &AppState, cache: &mut MetadataCache, tracks: &[String]) {
+    let config = load_config();
+    let cached_paths: std::collections::HashSet<String> = cache.tracks.iter().map(|t| t.path.clone()).collect();
+    
+    let mut new_tracks = Vec::new();
+    for path in tracks {
+        if !cached_paths.contains(path) {
+            let mut meta = extract_track_meta(path, &config.infer_patterns);
+            if config.write_inferred_tags && !meta.inferred.is_empty() && tags::write_inferred(&meta).is_ok() {
+                meta = extract_track_meta(path, &config.infer_patterns);
+            }
+            new_tracks.push(meta);
+        }
+    }
+    
+    if !new_tracks.is_empty() {
+        let _ = update_metadata_cache(state, |saved| {
+            let saved_paths: std::collections::HashSet<&String> = saved.tracks.iter().map(|t| &t.path).collect();
+            let added: Vec<TrackMeta> = new_tracks.iter().filter(|t| !saved_paths.contains(&t.path)).cloned().collect();
+            saved.tracks.extend(added);
+            true
+        });
+        cache.tracks.extend(new_tracks);
     }
 }
 
 #[derive(Clone)]
 enum AudioCommand {
-    Play(String, f32, u64),
+    Play(String, Option<book::Book>, f32, u64), // The book, when the path is one
     Pause,
     Resume,
     Stop,
@@ -141,6 +260,8 @@ enum AudioCommand {
     Seek(u64),
     SetSpeed(f32),
     SetDevice(String), // Device name to switch to
+    SetNext(Option<String>), // Track expected to follow, so contiguous cue tracks play without a gap
+    FadeIn(u64), // Seconds to raise the volume over from silence, for alarms
 }
 
 struct PlaybackState {
@@ -152,6 +273,10 @@ struct PlaybackState {
     duration: Option<u64>,
     is_finished: bool,
     speed: f32,
+    segment: Option<cue::Segment>, // Part of the file being played, for cue sheet tracks
+    next_segment: Option<cue::Segment>,
+    book: Option<book::Book>, // Parts of the audiobook being played
+    book_next: usize,         // Next part to queue behind the playing one
 }
 
 impl PlaybackState {
@@ -165,6 +290,10 @@ impl PlaybackState {
             duration: None,
             is_finished: false,
             speed: 1.0,
+            segment: None,
+            next_segment: None,
+            book: None,
+            book_next: 0,
         }
     }
     
@@ -180,10 +309,90 @@ impl PlaybackState {
             0
         }
     }
+    
+    fn get_elapsed_millis(&self) -> u64 {
+        if let Some(start) = self.start_time {
+            let since_start = match (self.is_paused, self.pause_time) {
+                (true, Some(pause)) => pause.duration_since(start),
+                _ => start.elapsed(),
+            };
+            self.start_position * 1000 + since_start.as_millis() as u64
+        } else {
+            0
+        }
+    }
+    
+    // File to decode for the current track
+    fn source_path(&self, path: &str) -> String {
+        self.segment.as_ref().map(|s| s.file.clone()).unwrap_or_else(|| path.to_string())
+    }
+    
+    // Whether the next track starts where the current segment ends in the same file
+    fn runs_on(&self) -> bool {
+        match (&self.segment, &self.next_segment) {
+            (Some(current), Some(next)) => current.continues_into(next),
+            _ => false,
+        }
+    }
+    
+    // Decoder start and play length for a position within the current track.
+    // Segments are cut at their end unless the next track continues them.
+    fn source_window(&self, position_ms: u64) -> (u64, Option<u64>) {
+        match &self.segment {
+            Some(segment) => {
+                let start = segment.start_ms + position_ms;
+                let limit = if self.runs_on() {
+                    None
+                } else {
+                    segment.end_ms.map(|end| end.saturating_sub(start))
+                };
+                (start, limit)
+            }
+            None => (position_ms, None),
+        }
+    }
+    
+    // File, decoder start and play length for a position within the current track. For books
+    // that's the part holding the position; the parts after it are queued as it plays.
+    fn source(&mut self, path: &str, position_ms: u64) -> (String, u64, Option<u64>) {
+        if let Some(book) = &self.book {
+            let (index, offset) = book.locate(position_ms);
+            self.book_next = index + 1;
+            return (book.parts[index].path.clone(), offset, None);
+        }
+        let (seek_ms, limit_ms) = self.source_window(position_ms);
+        (self.source_path(path), seek_ms, limit_ms)
+    }
+    
+    // The book part to queue next, once the sink is down to its last source
+    fn next_part(&mut self, queued: usize) -> Option<String> {
+        let book = self.book.as_ref().filter(|_| queued < 2)?;
+        let part = book.parts.get(self.book_next)?;
+        self.book_next += 1;
+        Some(part.path.clone())
+    }
+    
+    fn segment_ended(&self) -> bool {
+        match &self.segment {
+            Some(cue::Segment { start_ms, end_ms: Some(end_ms), .. }) => start_ms + self.get_elapsed_millis() >= *end_ms,
+            _ => false,
+        }
+    }
+    
+    // Take over the running decoder for the next track of the same file
+    fn advance_segment(&mut self, path: String, segment: cue::Segment) {
+        let position = self.segment.as_ref().map(|s| s.start_ms).unwrap_or(0) + self.get_elapsed_millis();
+        let elapsed = Duration::from_millis(position.saturating_sub(segment.start_ms));
+        self.start_time = Instant::now().checked_sub(elapsed).or(Some(Instant::now()));
+        self.start_position = 0;
+        self.current_path = Some(path);
+        self.segment = Some(segment);
+        self.is_finished = false;
+    }
 }
 
-// Custom FLAC source using symphonia for fast seeking
-struct SymphoniaFlacSource {
+// Source decoded directly through symphonia, for FLAC (fast seeking) and the container formats
+struct SymphoniaSource {
     decoder: Box<dyn symphonia::core::codecs::Decoder>,
     format: Box<dyn symphonia::core::formats::FormatReader>,
     track_id: u32,
@@ -193,27 +402,10 @@ struct SymphoniaFlacSource {
     sample_index: usize,
 }
 
-impl SymphoniaFlacSource {
-    fn new(path: &str, seek_secs: u64) -> Option<Self> {
-        let file = std::fs::File::open(path).ok()?;
-        let mss = MediaSourceStream::new(Box::new(file), Default::default());
-        
-        let mut hint = Hint::new();
-        hint.with_extension("flac");
-        
-        let format_opts = FormatOptions::default();
-        let metadata_opts = MetadataOptions::default();
+impl SymphoniaSource {
+    fn new(path: &str, seek_ms: u64) -> Option<Self> {
         let decoder_opts = DecoderOptions::default();
-        
-        let probed = symphonia::default::get_probe()
-            .format(&hint, mss, &format_opts, &metadata_opts)
-            .ok()?;
-        
-        let mut format = probed.format;
-        
-        let track = format.tracks()
-            .iter()
-            .find(|t| t.codec_params.codec != CODEC_TYPE_NULL)?;
+        let (mut format, track) = formats::open(path).ok()?;
         
         let track_id = track.id;
         let sample_rate = track.codec_params.sample_rate.unwrap_or(44100);
@@ -224,8 +416,8 @@ impl SymphoniaFlacSource {
             .ok()?;
         
         // Seek if needed
-        if seek_secs > 0 {
-            let seek_ts = seek_secs * sample_rate as u64;
+        if seek_ms > 0 {
+            let seek_ts = seek_ms * sample_rate as u64 / 1000;
             let _ = format.seek(
                 SeekMode::Accurate,
                 SeekTo::TimeStamp { ts: seek_ts, track_id },
@@ -273,7 +465,7 @@ impl SymphoniaFlacSource {
     }
 }
 
-impl Iterator for SymphoniaFlacSource {
+impl Iterator for SymphoniaSource {
     type Item = i16;
     
     fn next(&mut self) -> Option<Self::Item> {
@@ -289,7 +481,7 @@ impl Iterator for SymphoniaFlacSource {
     }
 }
 
-impl rodio::Source for SymphoniaFlacSource {
+impl rodio::Source for SymphoniaSource {
     fn current_frame_len(&self) -> Option<usize> {
         Some(self.current_samples.len() - self.sample_index)
     }
@@ -310,6 +502,8 @@ impl rodio::Source for SymphoniaFlacSource {
 struct AudioPlayer {
     pub command_tx: Sender<AudioCommand>,
     playback_state: Arc<Mutex<PlaybackState>>,
+    spectrum: Arc<spectrum::SampleRing>, // Samples on their way to the output, for the visualizer
+    alarms: Arc<Mutex<alarms::Schedule>>, // Checked by the audio thread each time it wakes
 }
 
 impl AudioPlayer {
@@ -317,9 +511,13 @@ impl AudioPlayer {
         let (tx, rx) = channel::<AudioCommand>();
         let playback_state = Arc::new(Mutex::new(PlaybackState::new()));
         let state_clone = playback_state.clone();
+        let spectrum = Arc::new(spectrum::SampleRing::new());
+        let ring = spectrum.clone();
+        let alarms = Arc::new(Mutex::new(alarms::Schedule::load()));
+        let schedule = alarms.clone();
         
         thread::spawn(move || {
-            use rodio::{Decoder, OutputStream, Sink};
+            use rodio::{Decoder, OutputStream, Sink, Source};
             use cpal::traits::{HostTrait, DeviceTrait};
             use std::fs::File;
             use std::io::BufReader;
@@ -330,6 +528,8 @@ impl AudioPlayer {
                 OutputStream::try_default().ok();
             let mut current_sink: Option<Sink> = None;
             let mut selected_device_name: Option<String> = None;
+            let mut volume_target = 1.0; // Volume last asked for, which a fade-in works up to
+            let mut fade_in: Option<alarms::FadeIn> = None;
             
             // Helper to create output for a specific device or default
             fn create_output_for_device(device_name: &Option<String>) -> Option<(OutputStream, rodio::OutputStreamHandle)> {
@@ -349,28 +549,14 @@ impl AudioPlayer {
                 OutputStream::try_default().ok()
             }
             
-            fn play_file(path: &str, volume: f32, seek_secs: u64, stream_handle: &rodio::OutputStreamHandle, start_paused: bool) -> Option<Sink> {
-                use std::path::Path;
-                
-                let ext = Path::new(path).extension()?.to_str()?.to_lowercase();
+            // Plays from seek_ms, stopping after limit_ms when given
+            fn play_file(path: &str, volume: f32, seek_ms: u64, limit_ms: Option<u64>, stream_handle: &rodio::OutputStreamHandle, start_paused: bool, ring: &Arc<spectrum::SampleRing>) -> Option<Sink> {
                 let sink = Sink::try_new(stream_handle).ok()?;
                 sink.set_volume(volume);
+                ring.set_volume(volume);
                 // Start paused so no audio plays until caller sets start_time
                 sink.pause();
-                
-                if ext == "flac" {
-                    // FLAC: use custom symphonia source for fast seeking
-                    let source = SymphoniaFlacSource::new(path, seek_secs)?;
-                    sink.append(source);
-                } else {
-                    // MP3/WAV: use rodio decoder with try_seek
-                    let file = File::open(path).ok()?;
-                    let source = Decoder::new(BufReader::new(file)).ok()?;
-                    sink.append(source);
-                    if seek_secs > 0 {
-                        let _ = sink.try_seek(Duration::from_secs(seek_secs));
-                    }
-                }
+                append_file(&sink, path, seek_ms, limit_ms, ring)?;
                 // Only start playing if caller doesn't want it paused
                 if !start_paused {
                     sink.play();
@@ -378,21 +564,89 @@ impl AudioPlayer {
                 Some(sink)
             }
             
+            // Queues the file on the sink, from seek_ms and for limit_ms when given
+            fn append_file(sink: &Sink, path: &str, seek_ms: u64, limit_ms: Option<u64>, ring: &Arc<spectrum::SampleRing>) -> Option<()> {
+                let format = formats::lookup(&paths::from_string(path))?;
+                if format.backend == formats::Backend::Symphonia {
+                    // FLAC and containers: decode through symphonia directly
+                    let source = spectrum::Tap::new(SymphoniaSource::new(path, seek_ms)?, ring);
+                    match limit_ms {
+                        Some(limit) => sink.append(source.take_duration(Duration::from_millis(limit))),
+                        None => sink.append(source),
+                    }
+                } else {
+                    // MP3/WAV/AIFF/Ogg Vorbis: use rodio decoder with try_seek
+                    let file = File::open(paths::from_string(path)).ok()?;
+                    let mut source = Decoder::new(BufReader::new(file)).ok()?;
+                    if seek_ms > 0 {
+                        let _ = source.try_seek(Duration::from_millis(seek_ms));
+                    }
+                    let source = spectrum::Tap::new(source, ring);
+                    match limit_ms {
+                        Some(limit) => sink.append(source.take_duration(Duration::from_millis(limit))),
+                        None => sink.append(source),
+                    }
+                }
+                Some(())
+            }
+            
             loop {
+                schedule.lock().unwrap().check();
+                
+                // Step an alarm's fade-in
+                if let Some(ref fade) = fade_in {
+                    let level = fade.level();
+                    if let Some(ref sink) = current_sink {
+                        sink.set_volume(volume_target * level.unwrap_or(1.0));
+                    }
+                    if level.is_none() {
+                        fade_in = None;
+                    }
+                }
+                
                 // Check if track finished
                 if let Some(ref sink) = current_sink {
+                    let mut state = state_clone.lock().unwrap();
+                    // Gapless books: the next part waits in the sink before the current one ends
+                    if let Some(part) = state.next_part(sink.len()) {
+                        append_file(sink, &part, 0, None, &ring);
+                    }
                     if sink.empty() {
-                        let mut state = state_clone.lock().unwrap();
                         if !state.is_finished && state.start_time.is_some() {
                             state.is_finished = true;
                         }
+                    } else if !state.is_finished && state.segment_ended() {
+                        // A cue track reached its end while the decoder runs on into the next one
+                        state.is_finished = true;
+                        if !state.runs_on() {
+                            sink.pause();
+                        }
                     }
                 }
                 
                 // Use timeout to periodically check sink status
                 match rx.recv_timeout(Duration::from_millis(100)) {
                     Ok(cmd) => match cmd {
-                        AudioCommand::Play(path, volume, skip_secs) => {
+                        AudioCommand::Play(path, book, volume, skip_secs) => {
+                            let segment = cue::segment(&path);
+                            let mut state = state_clone.lock().unwrap();
+                            let running = current_sink.as_ref().map(|s| !s.empty() && !s.is_paused()).unwrap_or(false);
+                            let continues = match (&state.segment, &segment) {
+                                (Some(current), Some(next)) => current.continues_into(next),
+                                _ => false,
+                            };
+                            if running && continues && skip_secs == 0 {
+                                // Gapless: the next cue track is already playing from the same decoder
+                                if let Some(segment) = segment {
+                                    state.advance_segment(path, segment);
+                                }
+                                continue;
+                            }
+                            state.segment = segment;
+                            state.book = book;
+                            let (file, seek_ms, limit_ms) = state.source(&path, skip_secs * 1000);
+                            drop(state);
+                            
                             if let Some(sink) = current_sink.take() {
                                 sink.stop();
                             }
@@ -400,7 +654,7 @@ impl AudioPlayer {
                             // Try to play, recreating output stream if needed
                             let mut played = false;
                             if let Some(ref handle) = audio_output.as_ref().map(|(_, h)| h) {
-                                if let Some(sink) = play_file(&path, volume, skip_secs, handle, false) {
+                                if let Some(sink) = play_file(&file, volume, seek_ms, limit_ms, handle, false, &ring) {
                                     current_sink = Some(sink);
                                     played = true;
                                 }
@@ -410,15 +664,23 @@ impl AudioPlayer {
                             if !played {
                                 audio_output = create_output_for_device(&selected_device_name);
                                 if let Some(ref handle) = audio_output.as_ref().map(|(_, h)| h) {
-                                    if let Some(sink) = play_file(&path, volume, skip_secs, handle, false) {
+                                    if let Some(sink) = play_file(&file, volume, seek_ms, limit_ms, handle, false, &ring) {
                                         current_sink = Some(sink);
                                         played = true;
                                     }
                                 }
                             }
                             
+                            volume_target = volume;
                             if played {
                                 let mut state = state_clone.lock().unwrap();
+                                // Keep the playback speed across tracks
+                                if let Some(ref sink) = current_sink {
+                                    sink.set_speed(state.speed);
+                                    if let Some(level) = fade_in.as_ref().and_then(|f| f.level()) {
+                                        sink.set_volume(volume * level);
+                                    }
+                                }
                                 state.start_time = Some(Instant::now());
                                 state.start_position = skip_secs;
                                 state.is_paused = false;
@@ -460,11 +722,16 @@ impl AudioPlayer {
                             state.is_paused = false;
                             state.pause_time = None;
                             state.current_path = None;
+                            state.segment = None;
+                            state.book = None;
                         }
                         AudioCommand::SetVolume(vol) => {
                             if let Some(ref sink) = current_sink {
                                 sink.set_volume(vol);
                             }
+                            ring.set_volume(vol);
+                            volume_target = vol;
+                            fade_in = None;
                         }
                         AudioCommand::SetSpeed(speed) => {
                             if let Some(ref sink) = current_sink {
@@ -474,18 +741,15 @@ impl AudioPlayer {
                             state.speed = speed;
                         }
                         AudioCommand::Seek(position) => {
-                            let state = state_clone.lock().unwrap();
+                            let mut state = state_clone.lock().unwrap();
                             let was_paused = state.is_paused;
-                            if let Some(ref path) = state.current_path.clone() {
-                                let ext = std::path::Path::new(&path)
-                                    .extension()
-                                    .and_then(|e| e.to_str())
-                                    .map(|e| e.to_lowercase())
-                                    .unwrap_or_default();
+                            let source = state.current_path.clone().map(|p| state.source(&p, position * 1000));
+                            if let Some((ref path, seek_ms, limit_ms)) = source {
+                                let backend = formats::lookup(&paths::from_string(path)).map(|f| f.backend);
                                 
-                                // For non-FLAC, try fast seek on current sink first
+                                // For rodio-decoded formats, try fast seek on current sink first
                                 let seek_duration = Duration::from_secs(position);
-                                let seek_success = if ext != "flac" {
+                                let seek_success = if backend == Some(formats::Backend::Rodio) && state.segment.is_none() && state.book.is_none() {
                                     if let Some(ref sink) = current_sink {
                                         sink.try_seek(seek_duration).is_ok()
                                     } else {
@@ -528,7 +792,7 @@ impl AudioPlayer {
                                     // Sink starts paused so we can set start_time before audio plays
                                     let mut played = false;
                                     if let Some(ref handle) = audio_output.as_ref().map(|(_, h)| h) {
-                                        if let Some(sink) = play_file(&path, volume, position, handle, true) {
+                                        if let Some(sink) = play_file(path, volume, seek_ms, limit_ms, handle, true, &ring) {
                                             current_sink = Some(sink);
                                             played = true;
                                         }
@@ -537,7 +801,7 @@ impl AudioPlayer {
                                     if !played {
                                         audio_output = create_output_for_device(&selected_device_name);
                                         if let Some(ref handle) = audio_output.as_ref().map(|(_, h)| h) {
-                                            if let Some(sink) = play_file(&path, volume, position, handle, true) {
+                                            if let Some(sink) = play_file(path, volume, seek_ms, limit_ms, handle, true, &ring) {
                                                 current_sink = Some(sink);
                                                 played = true;
                                             }
@@ -545,6 +809,9 @@ impl AudioPlayer {
                                     }
                                     
                                     if played {
+                                        if let Some(ref sink) = current_sink {
+                                            sink.set_speed(state_clone.lock().unwrap().speed);
+                                        }
                                         if was_paused {
                                             // Keep paused — sink is already paused from play_file
                                             let now = Instant::now();
@@ -577,10 +844,10 @@ impl AudioPlayer {
                             selected_device_name = if device_name.is_empty() { None } else { Some(device_name) };
                             
                             // Get current playback state before switching
-                            let state = state_clone.lock().unwrap();
+                            let mut state = state_clone.lock().unwrap();
                             let was_playing = state.start_time.is_some() && !state.is_paused;
-                            let current_path = state.current_path.clone();
                             let current_position = state.get_elapsed();
+                            let source = state.current_path.clone().map(|p| state.source(&p, current_position * 1000));
                             drop(state);
                             
                             // Get current volume before stopping
@@ -600,12 +867,12 @@ impl AudioPlayer {
                             
                             // Resume playback if was playing
                             if was_playing {
-                                if let Some(ref path) = current_path {
+                                if let Some((ref path, seek_ms, limit_ms)) = source {
                                     if let Some(ref handle) = audio_output.as_ref().map(|(_, h)| h) {
-                                        if let Some(sink) = play_file(path, volume, current_position, handle, false) {
-                                            current_sink = Some(sink);
-                                            
+                                        if let Some(sink) = play_file(path, volume, seek_ms, limit_ms, handle, false, &ring) {
                                             let mut state = state_clone.lock().unwrap();
+                                            sink.set_speed(state.speed);
+                                            current_sink = Some(sink);
                                             state.start_time = Some(Instant::now());
                                             state.start_position = current_position;
                                             state.is_paused = false;
@@ -615,6 +882,13 @@ impl AudioPlayer {
                                 }
                             }
                         }
+                        AudioCommand::SetNext(path) => {
+                            let mut state = state_clone.lock().unwrap();
+                            state.next_segment = path.and_then(|p| cue::segment(&p));
+                        }
+                        AudioCommand::FadeIn(secs) => {
+                            fade_in = Some(alarms::FadeIn::new(secs));
+                        }
                     },
                     Err(_) => {
                         // Timeout - continue loop to check sink status
@@ -623,7 +897,7 @@ impl AudioPlayer {
             }
         });
         
-        Self { command_tx: tx, playback_state }
+        Self { command_tx: tx, playback_state, spectrum, alarms }
     }
     
     fn send(&self, cmd: AudioCommand) {
@@ -634,6 +908,14 @@ impl AudioPlayer {
         self.playback_state.lock().unwrap().get_elapsed()
     }
     
+    fn get_elapsed_millis(&self) -> u64 {
+        self.playback_state.lock().unwrap().get_elapsed_millis()
+    }
+    
+    fn get_current_path(&self) -> Option<String> {
+        self.playback_state.lock().unwrap().current_path.clone()
+    }
+    
     fn is_finished(&self) -> bool {
         self.playback_state.lock().unwrap().is_finished
     }
@@ -653,6 +935,15 @@ struct AppState {
     is_playing: Mutex<bool>,
     is_paused: Mutex<bool>,
     media_controls: Mutex<Option<MediaControls>>,
+    tag_undo: Mutex<Vec<tags::TagSnapshot>>, // Previous values from the last write_tags batch
+    lyrics: Mutex<Option<lyrics::LoadedLyrics>>,
+    chapters: Mutex<Option<chapters::LoadedChapters>>,
+    waveform_request: AtomicU64, // Bumped by each get_waveform call so superseded decodes stop
+    spectrum: Mutex<spectrum::SpectrumConfig>,
+    session: Mutex<session::Session>,
+    speed_outside_books: Mutex<Option<f32>>, // Speed to return to once a book stops playing
+    sleep: Mutex<sleep::SleepTimer>,
+    metadata_cache: Mutex<()>, // Held from loading the metadata cache to saving it, see update_metadata_cache
 }
 
 impl AppState {
@@ -667,6 +958,15 @@ impl AppState {
             is_playing: Mutex::new(false),
             is_paused: Mutex::new(false),
             media_controls: Mutex::new(None),
+            tag_undo: Mutex::new(Vec::new()),
+            lyrics: Mutex::new(None),
+            chapters: Mutex::new(None),
+            waveform_request: AtomicU64::new(0),
+            spectrum: Mutex::new(spectrum::SpectrumConfig::default()),
+            session: Mutex::new(session::Session::default()),
+            speed_outside_books: Mutex::new(None),
+            sleep: Mutex::new(sleep::SleepTimer::default()),
+            metadata_cache: Mutex::new(()),
         }
     }
     
@@ -690,19 +990,26 @@ impl AppState {
         }
     }
     
-    fn update_media_metadata(&self, title: &str, duration: Option<u64>) {
+    fn update_media_metadata(&self, title: &str, duration: Option<u64>, cover_url: Option<&str>) {
         if let Ok(mut controls) = self.media_controls.lock() {
             if let Some(ref mut mc) = *controls {
                 let _ = mc.set_metadata(MediaMetadata {
                     title: Some(title),
                     artist: Some("VI Music"),
                     album: None,
-                    cover_url: None,
+                    cover_url,
                     duration: duration.map(|d| std::time::Duration::from_secs(d)),
                 });
             }
         }
     }
+    
+    // Cover art is resolved after playback starts, so re-send the current metadata with it
+    fn update_media_cover(&self, cover_url: &str) {
+        let title = self.current_track.lock().unwrap().clone().unwrap_or_default();
+        let duration = *self.current_duration.lock().unwrap();
+        self.update_media_metadata(&title, duration, Some(cover_url));
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -711,6 +1018,16 @@ struct TrackInfo {
     name: String,
     index: usize,
     duration: Option<u64>,
+    #[serde(default)]
+    missing: bool, // A saved playlist entry whose file is gone; see repair.rs
+}
+
+// What play_track started, from which second; long tracks may resume where they were left
+#[derive(Serialize)]
+struct StartedTrack {
+    #[serde(flatten)]
+    track: TrackInfo,
+    start_secs: u64,
 }
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -737,21 +1054,38 @@ struct FolderContents {
 }
 
 fn get_audio_duration(path: &str) -> Option<u64> {
-    let path_buf = std::path::Path::new(path);
-    let ext = path_buf.extension()?.to_str()?.to_lowercase();
+    get_audio_duration_ms(path).map(|ms| ms / 1000)
+}
+
+fn get_audio_duration_ms(path: &str) -> Option<u64> {
+    if let Some(segment) = cue::segment(path) {
+        let end_ms = match segment.end_ms {
+            Some(end_ms) => end_ms,
+            None => get_audio_duration_ms(&segment.file)?,
+        };
+        return Some(end_ms.saturating_sub(segment.start_ms));
+    }
+    if book::is_book(path) {
+        return book::load(path).map(|b| b.duration_ms);
+    }
+    
+    let path_buf = paths::from_string(path);
+    let ext = path_buf.extension()?.to_string_lossy().to_lowercase();
+    let backend = formats::lookup(&path_buf)?.backend;
     
     match ext.as_str() {
         "mp3" => {
-            mp3_duration::from_path(path).ok().map(|d| d.as_secs())
+            mp3_duration::from_path(&path_buf).ok().map(|d| d.as_millis() as u64)
         }
+        _ if backend == formats::Backend::Symphonia => formats::symphonia_duration_ms(path),
         _ => {
             use rodio::{Decoder, Source};
             use std::fs::File;
             use std::io::BufReader;
             
-            let file = File::open(path).ok()?;
+            let file = File::open(&path_buf).ok()?;
             let source = Decoder::new(BufReader::new(file)).ok()?;
-            source.total_duration().map(|d| d.as_secs())
+            source.total_duration().map(|d| d.as_millis() as u64)
         }
     }
 }
@@ -768,44 +1102,61 @@ struct PlayerStatus {
     playlist_length: usize,
     elapsed: u64,
     duration: Option<u64>,
+    chapter_index: Option<usize>,
+    chapter_title: Option<String>,
+    sleep: Option<sleep::SleepStatus>,
 }
 
 fn is_audio_file(path: &PathBuf) -> bool {
-    if let Some(ext) = path.extension() {
-        let ext = ext.to_string_lossy().to_lowercase();
-        matches!(ext.as_str(), "mp3" | "wav" | "flac" | "ogg" | "m4a" | "aif" | "aiff")
-    } else {
-        false
-    }
+    formats::is_supported(path)
+}
+
+// Name shown for a track: the file name, or "NN. Title" for cue sheet tracks
+fn track_name(path: &str) -> String {
+    cue::track_name(path).unwrap_or_else(|| {
+        paths::from_string(path)
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default()
+    })
+}
+
+// Lets the audio thread keep decoding into the next entry when it continues the same cue image
+fn send_next_hint(state: &AppState, index: usize) {
+    let next = state.playlist.lock().unwrap().get(index + 1).cloned();
+    state.player.send(AudioCommand::SetNext(next));
 }
 
 #[tauri::command]
 fn load_folder(path: String, state: State<AppState>) -> Result<Vec<TrackInfo>, String> {
-    let mut tracks = Vec::new();
-    
-    for entry in WalkDir::new(&path).into_iter().filter_map(|e| e.ok()) {
-        let path_buf = entry.path().to_path_buf();
-        if path_buf.is_file() && is_audio_file(&path_buf) {
-            tracks.push(path_buf.to_string_lossy().to_string());
-        }
-    }
-    
-    tracks.sort();
+    let root = paths::from_string(&path);
+    let tracks = if playlistfile::is_playlist_file(&root) {
+        // A playlist file loads its entries in their own order
+        playlistfile::read(&root)?.into_iter().filter(|p| cue::exists(p)).collect()
+    } else {
+        let mut tracks: Vec<String> = scanfilter::Scanner::new(&root, &root)
+            .files(&root)
+            .iter()
+            .map(|p| paths::to_string(p))
+            .collect();
+        
+        let sorter = sortkey::Sorter::from_config();
+        tracks.sort_by_cached_key(|p| sorter.path_key(p));
+        cue::expand(tracks)
+    };
     
     let track_infos: Vec<TrackInfo> = tracks
         .iter()
         .enumerate()
         .map(|(i, p)| {
-            let name = PathBuf::from(p)
-                .file_name()
-                .map(|n| n.to_string_lossy().to_string())
-                .unwrap_or_default();
+            let name = track_name(p);
             let duration = get_audio_duration(p);
             TrackInfo {
                 path: p.clone(),
                 name,
                 index: i,
                 duration,
+                missing: false,
             }
         })
         .collect();
@@ -818,60 +1169,58 @@ fn load_folder(path: String, state: State<AppState>) -> Result<Vec<TrackInfo>, S
 
 #[tauri::command]
 fn browse_folder(path: String, root_path: String) -> Result<FolderContents, String> {
-    let path_buf = PathBuf::from(&path);
-    let root_buf = PathBuf::from(&root_path);
+    let path_buf = paths::from_string(&path);
+    let root_buf = paths::from_string(&root_path);
     
     if !path_buf.exists() || !path_buf.is_dir() {
         return Err("Invalid folder path".to_string());
     }
     
     let parent = if path_buf != root_buf {
-        path_buf.parent().map(|p| p.to_string_lossy().to_string())
+        path_buf.parent().map(paths::to_string)
     } else {
         None
     };
     
     let mut items = Vec::new();
     
-    if let Ok(entries) = std::fs::read_dir(&path) {
-        for entry in entries.filter_map(|e| e.ok()) {
-            let entry_path = entry.path();
-            let name = entry_path
-                .file_name()
-                .map(|n| n.to_string_lossy().to_string())
-                .unwrap_or_default();
-            
-            if entry_path.is_dir() {
-                let track_count = count_audio_files(&entry_path);
-                if track_count > 0 {
-                    items.push(FolderItem {
-                        name,
-                        path: entry_path.to_string_lossy().to_string(),
-                        is_folder: true,
-                        track_count,
-                        duration: None,
-                    });
-                }
-            } else if is_audio_file(&entry_path) {
-                let duration = get_audio_duration(&entry_path.to_string_lossy());
-                items.push(FolderItem {
-                    name,
-                    path: entry_path.to_string_lossy().to_string(),
-                    is_folder: false,
-                    track_count: 0,
-                    duration,
-                });
-            }
+    // Ignore files between the library root and this folder apply too
+    let scanner = scanfilter::Scanner::new(&root_buf, &path_buf);
+    let (folders, files) = scanner.entries(&path_buf);
+    let subfolder_scanner = scanner.descend(&path_buf);
+    
+    for folder in folders {
+        let track_count = count_audio_files(&subfolder_scanner, &folder);
+        if track_count > 0 {
+            items.push(FolderItem {
+                name: folder
+                    .file_name()
+                    .map(|n| n.to_string_lossy().to_string())
+                    .unwrap_or_default(),
+                path: paths::to_string(&folder),
+                is_folder: true,
+                track_count,
+                duration: None,
+            });
         }
     }
+    let mut files: Vec<String> = files.iter().map(|p| paths::to_string(p)).collect();
+    
+    // Albums with a cue sheet are listed as their tracks
+    let sorter = sortkey::Sorter::from_config();
+    files.sort_by_cached_key(|p| sorter.path_key(p));
+    for file in cue::expand(files) {
+        items.push(FolderItem {
+            name: track_name(&file),
+            duration: get_audio_duration(&file),
+            path: file,
+            is_folder: false,
+            track_count: 0,
+        });
+    }
     
-    items.sort_by(|a, b| {
-        match (a.is_folder, b.is_folder) {
-            (true, false) => std::cmp::Ordering::Less,
-            (false, true) => std::cmp::Ordering::Greater,
-            _ => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
-        }
-    });
+    // Folders first
+    items.sort_by_cached_key(|item| (!item.is_folder, sorter.key(&item.name)));
     
     Ok(FolderContents {
         path,
@@ -880,11 +1229,11 @@ fn browse_folder(path: String, root_path: String) -> Result<FolderContents, Stri
     })
 }
 
-fn count_audio_files(path: &PathBuf) -> usize {
-    WalkDir::new(path)
-        .into_iter()
-        .filter_map(|e| e.ok())
-        .filter(|e| e.path().is_file() && is_audio_file(&e.path().to_path_buf()))
+fn count_audio_files(scanner: &scanfilter::Scanner, path: &std::path::Path) -> usize {
+    scanner
+        .files(path)
+        .iter()
+        .filter(|p| formats::is_supported(p))
         .count()
 }
 
@@ -912,23 +1261,10 @@ fn set_audio_device(device_name: String, state: State<AppState>) {
 }
 
 #[tauri::command]
-fn scan_metadata(tracks: Vec<String>) -> Result<Vec<TrackMeta>, String> {
-    // Check cache first - build a set of already-cached paths
+fn scan_metadata(tracks: Vec<String>, state: State<AppState>) -> Result<Vec<TrackMeta>, String> {
+    // Check cache first and scan only what's missing
     let mut cache = load_metadata_cache();
-    let cached_paths: std::collections::HashSet<String> = cache.tracks.iter().map(|t| t.path.clone()).collect();
-    
-    let mut new_tracks = Vec::new();
-    for path in &tracks {
-        if !cached_paths.contains(path) {
-            new_tracks.push(extract_track_meta(path));
-        }
-    }
-    
-    // Merge new tracks into cache
-    if !new_tracks.is_empty() {
-        cache.tracks.extend(new_tracks);
-        let _ = save_metadata_cache(&cache);
-    }
+    cache_missing_tracks(&state, &mut cache, &tracks);
     
     // Return only metadata for the requested tracks
     let requested: std::collections::HashSet<&String> = tracks.iter().collect();
@@ -941,39 +1277,35 @@ fn scan_metadata(tracks: Vec<String>) -> Result<Vec<TrackMeta>, String> {
 }
 
 #[tauri::command]
-fn get_artists(tracks: Vec<String>) -> Result<Vec<ArtistInfo>, String> {
+fn get_artists(tracks: Vec<String>, state: State<AppState>) -> Result<Vec<ArtistInfo>, String> {
     // Load cache, scan any uncached tracks
     let mut cache = load_metadata_cache();
-    let cached_paths: std::collections::HashSet<String> = cache.tracks.iter().map(|t| t.path.clone()).collect();
-    
-    let mut new_tracks = Vec::new();
-    for path in &tracks {
-        if !cached_paths.contains(path) {
-            new_tracks.push(extract_track_meta(path));
-        }
-    }
-    
-    if !new_tracks.is_empty() {
-        cache.tracks.extend(new_tracks);
-        let _ = save_metadata_cache(&cache);
-    }
+    cache_missing_tracks(&state, &mut cache, &tracks);
     
-    // Build artist list from requested tracks
+    // Build artist list from requested tracks, tracking whether any track carries the name in its tags
     let requested: std::collections::HashSet<&String> = tracks.iter().collect();
-    let mut artist_counts: std::collections::HashMap<String, usize> = std::collections::HashMap::new();
+    let mut artist_counts: std::collections::HashMap<String, (usize, bool)> = std::collections::HashMap::new();
     
     for track in &cache.tracks {
         if requested.contains(&track.path) {
             let artist_name = track.artist.clone().unwrap_or_else(|| "Unknown Artist".to_string());
-            *artist_counts.entry(artist_name).or_insert(0) += 1;
+            let from_tags = track.artist.is_some() && !track.inferred.iter().any(|f| f == "artist");
+            let entry = artist_counts.entry(artist_name).or_insert((0, false));
+            entry.0 += 1;
+            entry.1 |= from_tags;
         }
     }
     
     let mut artists: Vec<ArtistInfo> = artist_counts.into_iter()
-        .map(|(name, track_count)| ArtistInfo { name, track_count })
+        .map(|(name, (track_count, from_tags))| ArtistInfo {
+            inferred: !from_tags && name != "Unknown Artist",
+            name,
+            track_count,
+        })
         .collect();
     
-    artists.sort_by(|a, b| a.name.to_lowercase().cmp(&b.name.to_lowercase()));
+    let sorter = sortkey::Sorter::from_config();
+    artists.sort_by_cached_key(|a| sorter.key(&a.name));
     
     Ok(artists)
 }
@@ -990,23 +1322,20 @@ fn get_artist_tracks(artist: String, tracks: Vec<String>) -> Result<Vec<TrackInf
         })
         .enumerate()
         .map(|(i, t)| {
-            let name = t.title.clone().unwrap_or_else(|| {
-                PathBuf::from(&t.path)
-                    .file_name()
-                    .map(|n| n.to_string_lossy().to_string())
-                    .unwrap_or_default()
-            });
+            let name = t.title.clone().unwrap_or_else(|| track_name(&t.path));
             let duration = get_audio_duration(&t.path);
             TrackInfo {
                 path: t.path.clone(),
                 name,
                 index: i,
                 duration,
+                missing: false,
             }
         })
         .collect();
     
-    result.sort_by(|a, b| a.name.to_lowercase().cmp(&b.name.to_lowercase()));
+    let sorter = sortkey::Sorter::from_config();
+    result.sort_by_cached_key(|t| sorter.key(&t.name));
     // Re-index after sort
     for (i, track) in result.iter_mut().enumerate() {
         track.index = i;
@@ -1016,43 +1345,67 @@ fn get_artist_tracks(artist: String, tracks: Vec<String>) -> Result<Vec<TrackInf
 }
 
 #[tauri::command]
-fn play_track(index: usize, skip_secs: Option<u64>, state: State<AppState>) -> Result<TrackInfo, String> {
-    let playlist = state.playlist.lock().unwrap();
-    
-    if index >= playlist.len() {
+fn play_track(index: usize, skip_secs: Option<u64>, state: State<AppState>) -> Result<StartedTrack, String> {
+    if index >= state.playlist.lock().unwrap().len() {
         return Err("Invalid track index".to_string());
     }
+    start_track(&state, index, skip_secs)
+}
+
+// Start the playlist track at `index`, from `skip_secs` or where a long track was left off
+fn start_track(state: &AppState, index: usize, skip_secs: Option<u64>) -> Result<StartedTrack, String> {
+    let path = state.playlist.lock().unwrap()[index].clone();
     
-    let path = playlist[index].clone();
-    drop(playlist);
-    
-    let duration = get_audio_duration(&path);
+    if !cue::exists(&path) {
+        return Err(format!("{} is unavailable (:repair to locate it)", track_name(&path)));
+    }
+    // Books are loaded here rather than on the audio thread: measuring their parts the first
+    // time takes a while, and the audio thread would stop answering meanwhile
+    let book = if book::is_book(&path) {
+        Some(book::load(&path).ok_or("No playable audio files in this book")?)
+    } else {
+        formats::check_playable(&cue::backing_file(&path))?;
+        None
+    };
+    let duration = match &book {
+        Some(book) => Some(book.duration_ms / 1000),
+        None => get_audio_duration(&path),
+    };
     *state.current_duration.lock().unwrap() = duration;
     
     let volume = *state.volume.lock().unwrap();
-    let skip = skip_secs.unwrap_or(0);
-    state.player.send(AudioCommand::Play(path.clone(), volume, skip));
+    let skip = resume::start_position(&path, duration, skip_secs);
+    book::apply_speed(state, &path);
+    // A book starts the sleep timer it was last listened with
+    if let Some(minutes) = book.as_ref().and_then(|_| book::settings(&path).sleep_minutes) {
+        sleep::start_for_book(state, minutes);
+    }
+    send_next_hint(state, index);
+    state.player.send(AudioCommand::Play(path.clone(), book, volume, skip));
+    alarms::stop_ringing(state);
     
     *state.current_index.lock().unwrap() = index;
     *state.is_playing.lock().unwrap() = true;
     *state.is_paused.lock().unwrap() = false;
     
-    let name = PathBuf::from(&path)
-        .file_name()
-        .map(|n| n.to_string_lossy().to_string())
-        .unwrap_or_default();
+    let name = track_name(&path);
     
     *state.current_track.lock().unwrap() = Some(name.clone());
+    chapters::load_for(state, &path);
     
     // Update media controls
-    state.update_media_metadata(&name, duration);
+    state.update_media_metadata(&name, duration, None);
     state.update_media_playback(true, false);
     
-    Ok(TrackInfo {
-        path,
-        name,
-        index,
-        duration,
+    Ok(StartedTrack {
+        track: TrackInfo {
+            path,
+            name,
+            index,
+            duration,
+            missing: false,
+        },
+        start_secs: skip,
     })
 }
 
@@ -1081,98 +1434,45 @@ fn toggle_pause(state: State<AppState>) -> Result<bool, String> {
 
 #[tauri::command]
 fn stop(state: State<AppState>) -> Result<(), String> {
+    stop_playback(&state);
+    Ok(())
+}
+
+fn stop_playback(state: &AppState) {
     state.player.send(AudioCommand::Stop);
+    alarms::stop_ringing(state);
     *state.is_playing.lock().unwrap() = false;
     *state.is_paused.lock().unwrap() = false;
     *state.current_track.lock().unwrap() = None;
     state.update_media_playback(false, false);
-    Ok(())
 }
 
-#[tauri::command]
-fn next_track(state: State<AppState>) -> Result<TrackInfo, String> {
-    let playlist_len = state.playlist.lock().unwrap().len();
-    if playlist_len == 0 {
+// The nearest playlist track after (or before) the current one whose file is there,
+// wrapping around; missing entries stay in the list until repaired, and are skipped
+fn step_available(state: &AppState, forward: bool) -> Result<usize, String> {
+    let playlist = state.playlist.lock().unwrap();
+    let len = playlist.len();
+    if len == 0 {
         return Err("Playlist is empty".to_string());
     }
     
     let current = *state.current_index.lock().unwrap();
-    let next_index = (current + 1) % playlist_len;
-    
-    let playlist = state.playlist.lock().unwrap();
-    let path = playlist[next_index].clone();
-    drop(playlist);
-    
-    let duration = get_audio_duration(&path);
-    *state.current_duration.lock().unwrap() = duration;
-    
-    let volume = *state.volume.lock().unwrap();
-    state.player.send(AudioCommand::Play(path.clone(), volume, 0));
-    
-    *state.current_index.lock().unwrap() = next_index;
-    *state.is_playing.lock().unwrap() = true;
-    *state.is_paused.lock().unwrap() = false;
-    
-    let name = PathBuf::from(&path)
-        .file_name()
-        .map(|n| n.to_string_lossy().to_string())
-        .unwrap_or_default();
-    
-    *state.current_track.lock().unwrap() = Some(name.clone());
-    
-    // Update media controls
-    state.update_media_metadata(&name, duration);
-    state.update_media_playback(true, false);
-    
-    Ok(TrackInfo {
-        path,
-        name,
-        index: next_index,
-        duration,
-    })
+    (1..=len)
+        .map(|step| if forward { (current + step) % len } else { (current + len - step) % len })
+        .find(|&i| cue::exists(&playlist[i]))
+        .ok_or_else(|| "No track in the playlist is available (:repair to locate them)".to_string())
+}
+
+#[tauri::command]
+fn next_track(state: State<AppState>) -> Result<TrackInfo, String> {
+    let index = step_available(&state, true)?;
+    start_track(&state, index, None).map(|started| started.track)
 }
 
 #[tauri::command]
 fn prev_track(state: State<AppState>) -> Result<TrackInfo, String> {
-    let playlist_len = state.playlist.lock().unwrap().len();
-    if playlist_len == 0 {
-        return Err("Playlist is empty".to_string());
-    }
-    
-    let current = *state.current_index.lock().unwrap();
-    let prev_index = if current == 0 { playlist_len - 1 } else { current - 1 };
-    
-    let playlist = state.playlist.lock().unwrap();
-    let path = playlist[prev_index].clone();
-    drop(playlist);
-    
-    let duration = get_audio_duration(&path);
-    *state.current_duration.lock().unwrap() = duration;
-    
-    let volume = *state.volume.lock().unwrap();
-    state.player.send(AudioCommand::Play(path.clone(), volume, 0));
-    
-    *state.current_index.lock().unwrap() = prev_index;
-    *state.is_playing.lock().unwrap() = true;
-    *state.is_paused.lock().unwrap() = false;
-    
-    let name = PathBuf::from(&path)
-        .file_name()
-        .map(|n| n.to_string_lossy().to_string())
-        .unwrap_or_default();
-    
-    *state.current_track.lock().unwrap() = Some(name.clone());
-    
-    // Update media controls
-    state.update_media_metadata(&name, duration);
-    state.update_media_playback(true, false);
-    
-    Ok(TrackInfo {
-        path,
-        name,
-        index: prev_index,
-        duration,
-    })
+    let index = step_available(&state, false)?;
+    start_track(&state, index, None).map(|started| started.track)
 }
 
 #[tauri::command]
@@ -1187,15 +1487,20 @@ fn set_volume(volume: f32, state: State<AppState>) -> Result<f32, String> {
 fn set_speed(speed: f32, state: State<AppState>) -> Result<f32, String> {
     let clamped = speed.clamp(0.25, 3.0);
     state.player.send(AudioCommand::SetSpeed(clamped));
+    book::remember_speed(&state, clamped);
     Ok(clamped)
 }
 
 #[tauri::command]
 fn get_status(state: State<AppState>) -> PlayerStatus {
+    let chapter = chapters::current_chapter(&state);
+    let is_playing = *state.is_playing.lock().unwrap();
+    // The sleep timer stops playback at this track's end; the next one mustn't start first
+    let is_finished = is_playing && state.player.is_finished() && !sleep::holds_track_end(&state);
     PlayerStatus {
-        is_playing: *state.is_playing.lock().unwrap(),
+        is_playing,
         is_paused: *state.is_paused.lock().unwrap(),
-        is_finished: state.player.is_finished(),
+        is_finished,
         current_track: state.current_track.lock().unwrap().clone(),
         current_index: *state.current_index.lock().unwrap(),
         volume: *state.volume.lock().unwrap(),
@@ -1203,6 +1508,9 @@ fn get_status(state: State<AppState>) -> PlayerStatus {
         playlist_length: state.playlist.lock().unwrap().len(),
         elapsed: state.player.get_elapsed(),
         duration: *state.current_duration.lock().unwrap(),
+        chapter_index: chapter.as_ref().map(|(i, _)| *i),
+        chapter_title: chapter.map(|(_, title)| title),
+        sleep: sleep::status(&state),
     }
 }
 
@@ -1285,31 +1593,25 @@ fn load_playlist(name: String, state: State<AppState>) -> Result<Vec<TrackInfo>,
     let content = fs::read_to_string(&path).map_err(|_| "Playlist not found")?;
     let saved: SavedPlaylist = serde_json::from_str(&content).map_err(|e| e.to_string())?;
     
-    // Filter out tracks that no longer exist
-    let valid_tracks: Vec<String> = saved.tracks
-        .into_iter()
-        .filter(|p| PathBuf::from(p).exists())
-        .collect();
-    
-    let track_infos: Vec<TrackInfo> = valid_tracks
+    // Tracks that no longer exist stay listed, marked missing until :repair finds them
+    let track_infos: Vec<TrackInfo> = saved.tracks
         .iter()
         .enumerate()
         .map(|(i, p)| {
-            let name = PathBuf::from(p)
-                .file_name()
-                .map(|n| n.to_string_lossy().to_string())
-                .unwrap_or_default();
-            let duration = get_audio_duration(p);
+            let name = track_name(p);
+            let missing = !cue::exists(p);
+            let duration = if missing { None } else { get_audio_duration(p) };
             TrackInfo {
                 path: p.clone(),
                 name,
                 index: i,
                 duration,
+                missing,
             }
         })
         .collect();
     
-    *state.playlist.lock().unwrap() = valid_tracks;
+    *state.playlist.lock().unwrap() = saved.tracks;
     *state.current_index.lock().unwrap() = 0;
     
     Ok(track_infos)
@@ -1341,7 +1643,8 @@ fn list_playlists() -> Result<Vec<PlaylistInfo>, String> {
         }
     }
     
-    playlists.sort_by(|a, b| a.name.to_lowercase().cmp(&b.name.to_lowercase()));
+    let sorter = sortkey::Sorter::from_config();
+    playlists.sort_by_cached_key(|p| sorter.key(&p.name));
     Ok(playlists)
 }
 
@@ -1450,6 +1753,61 @@ fn get_config_dir() -> Option<PathBuf> {
     dirs::config_dir().map(|p| p.join("vi-music"))
 }
 
+// The settings.json values the backend reads. `:set no<x>` stores false whatever the
+// setting's type, so a value of the wrong type reads as that setting switched off
+// rather than discarding the whole file.
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+struct Settings {
+    #[serde(deserialize_with = "or_off")]
+    exclude: String, // Comma-separated gitignore patterns applied below every scanned folder
+    #[serde(deserialize_with = "or_off")]
+    skiphidden: bool,
+    #[serde(deserialize_with = "or_off")]
+    minsize: f64, // KB
+    #[serde(deserialize_with = "or_off")]
+    minduration: f64, // Seconds
+    #[serde(deserialize_with = "or_off")]
+    maxdepth: f64, // Folder levels below the scanned folder, 0 for no limit
+    #[serde(deserialize_with = "or_off")]
+    resumeminutes: f64, // Tracks at least this long remember their position, 0 for none
+    #[serde(deserialize_with = "or_off")]
+    resumefolders: String, // Comma-separated folders whose tracks always remember it
+    #[serde(deserialize_with = "or_off")]
+    sleepfade: f64, // Minutes the sleep timer fades out over before stopping, 0 for none
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            exclude: String::new(),
+            skiphidden: false,
+            minsize: 0.0,
+            minduration: 0.0,
+            maxdepth: 0.0,
+            resumeminutes: 20.0,
+            resumefolders: String::new(),
+            sleepfade: 1.0,
+        }
+    }
+}
+
+fn or_off<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: serde::de::DeserializeOwned + Default,
+{
+    let value = serde_json::Value::deserialize(deserializer)?;
+    Ok(serde_json::from_value(value).unwrap_or_default())
+}
+
+fn load_settings() -> Settings {
+    get_config_dir()
+        .and_then(|dir| fs::read_to_string(dir.join("settings.json")).ok())
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
 #[tauri::command]
 fn get_keybindings() -> Result<String, String> {
     let config_dir = get_config_dir().ok_or("Could not determine config directory")?;
@@ -1551,15 +1909,16 @@ fn remove_library_folder(folder: String) -> Result<Vec<String>, String> {
 
 #[tauri::command]
 fn scan_library_folder(folder: String) -> Result<Vec<TrackInfo>, String> {
-    let path = PathBuf::from(&folder);
+    let path = paths::from_string(&folder);
     if !path.exists() || !path.is_dir() {
-        return Err(format!("Folder not found: {}", folder));
+        return Err(format!("Folder not found: {}", path.display()));
     }
     
     let mut tracks = Vec::new();
     scan_folder_recursive(&path, &mut tracks);
     
-    tracks.sort_by(|a, b| a.name.to_lowercase().cmp(&b.name.to_lowercase()));
+    let sorter = sortkey::Sorter::from_config();
+    tracks.sort_by_cached_key(|t| sorter.key(&t.name));
     Ok(tracks)
 }
 
@@ -1569,54 +1928,50 @@ fn set_playlist(paths: Vec<String>, state: State<AppState>) {
     *playlist = paths;
 }
 
-fn scan_folder_recursive(dir: &PathBuf, tracks: &mut Vec<TrackInfo>) {
-    if let Ok(entries) = fs::read_dir(dir) {
-        for entry in entries.filter_map(|e| e.ok()) {
-            let path = entry.path();
-            if path.is_dir() {
-                scan_folder_recursive(&path, tracks);
-            } else if let Some(ext) = path.extension() {
-                let ext_lower = ext.to_string_lossy().to_lowercase();
-                if ext_lower == "mp3" || ext_lower == "flac" || ext_lower == "wav" || ext_lower == "ogg" || ext_lower == "m4a" || ext_lower == "aif" || ext_lower == "aiff" {
-                    if let Some(name) = path.file_name() {
-                        let path_str = path.to_string_lossy().to_string();
-                        let duration = get_audio_duration(&path_str);
-                        tracks.push(TrackInfo {
-                            name: name.to_string_lossy().to_string(),
-                            path: path_str,
-                            index: 0, // Will be set after sorting
-                            duration,
-                        });
-                    }
-                }
-            }
-        }
+fn scan_folder_recursive(dir: &std::path::Path, tracks: &mut Vec<TrackInfo>) {
+    let mut files: Vec<String> = scanfilter::Scanner::new(dir, dir)
+        .files(dir)
+        .iter()
+        .map(|p| paths::to_string(p))
+        .collect();
+    
+    let sorter = sortkey::Sorter::from_config();
+    files.sort_by_cached_key(|p| sorter.path_key(p));
+    for path_str in cue::expand(files) {
+        let duration = get_audio_duration(&path_str);
+        tracks.push(TrackInfo {
+            name: track_name(&path_str),
+            path: path_str,
+            index: 0, // Will be set after sorting
+            duration,
+            missing: false,
+        });
     }
 }
 
 #[tauri::command]
 fn reveal_in_explorer(path: String) -> Result<(), String> {
+    let path = paths::from_string(&cue::backing_file(&path));
     #[cfg(target_os = "windows")]
     {
         std::process::Command::new("explorer")
-            .args(["/select,", &path])
+            .arg("/select,")
+            .arg(&path)
             .spawn()
             .map_err(|e| e.to_string())?;
     }
     #[cfg(target_os = "macos")]
     {
         std::process::Command::new("open")
-            .args(["-R", &path])
+            .arg("-R")
+            .arg(&path)
             .spawn()
             .map_err(|e| e.to_string())?;
     }
     #[cfg(target_os = "linux")]
     {
         // Try common file managers with selection support
-        let parent = std::path::Path::new(&path)
-            .parent()
-            .map(|p| p.to_string_lossy().to_string())
-            .unwrap_or_else(|| path.clone());
+        let parent = path.parent().map(|p| p.to_path_buf()).unwrap_or_else(|| path.clone());
         std::process::Command::new("xdg-open")
             .arg(&parent)
             .spawn()
@@ -1625,6 +1980,28 @@ fn reveal_in_explorer(path: String) -> Result<(), String> {
     Ok(())
 }
 
+// Polls the playback position for features that follow it and emits their events
+fn spawn_playback_watcher(app: tauri::AppHandle) {
+    thread::spawn(move || {
+        let mut lyrics_watch = lyrics::LyricsWatch::default();
+        let mut chapter_watch = chapters::ChapterWatch::default();
+        let mut session_watch = session::SessionWatch::default();
+        let mut resume_watch = resume::ResumeWatch::default();
+        loop {
+            thread::sleep(Duration::from_millis(100));
+            let state = app.state::<AppState>();
+            let path = state.player.get_current_path().unwrap_or_default();
+            let elapsed_ms = state.player.get_elapsed_millis();
+            lyrics_watch.tick(&app, &state, &path, elapsed_ms);
+            chapter_watch.tick(&app, &state, &path, elapsed_ms);
+            session_watch.tick(&state);
+            resume_watch.tick(&state, &path, elapsed_ms);
+            sleep::SleepTimer::tick(&app, &state, &path, elapsed_ms);
+            alarms::tick(&app, &state);
+        }
+    });
+}
+
 fn main() {
     let app_state = AppState::new();
     
@@ -1652,6 +2029,24 @@ fn main() {
             delete_playlist,
             rename_playlist,
             create_playlist,
+            playlistfile::import_playlist,
+            playlistfile::export_playlist,
+            relocate::remap_library,
+            repair::plan_repair,
+            repair::apply_repair,
+            session::get_session,
+            session::set_session_state,
+            book::load_book,
+            sleep::set_sleep_timer,
+            alarms::list_alarms,
+            alarms::add_alarm,
+            alarms::remove_alarm,
+            alarms::toggle_alarm,
+            alarms::snooze_alarm,
+            marks::set_mark,
+            marks::get_mark,
+            marks::delete_mark,
+            marks::list_marks,
             add_tracks_to_playlist,
             get_keybindings,
             save_keybindings,
@@ -1668,8 +2063,39 @@ fn main() {
             get_artists,
             get_artist_tracks,
             reveal_in_explorer,
+            tags::write_tags,
+            tags::undo_tag_batch,
+            tags::get_infer_config,
+            tags::set_infer_config,
+            tags::write_inferred_tags,
+            organize::organize_files,
+            duplicates::find_duplicates,
+            duplicates::trash_duplicates,
+            fingerprint::find_recording,
+            fingerprint::export_fingerprints,
+            analysis::analyze_tracks,
+            analysis::tracks_in_tempo,
+            sortkey::sort_tracks,
+            sortkey::set_sort_ignore_articles,
+            cover::get_cover_art,
+            waveform::get_waveform,
+            waveform::cancel_waveform,
+            spectrum::set_spectrum,
+            spectrum::get_spectrum,
+            lyrics::get_lyrics,
+            lyrics::save_lyrics,
+            lyrics::set_lyrics_offset,
+            chapters::get_chapters,
+            chapters::next_chapter,
+            chapters::prev_chapter,
+            chapters::play_chapter,
         ])
+        .register_uri_scheme_protocol("cover", cover::handle_cover_protocol)
         .setup(|app| {
+            session::restore(&app.state::<AppState>());
+            spawn_playback_watcher(app.handle());
+            spectrum::spawn_worker(app.handle());
+            
             // Initialize media controls
             let window = app.get_window("main").expect("main window not found");
             
@@ -1780,6 +2206,13 @@ fn main() {
             
             Ok(())
         })
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app, event| {
+            if let tauri::RunEvent::Exit = event {
+                let state = app.state::<AppState>();
+                resume::record_current(&state);
+                session::save(&state);
+            }
+        });
 }
@@ -0,0 +1,149 @@
+// Vim-style marks, kept in marks.json in the config directory. Lowercase marks belong to
+// one track (every track has its own `a`); uppercase marks are global and remember the track.
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use tauri::State;
+
+use crate::relocate::write_atomic;
+use crate::{cue, get_config_dir, track_name, AppState};
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Mark {
+    key: char,
+    path: String,
+    position_ms: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    label: Option<String>,
+}
+
+impl Mark {
+    fn is_global(&self) -> bool {
+        self.key.is_ascii_uppercase()
+    }
+    
+    // Whether this is the mark `key` as seen from `path`
+    fn matches(&self, key: char, path: Option<&str>) -> bool {
+        self.key == key && (self.is_global() || Some(self.path.as_str()) == path)
+    }
+}
+
+#[derive(Serialize)]
+pub struct MarkInfo {
+    #[serde(flatten)]
+    mark: Mark,
+    name: String,
+    missing: bool,
+}
+
+impl From<Mark> for MarkInfo {
+    fn from(mark: Mark) -> Self {
+        Self {
+            name: track_name(&mark.path),
+            missing: !cue::exists(&mark.path),
+            mark,
+        }
+    }
+}
+
+pub fn get_marks_path() -> Option<PathBuf> {
+    get_config_dir().map(|p| p.join("marks.json"))
+}
+
+pub fn load_marks() -> Vec<Mark> {
+    get_marks_path()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+pub fn save_marks(marks: &[Mark]) -> Result<(), String> {
+    let path = get_marks_path().ok_or("Could not determine config directory")?;
+    fs::create_dir_all(path.parent().unwrap()).map_err(|e| e.to_string())?;
+    let content = serde_json::to_string_pretty(marks).map_err(|e| e.to_string())?;
+    write_atomic(&path, &content)
+}
+
+// Point marks at the new paths of moved tracks; returns the updated list when any changed
+pub fn remap_marks(remap: impl Fn(&str) -> Option<String>) -> Option<Vec<Mark>> {
+    let mut marks = load_marks();
+    let mut changed = false;
+    for mark in marks.iter_mut() {
+        if let Some(path) = remap(&mark.path) {
+            mark.path = path;
+            changed = true;
+        }
+    }
+    changed.then_some(marks)
+}
+
+fn check_key(key: char) -> Result<(), String> {
+    if key.is_ascii_alphabetic() {
+        Ok(())
+    } else {
+        Err(format!("Invalid mark '{}' (use a-z, or A-Z for global marks)", key))
+    }
+}
+
+// Mark the playing track's current position, replacing the mark of the same name
+#[tauri::command]
+pub fn set_mark(key: char, label: Option<String>, state: State<AppState>) -> Result<MarkInfo, String> {
+    check_key(key)?;
+    let path = state
+        .player
+        .get_current_path()
+        .filter(|_| *state.is_playing.lock().unwrap())
+        .ok_or("No track playing to mark")?;
+    let mark = Mark {
+        key,
+        position_ms: state.player.get_elapsed_millis(),
+        label: label.map(|l| l.trim().to_string()).filter(|l| !l.is_empty()),
+        path,
+    };
+    
+    let mut marks = load_marks();
+    marks.retain(|m| !m.matches(key, Some(&mark.path)));
+    marks.push(mark.clone());
+    save_marks(&marks)?;
+    Ok(mark.into())
+}
+
+// The mark `key`; lowercase marks are looked up on `path`
+#[tauri::command]
+pub fn get_mark(key: char, path: Option<String>) -> Result<MarkInfo, String> {
+    check_key(key)?;
+    load_marks()
+        .into_iter()
+        .find(|m| m.matches(key, path.as_deref()))
+        .map(MarkInfo::from)
+        .ok_or_else(|| format!("No mark '{}'", key))
+}
+
+#[tauri::command]
+pub fn delete_mark(key: char, path: Option<String>) -> Result<(), String> {
+    check_key(key)?;
+    let mut marks = load_marks();
+    let count = marks.len();
+    marks.retain(|m| !m.matches(key, path.as_deref()));
+    if marks.len() == count {
+        return Err(format!("No mark '{}'", key));
+    }
+    save_marks(&marks)
+}
+
+// Every mark in the library: global ones first, then per track
+#[tauri::command]
+pub fn list_marks() -> Vec<MarkInfo> {
+    let mut marks = load_marks();
+    marks.sort_by(|a, b| {
+        b.is_global().cmp(&a.is_global()).then_with(|| {
+            if a.is_global() {
+                a.key.cmp(&b.key)
+            } else {
+                a.path.cmp(&b.path).then(a.key.cmp(&b.key))
+            }
+        })
+    });
+    marks.into_iter().map(MarkInfo::from).collect()
+}
@@ -0,0 +1,322 @@
+// Moving and renaming files into a folder structure built from their tags
+
+use lofty::{Accessor, ItemKey, TaggedFileExt};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::State;
+
+use crate::paths;
+use crate::relocate::rewrite_track_paths;
+use crate::{add_library_folder, get_library_folders, AppState};
+
+#[derive(Serialize, Clone)]
+pub struct OrganizeEntry {
+    from: String,
+    to: String,
+    status: String, // "move" | "unchanged" | "exists" | "duplicate" | "missing" | "moved" | "failed"
+    error: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct OrganizeReport {
+    dry_run: bool,
+    entries: Vec<OrganizeEntry>,
+    moved: usize,
+    conflicts: usize,
+    playlists_updated: usize,
+}
+
+// Tag values available to templates, keyed by placeholder name
+fn read_template_fields(path: &Path) -> HashMap<&'static str, String> {
+    let mut fields = HashMap::new();
+    
+    if let Ok(tagged_file) = lofty::read_from_path(path) {
+        if let Some(tag) = tagged_file.primary_tag().or_else(|| tagged_file.first_tag()) {
+            if let Some(v) = tag.artist() {
+                fields.insert("artist", v.to_string());
+            }
+            if let Some(v) = tag.get_string(&ItemKey::AlbumArtist) {
+                fields.insert("albumartist", v.to_string());
+            }
+            if let Some(v) = tag.album() {
+                fields.insert("album", v.to_string());
+            }
+            if let Some(v) = tag.title() {
+                fields.insert("title", v.to_string());
+            }
+            if let Some(v) = tag.genre() {
+                fields.insert("genre", v.to_string());
+            }
+            if let Some(v) = tag.year() {
+                fields.insert("year", v.to_string());
+            }
+            if let Some(v) = tag.track() {
+                fields.insert("track", v.to_string());
+            }
+            // Only multi-disc releases get a disc number, so "{disc}{track:02}" reads "03" or "103"
+            if let Some(v) = tag.disk() {
+                if tag.disk_total().map(|t| t > 1).unwrap_or(v > 1) {
+                    fields.insert("disc", v.to_string());
+                }
+            }
+        }
+    }
+    
+    // Fallbacks so every file still gets a usable path
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+    fields.entry("title").or_insert(stem);
+    let artist = fields.get("artist").cloned().unwrap_or_else(|| "Unknown Artist".to_string());
+    fields.entry("albumartist").or_insert_with(|| artist.clone());
+    fields.entry("artist").or_insert(artist);
+    fields.entry("album").or_insert_with(|| "Unknown Album".to_string());
+    let ext = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    fields.insert("ext", ext);
+    
+    fields
+}
+
+// Make a single path component safe on every platform we ship on
+pub fn sanitize_component(name: &str) -> String {
+    let cleaned: String = name
+        .chars()
+        .map(|c| match c {
+            '<' | '>' | ':' | '"' | '/' | '\\' | '|' | '?' | '*' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+    
+    // Windows rejects trailing dots and spaces
+    let mut cleaned = cleaned.trim().trim_end_matches('.').trim_end().to_string();
+    
+    let stem = cleaned.split('.').next().unwrap_or("").to_uppercase();
+    let reserved = matches!(stem.as_str(), "CON" | "PRN" | "AUX" | "NUL")
+        || ((stem.starts_with("COM") || stem.starts_with("LPT"))
+            && stem.len() == 4
+            && stem.chars().last().map(|c| c.is_ascii_digit()).unwrap_or(false));
+    if reserved {
+        cleaned.insert(0, '_');
+    }
+    
+    // Most filesystems cap a component at 255 bytes; shorten the stem so the extension survives
+    if cleaned.len() > 255 {
+        let ext_start = cleaned.rfind('.').filter(|i| *i > 0 && cleaned.len() - i <= 16);
+        let ext = ext_start.map(|i| cleaned.split_off(i)).unwrap_or_default();
+        let mut end = 255usize.saturating_sub(ext.len());
+        while !cleaned.is_char_boundary(end) {
+            end -= 1;
+        }
+        cleaned.truncate(end);
+        cleaned.push_str(&ext);
+    }
+    
+    if cleaned.is_empty() {
+        "_".to_string()
+    } else {
+        cleaned
+    }
+}
+
+// Render one template component such as "{year} - {album}" or "{track:02} {title}.{ext}",
+// before it is made safe as a file name
+fn render_text(template: &str, fields: &HashMap<&'static str, String>) -> Result<String, String> {
+    let mut out = String::new();
+    let mut rest = template;
+    
+    while let Some(start) = rest.find('{') {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let end = after.find('}').ok_or_else(|| format!("Unclosed '{{' in template: {}", template))?;
+        let placeholder = &after[..end];
+        rest = &after[end + 1..];
+        
+        let (name, spec) = match placeholder.split_once(':') {
+            Some((n, s)) => (n.trim().to_lowercase(), Some(s.trim())),
+            None => (placeholder.trim().to_lowercase(), None),
+        };
+        let known = [
+            "artist", "albumartist", "album", "title", "genre", "year", "track", "disc", "ext",
+        ];
+        if !known.contains(&name.as_str()) {
+            return Err(format!("Unknown template field: {{{}}}", name));
+        }
+        
+        let value = fields.get(name.as_str()).cloned().unwrap_or_default();
+        let value = match spec {
+            Some(spec) if !value.is_empty() => {
+                let width: usize = spec.trim_start_matches('0').parse().unwrap_or(0);
+                format!("{:0>width$}", value, width = width)
+            }
+            _ => value,
+        };
+        // Tag values may contain separators ("AC/DC"); they must not create folders
+        out.push_str(&value.replace(['/', '\\'], "_"));
+    }
+    out.push_str(rest);
+    
+    // Missing fields leave dangling separators, e.g. " - Album" without a year
+    Ok(out.trim_matches(|c: char| c == ' ' || c == '-').to_string())
+}
+
+fn render_path(template: &str, destination: &Path, source: &Path) -> Result<PathBuf, String> {
+    let fields = read_template_fields(source);
+    let mut target = destination.to_path_buf();
+    let parts: Vec<&str> = template.split(['/', '\\']).filter(|p| !p.trim().is_empty()).collect();
+    if parts.is_empty() {
+        return Err("Template is empty".to_string());
+    }
+    let last = parts.len() - 1;
+    for (i, part) in parts.into_iter().enumerate() {
+        let mut name = render_text(part, &fields)?;
+        // The extension is appended, not set: titles such as "Mr. Brightside" hold dots of their own
+        let ext = fields.get("ext").map(String::as_str).unwrap_or("");
+        if i == last && !template.contains("{ext}") && !ext.is_empty() {
+            let stem = if name.is_empty() { "_" } else { name.as_str() };
+            name = format!("{}.{}", stem, ext);
+        }
+        target.push(sanitize_component(&name));
+    }
+    Ok(target)
+}
+
+// Rename, falling back to copy + delete when the target is on another volume
+fn move_file(from: &Path, to: &Path) -> Result<(), String> {
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    if fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+    fs::copy(from, to).map_err(|e| e.to_string())?;
+    fs::remove_file(from).map_err(|e| {
+        let _ = fs::remove_file(to);
+        e.to_string()
+    })
+}
+
+// Remove directories left empty by a move, walking up until a non-empty one
+// or one of the protected roots
+fn prune_empty_dirs(start: Option<&Path>, protected: &[PathBuf]) {
+    let mut dir = start.map(|d| d.to_path_buf());
+    while let Some(d) = dir {
+        if protected.iter().any(|p| p == &d) {
+            break;
+        }
+        let is_empty = fs::read_dir(&d).map(|mut e| e.next().is_none()).unwrap_or(false);
+        if !is_empty || fs::remove_dir(&d).is_err() {
+            break;
+        }
+        dir = d.parent().map(|p| p.to_path_buf());
+    }
+}
+
+#[tauri::command]
+pub fn organize_files(
+    paths: Vec<String>,
+    template: String,
+    destination: String,
+    dry_run: bool,
+    state: State<AppState>,
+) -> Result<OrganizeReport, String> {
+    let destination = paths::from_string(&destination);
+    if !destination.is_dir() {
+        return Err(format!("Destination folder not found: {}", destination.display()));
+    }
+    
+    // Plan every move first so conflicts are known before anything is touched
+    let mut entries = Vec::new();
+    let mut claimed: HashSet<String> = HashSet::new();
+    for path in &paths {
+        let source = paths::from_string(path);
+        if !source.is_file() {
+            entries.push(OrganizeEntry {
+                from: path.clone(),
+                to: String::new(),
+                status: "missing".to_string(),
+                error: None,
+            });
+            continue;
+        }
+        
+        let target = render_path(&template, &destination, &source)?;
+        let to = paths::to_string(&target);
+        // Case-insensitive filesystems treat "Album" and "album" as the same folder
+        let key = to.to_lowercase();
+        
+        let status = if target == source {
+            "unchanged"
+        } else if !claimed.insert(key) {
+            "duplicate"
+        } else if target.exists() && to.to_lowercase() != path.to_lowercase() {
+            "exists"
+        } else {
+            "move"
+        };
+        entries.push(OrganizeEntry {
+            from: path.clone(),
+            to,
+            status: status.to_string(),
+            error: None,
+        });
+    }
+    
+    let conflicts = entries
+        .iter()
+        .filter(|e| matches!(e.status.as_str(), "exists" | "duplicate" | "missing"))
+        .count();
+    
+    if dry_run {
+        return Ok(OrganizeReport {
+            dry_run,
+            entries,
+            moved: 0,
+            conflicts,
+            playlists_updated: 0,
+        });
+    }
+    
+    let library_folders = get_library_folders().unwrap_or_default();
+    let mut protected: Vec<PathBuf> = library_folders.iter().map(|f| paths::from_string(f)).collect();
+    protected.push(destination.clone());
+    
+    let mut moves = HashMap::new();
+    for entry in entries.iter_mut().filter(|e| e.status == "move") {
+        let from = paths::from_string(&entry.from);
+        match move_file(&from, &paths::from_string(&entry.to)) {
+            Ok(()) => {
+                entry.status = "moved".to_string();
+                moves.insert(entry.from.clone(), entry.to.clone());
+                prune_empty_dirs(from.parent(), &protected);
+            }
+            Err(e) => {
+                entry.status = "failed".to_string();
+                entry.error = Some(e);
+            }
+        }
+    }
+    
+    let playlists_updated = rewrite_track_paths(&moves, &state)?;
+    crate::tags::relocate_undo(&moves, &state);
+    
+    // Keep moved files in the library when they land outside every library folder
+    let inside_library = library_folders.iter().any(|f| destination.starts_with(paths::from_string(f)));
+    if !moves.is_empty() && !library_folders.is_empty() && !inside_library {
+        add_library_folder(paths::to_string(&destination))?;
+    }
+    
+    Ok(OrganizeReport {
+        dry_run,
+        moved: moves.len(),
+        entries,
+        conflicts,
+        playlists_updated,
+    })
+}
@@ -0,0 +1,79 @@
+// Track paths as strings that round-trip any OS path. UTF-8 paths are kept as they
+// are; anything else is stored as "raw:" followed by the percent-escaped native bytes
+// (UTF-16LE code units on Windows), so playlists and caches can still name the file.
+
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+const RAW_PREFIX: &str = "raw:";
+
+#[cfg(unix)]
+fn native_bytes(path: &Path) -> Vec<u8> {
+    use std::os::unix::ffi::OsStrExt;
+    path.as_os_str().as_bytes().to_vec()
+}
+
+#[cfg(unix)]
+fn from_native_bytes(bytes: Vec<u8>) -> OsString {
+    use std::os::unix::ffi::OsStringExt;
+    OsString::from_vec(bytes)
+}
+
+#[cfg(windows)]
+fn native_bytes(path: &Path) -> Vec<u8> {
+    use std::os::windows::ffi::OsStrExt;
+    path.as_os_str().encode_wide().flat_map(|unit| unit.to_le_bytes()).collect()
+}
+
+#[cfg(windows)]
+fn from_native_bytes(bytes: Vec<u8>) -> OsString {
+    use std::os::windows::ffi::OsStringExt;
+    let units: Vec<u16> = bytes.chunks_exact(2).map(|pair| u16::from_le_bytes([pair[0], pair[1]])).collect();
+    OsString::from_wide(&units)
+}
+
+// The string stored for a path
+pub fn to_string(path: &Path) -> String {
+    match path.to_str() {
+        Some(s) if !s.starts_with(RAW_PREFIX) => s.to_string(),
+        _ => {
+            let mut encoded = String::from(RAW_PREFIX);
+            for byte in native_bytes(path) {
+                if byte.is_ascii_graphic() && byte != b'%' || byte == b' ' {
+                    encoded.push(byte as char);
+                } else {
+                    encoded.push_str(&format!("%{:02X}", byte));
+                }
+            }
+            encoded
+        }
+    }
+}
+
+// The OS path behind a stored string, including cue sheet paths of virtual tracks
+pub fn from_string(s: &str) -> PathBuf {
+    let encoded = match s.strip_prefix(RAW_PREFIX) {
+        Some(encoded) => encoded.as_bytes(),
+        None => return PathBuf::from(s),
+    };
+    let mut bytes = Vec::with_capacity(encoded.len());
+    let mut i = 0;
+    while i < encoded.len() {
+        let escaped = encoded
+            .get(i + 1..i + 3)
+            .filter(|_| encoded[i] == b'%')
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                bytes.push(byte);
+                i += 3;
+            }
+            None => {
+                bytes.push(encoded[i]);
+                i += 1;
+            }
+        }
+    }
+    PathBuf::from(from_native_bytes(bytes))
+}
@@ -0,0 +1,448 @@
+// Playlist files shared with other players: M3U/M3U8 (with EXTINF), PLS and XSPF
+
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use tauri::State;
+
+use crate::{
+    book, cache_missing_tracks, cue, get_audio_duration, get_playlists_dir, load_metadata_cache, paths, sanitize_filename,
+    track_name, AppState, SavedPlaylist, TrackMeta,
+};
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Format {
+    M3u,
+    M3u8,
+    Pls,
+    Xspf,
+}
+
+impl Format {
+    fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "m3u" => Some(Format::M3u),
+            "m3u8" => Some(Format::M3u8),
+            "pls" => Some(Format::Pls),
+            "xspf" => Some(Format::Xspf),
+            _ => None,
+        }
+    }
+    
+    fn from_path(path: &Path) -> Option<Self> {
+        Self::from_name(&path.extension()?.to_string_lossy())
+    }
+    
+    fn extension(self) -> &'static str {
+        match self {
+            Format::M3u => "m3u",
+            Format::M3u8 => "m3u8",
+            Format::Pls => "pls",
+            Format::Xspf => "xspf",
+        }
+    }
+}
+
+pub fn is_playlist_file(path: &Path) -> bool {
+    path.is_file() && Format::from_path(path).is_some()
+}
+
+// Windows-1252 characters for 0x80..0x9F; the rest of the upper half matches Latin-1
+const CP1252_HIGH: [char; 32] = [
+    '€', '\u{81}', '‚', 'ƒ', '„', '…', '†', '‡', 'ˆ', '‰', 'Š', '‹', 'Œ', '\u{8D}', 'Ž', '\u{8F}',
+    '\u{90}', '‘', '’', '“', '”', '•', '–', '—', '˜', '™', 'š', '›', 'œ', '\u{9D}', 'ž', 'Ÿ',
+];
+
+// Text of a playlist file: a byte order mark wins, then UTF-8 if the bytes are valid,
+// otherwise Windows-1252, which is what older players write into plain .m3u and .pls files
+fn decode_text(bytes: &[u8]) -> String {
+    if let Some(rest) = bytes.strip_prefix(&[0xEF, 0xBB, 0xBF]) {
+        return String::from_utf8_lossy(rest).to_string();
+    }
+    let utf16 = |rest: &[u8], big_endian: bool| {
+        let units: Vec<u16> = rest
+            .chunks_exact(2)
+            .map(|pair| if big_endian { u16::from_be_bytes([pair[0], pair[1]]) } else { u16::from_le_bytes([pair[0], pair[1]]) })
+            .collect();
+        String::from_utf16_lossy(&units)
+    };
+    if let Some(rest) = bytes.strip_prefix(&[0xFF, 0xFE]) {
+        return utf16(rest, false);
+    }
+    if let Some(rest) = bytes.strip_prefix(&[0xFE, 0xFF]) {
+        return utf16(rest, true);
+    }
+    match std::str::from_utf8(bytes) {
+        Ok(text) => text.to_string(),
+        Err(_) => bytes
+            .iter()
+            .map(|&b| match b {
+                0x80..=0x9F => CP1252_HIGH[(b - 0x80) as usize],
+                _ => b as char,
+            })
+            .collect(),
+    }
+}
+
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = bytes
+            .get(i + 1..i + 3)
+            .filter(|_| bytes[i] == b'%')
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+fn percent_encode(s: &str) -> String {
+    let mut encoded = String::new();
+    for byte in s.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-._~/:".contains(&byte) {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    encoded
+}
+
+fn xml_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+fn xml_unescape(s: &str) -> String {
+    let mut text = String::new();
+    let mut rest = s;
+    while let Some(start) = rest.find('&') {
+        text.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let end = match rest.find(';') {
+            Some(end) => end,
+            None => break,
+        };
+        let entity = &rest[1..end];
+        let ch = match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ => entity
+                .strip_prefix("#x")
+                .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                .or_else(|| entity.strip_prefix('#').and_then(|dec| dec.parse().ok()))
+                .and_then(char::from_u32),
+        };
+        match ch {
+            Some(ch) => {
+                text.push(ch);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                text.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    text.push_str(rest);
+    text
+}
+
+// A playlist entry as a stored track path: file:// URLs are decoded, relative entries are
+// taken from the playlist's folder, and streams are skipped
+fn resolve(entry: &str, base: &Path, is_url: bool) -> Option<String> {
+    let entry = entry.trim();
+    if entry.is_empty() {
+        return None;
+    }
+    let url = entry.get(..7).filter(|scheme| scheme.eq_ignore_ascii_case("file://")).map(|_| &entry[7..]);
+    let location = if let Some(rest) = url {
+        // file:///C:/Music on Windows, file:///home/me on unix, file://server/share for shares
+        let rest = percent_decode(rest);
+        let rest = rest.strip_prefix("localhost").unwrap_or(&rest);
+        if !cfg!(windows) || rest.starts_with("//") {
+            rest.to_string()
+        } else {
+            match rest.strip_prefix('/') {
+                Some(local) if local.chars().nth(1) == Some(':') => local.to_string(),
+                Some(_) => rest.to_string(),
+                None => format!("//{}", rest),
+            }
+        }
+    } else if entry.contains("://") {
+        return None;
+    } else if is_url {
+        percent_decode(entry)
+    } else {
+        entry.to_string()
+    };
+    
+    // Playlists written on Windows separate with backslashes
+    let location = if cfg!(windows) { location } else { location.replace('\\', "/") };
+    let path = PathBuf::from(&location);
+    let path = if path.is_absolute() { path } else { base.join(path) };
+    
+    // "../Album/01.flac" should name the same track as the library listing does
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::ParentDir if normalized.file_name().is_some() => {
+                normalized.pop();
+            }
+            Component::CurDir => {}
+            other => normalized.push(other),
+        }
+    }
+    Some(paths::to_string(&normalized))
+}
+
+fn parse_m3u(text: &str, base: &Path) -> Vec<String> {
+    text.lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| resolve(line, base, false))
+        .collect()
+}
+
+fn parse_pls(text: &str, base: &Path) -> Vec<String> {
+    let mut entries: Vec<(u32, String)> = text
+        .lines()
+        .filter_map(|line| {
+            let (key, value) = line.trim().split_once('=')?;
+            let number = key.trim().to_lowercase().strip_prefix("file")?.parse().ok()?;
+            Some((number, resolve(value, base, false)?))
+        })
+        .collect();
+    entries.sort_by_key(|(number, _)| *number);
+    entries.into_iter().map(|(_, path)| path).collect()
+}
+
+fn parse_xspf(text: &str, base: &Path) -> Vec<String> {
+    let mut entries = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("<location>") {
+        rest = &rest[start + "<location>".len()..];
+        let end = match rest.find("</location>") {
+            Some(end) => end,
+            None => break,
+        };
+        if let Some(path) = resolve(&xml_unescape(&rest[..end]), base, true) {
+            entries.push(path);
+        }
+        rest = &rest[end..];
+    }
+    entries
+}
+
+// Track paths listed in a playlist file, in order. A listed .cue sheet stands for its tracks.
+pub fn read(path: &Path) -> Result<Vec<String>, String> {
+    let bytes = fs::read(path).map_err(|e| e.to_string())?;
+    let text = decode_text(&bytes);
+    let base = path.parent().unwrap_or(Path::new(""));
+    
+    // Sniff the content too, since playlists get saved under the wrong extension
+    let trimmed = text.trim_start();
+    let entries = if trimmed.starts_with("<?xml") || trimmed.starts_with("<playlist") {
+        parse_xspf(&text, base)
+    } else if trimmed.to_lowercase().starts_with("[playlist]") {
+        parse_pls(&text, base)
+    } else {
+        parse_m3u(&text, base)
+    };
+    
+    Ok(entries
+        .into_iter()
+        .flat_map(|entry| {
+            let file = paths::from_string(&entry);
+            if cue::is_cue_file(&file) {
+                cue::sheet_tracks(&file)
+            } else {
+                vec![entry]
+            }
+        })
+        .collect())
+}
+
+struct Entry {
+    location: String,
+    relative: bool, // Location is relative to the playlist file, for tracks inside its folder
+    title: String,
+    duration: Option<u64>,
+}
+
+// Other players know neither virtual cue tracks nor book folders, so a cue track is listed
+// as the file holding its sheet, once however many of its tracks the playlist has, and a
+// book as its parts. Entries with a title here are sheets, which have no metadata of their own.
+fn exported_files(tracks: &[String]) -> Vec<(String, Option<String>)> {
+    let mut sheets = HashSet::new();
+    let mut files = Vec::new();
+    for track in tracks {
+        if let Some(sheet) = cue::sheet_file(track) {
+            if sheets.insert(sheet.clone()) {
+                let title = cue::lookup(track).and_then(|(sheet, _)| match (sheet.performer, sheet.title) {
+                    (Some(performer), Some(title)) => Some(format!("{} - {}", performer, title)),
+                    (None, Some(title)) => Some(title),
+                    _ => None,
+                });
+                files.push((sheet.clone(), Some(title.unwrap_or_else(|| track_name(&sheet)))));
+            }
+        } else if book::is_book(track) {
+            let parts = book::load(track).map(|b| b.parts).unwrap_or_default();
+            files.extend(parts.into_iter().map(|part| (part.path, None)));
+        } else {
+            files.push((track.clone(), None));
+        }
+    }
+    files
+}
+
+fn entries(state: &AppState, tracks: &[String], base: &Path) -> Vec<Entry> {
+    let files = exported_files(tracks);
+    let audio: Vec<String> = files.iter().filter(|(_, title)| title.is_none()).map(|(file, _)| file.clone()).collect();
+    let mut cache = load_metadata_cache();
+    cache_missing_tracks(state, &mut cache, &audio);
+    let metas: HashMap<&String, &TrackMeta> = cache.tracks.iter().map(|m| (&m.path, m)).collect();
+    
+    files
+        .into_iter()
+        .map(|(file, sheet_title)| {
+            let absolute = paths::from_string(&file);
+            let (location, relative) = match absolute.strip_prefix(base) {
+                Ok(relative) if !base.as_os_str().is_empty() => (relative.to_string_lossy().to_string(), true),
+                _ => (absolute.to_string_lossy().to_string(), false),
+            };
+            let title = sheet_title.unwrap_or_else(|| match metas.get(&file).map(|m| (&m.artist, &m.title)) {
+                Some((Some(artist), Some(title))) => format!("{} - {}", artist, title),
+                Some((None, Some(title))) => title.clone(),
+                _ => track_name(&file),
+            });
+            Entry { location, relative, title, duration: get_audio_duration(&file) }
+        })
+        .collect()
+}
+
+fn write_m3u(entries: &[Entry]) -> String {
+    let mut text = String::from("#EXTM3U\n");
+    for entry in entries {
+        let seconds = entry.duration.map(|d| d as i64).unwrap_or(-1);
+        text.push_str(&format!("#EXTINF:{},{}\n{}\n", seconds, entry.title, entry.location));
+    }
+    text
+}
+
+fn write_pls(entries: &[Entry]) -> String {
+    let mut text = String::from("[playlist]\n");
+    for (i, entry) in entries.iter().enumerate() {
+        let number = i + 1;
+        let seconds = entry.duration.map(|d| d as i64).unwrap_or(-1);
+        text.push_str(&format!("File{}={}\nTitle{}={}\nLength{}={}\n", number, entry.location, number, entry.title, number, seconds));
+    }
+    text.push_str(&format!("NumberOfEntries={}\nVersion=2\n", entries.len()));
+    text
+}
+
+fn write_xspf(name: &str, entries: &[Entry]) -> String {
+    let mut text = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<playlist version=\"1\" xmlns=\"http://xspf.org/ns/0/\">\n");
+    text.push_str(&format!("  <title>{}</title>\n  <trackList>\n", xml_escape(name)));
+    for entry in entries {
+        let path = percent_encode(&entry.location.replace('\\', "/"));
+        let location = match (entry.relative, path.starts_with('/')) {
+            (true, _) => path,
+            (false, true) => format!("file://{}", path),
+            (false, false) => format!("file:///{}", path),
+        };
+        text.push_str(&format!("    <track>\n      <location>{}</location>\n", xml_escape(&location)));
+        text.push_str(&format!("      <title>{}</title>\n", xml_escape(&entry.title)));
+        if let Some(duration) = entry.duration {
+            text.push_str(&format!("      <duration>{}</duration>\n", duration * 1000));
+        }
+        text.push_str("    </track>\n");
+    }
+    text.push_str("  </trackList>\n</playlist>\n");
+    text
+}
+
+fn load_saved(name: &str) -> Result<SavedPlaylist, String> {
+    let playlists_dir = get_playlists_dir().ok_or("Could not determine playlists directory")?;
+    let path = playlists_dir.join(format!("{}.json", sanitize_filename(name)));
+    let content = fs::read_to_string(&path).map_err(|_| "Playlist not found")?;
+    serde_json::from_str(&content).map_err(|e| e.to_string())
+}
+
+#[derive(Serialize)]
+pub struct ImportedPlaylist {
+    name: String,
+    track_count: usize,
+    missing: usize, // Entries whose files don't exist here
+}
+
+// Save a playlist file as a new playlist, named after the file
+#[tauri::command]
+pub async fn import_playlist(path: String) -> Result<ImportedPlaylist, String> {
+    let file = paths::from_string(&path);
+    let tracks = read(&file)?;
+    if tracks.is_empty() {
+        return Err("Playlist has no local tracks".to_string());
+    }
+    
+    let playlists_dir = get_playlists_dir().ok_or("Could not determine playlists directory")?;
+    fs::create_dir_all(&playlists_dir).map_err(|e| e.to_string())?;
+    let stem = file.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_else(|| "Imported".to_string());
+    let mut name = stem.clone();
+    let mut copy = 2;
+    while playlists_dir.join(format!("{}.json", sanitize_filename(&name))).exists() {
+        name = format!("{} ({})", stem, copy);
+        copy += 1;
+    }
+    
+    let missing = tracks.iter().filter(|p| !cue::exists(p)).count();
+    let saved = SavedPlaylist { name: name.clone(), tracks };
+    let content = serde_json::to_string_pretty(&saved).map_err(|e| e.to_string())?;
+    fs::write(playlists_dir.join(format!("{}.json", sanitize_filename(&name))), content).map_err(|e| e.to_string())?;
+    
+    Ok(ImportedPlaylist { name, track_count: saved.tracks.len(), missing })
+}
+
+// Write a saved playlist as m3u, m3u8, pls or xspf. `path` may be a folder, in which case
+// the file is named after the playlist. Returns the written file.
+#[tauri::command]
+pub async fn export_playlist(name: String, format: String, path: String, state: State<'_, AppState>) -> Result<String, String> {
+    let format = Format::from_name(&format).ok_or(format!("Unknown playlist format: {}", format))?;
+    let saved = load_saved(&name)?;
+    
+    let mut target = paths::from_string(&path);
+    if target.is_dir() {
+        target = target.join(format!("{}.{}", sanitize_filename(&saved.name), format.extension()));
+    }
+    let base = target.parent().map(|p| p.to_path_buf()).unwrap_or_default();
+    let entries = entries(&state, &saved.tracks, &base);
+    
+    let content = match format {
+        // M3U8 only promises UTF-8, which both get below, so they're written alike
+        Format::M3u | Format::M3u8 => write_m3u(&entries),
+        Format::Pls => write_pls(&entries),
+        Format::Xspf => write_xspf(&saved.name, &entries),
+    };
+    // Always UTF-8, which current players also read from plain .m3u and .pls files
+    fs::write(&target, content).map_err(|e| e.to_string())?;
+    Ok(paths::to_string(&target))
+}
@@ -0,0 +1,292 @@
+// Rewriting stored track paths after files move on disk
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf, MAIN_SEPARATOR};
+use tauri::State;
+
+use crate::marks;
+use crate::{
+    get_config_dir, get_config_path, get_library_folders, get_metadata_cache_path, get_playlists_dir, load_config,
+    load_metadata_cache, paths, update_metadata_cache, AppState, SavedPlaylist,
+};
+
+// A saved playlist as written to disk. Tracks inside a library folder are stored relative
+// to it, as "@N/<path>" with N indexing `roots`, so a library that moves only changes the
+// roots. Older playlists hold absolute paths only, which still load as they are.
+#[derive(Serialize, Deserialize)]
+pub struct StoredPlaylist {
+    name: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    roots: Vec<String>,
+    tracks: Vec<String>,
+}
+
+impl StoredPlaylist {
+    // Store `tracks` relative to the innermost of `folders` containing each one
+    fn encode(name: String, tracks: &[String], folders: &[String]) -> Self {
+        let mut roots: Vec<String> = Vec::new();
+        let tracks = tracks
+            .iter()
+            .map(|track| {
+                let path = paths::from_string(track);
+                let inside = folders
+                    .iter()
+                    .filter_map(|folder| Some((folder, path.strip_prefix(paths::from_string(folder)).ok()?)))
+                    .filter(|(_, relative)| !relative.as_os_str().is_empty())
+                    .max_by_key(|(folder, _)| folder.len());
+                match inside {
+                    Some((folder, relative)) => {
+                        let index = roots.iter().position(|r| r == folder).unwrap_or_else(|| {
+                            roots.push(folder.clone());
+                            roots.len() - 1
+                        });
+                        format!("@{}/{}", index, paths::to_string(relative))
+                    }
+                    None => track.clone(),
+                }
+            })
+            .collect();
+        Self { name, roots, tracks }
+    }
+    
+    // Absolute track paths
+    fn decode(&self) -> Vec<String> {
+        self.tracks
+            .iter()
+            .map(|track| {
+                let relative = track
+                    .strip_prefix('@')
+                    .and_then(|rest| rest.split_once('/'))
+                    .and_then(|(index, relative)| Some((self.roots.get(index.parse::<usize>().ok()?)?, relative)));
+                match relative {
+                    Some((root, relative)) => paths::to_string(&paths::from_string(root).join(paths::from_string(relative))),
+                    None => track.clone(),
+                }
+            })
+            .collect()
+    }
+}
+
+impl From<StoredPlaylist> for SavedPlaylist {
+    fn from(stored: StoredPlaylist) -> Self {
+        let tracks = stored.decode();
+        SavedPlaylist { name: stored.name, tracks }
+    }
+}
+
+impl From<SavedPlaylist> for StoredPlaylist {
+    fn from(saved: SavedPlaylist) -> Self {
+        let folders = get_library_folders().unwrap_or_default();
+        StoredPlaylist::encode(saved.name, &saved.tracks, &folders)
+    }
+}
+
+// Write through a temp file so an interrupted rewrite never leaves a truncated file
+pub fn write_atomic(path: &Path, content: &str) -> Result<(), String> {
+    let tmp_path = path.with_extension("tmp");
+    fs::write(&tmp_path, content).map_err(|e| e.to_string())?;
+    fs::rename(&tmp_path, path).map_err(|e| e.to_string())
+}
+
+// Apply an old -> new path map to every saved playlist, the metadata cache and
+// the in-memory playlist. Returns how many saved playlists changed.
+pub fn rewrite_track_paths(moves: &HashMap<String, String>, state: &AppState) -> Result<usize, String> {
+    if moves.is_empty() {
+        return Ok(0);
+    }
+    
+    let mut playlists_updated = 0;
+    if let Some(playlists_dir) = get_playlists_dir() {
+        if let Ok(entries) = fs::read_dir(&playlists_dir) {
+            for entry in entries.filter_map(|e| e.ok()) {
+                let path = entry.path();
+                if path.extension().map(|e| e != "json").unwrap_or(true) {
+                    continue;
+                }
+                let content = match fs::read_to_string(&path) {
+                    Ok(c) => c,
+                    Err(_) => continue,
+                };
+                let mut saved: SavedPlaylist = match serde_json::from_str(&content) {
+                    Ok(s) => s,
+                    Err(_) => continue,
+                };
+                
+                let mut changed = false;
+                for track in saved.tracks.iter_mut() {
+                    if let Some(new_path) = moves.get(track) {
+                        *track = new_path.clone();
+                        changed = true;
+                    }
+                }
+                
+                if changed {
+                    let content = serde_json::to_string_pretty(&saved).map_err(|e| e.to_string())?;
+                    write_atomic(&path, &content)?;
+                    playlists_updated += 1;
+                }
+            }
+        }
+    }
+    
+    update_metadata_cache(state, |cache| {
+        let mut changed = false;
+        for meta in cache.tracks.iter_mut() {
+            if let Some(new_path) = moves.get(&meta.path) {
+                meta.path = new_path.clone();
+                changed = true;
+            }
+        }
+        changed
+    })?;
+    if let Some(marks) = marks::remap_marks(|path| moves.get(path).cloned()) {
+        marks::save_marks(&marks)?;
+    }
+    
+    for track in state.playlist.lock().unwrap().iter_mut() {
+        if let Some(new_path) = moves.get(track) {
+            *track = new_path.clone();
+        }
+    }
+    
+    Ok(playlists_updated)
+}
+
+// `path` moved from under `old` to under `new`, matching whole path components only
+fn remap_path(path: &str, old: &str, new: &str) -> Option<String> {
+    let rest = path.strip_prefix(old)?;
+    if !(rest.is_empty() || rest.starts_with('/') || rest.starts_with(MAIN_SEPARATOR) || rest.starts_with('#')) {
+        return None;
+    }
+    Some(format!("{}{}", new, rest))
+}
+
+// Files rewritten together: everything is written to temp files first, and the originals
+// are only replaced once all of them succeeded
+#[derive(Default)]
+struct Transaction {
+    staged: Vec<(PathBuf, PathBuf)>,
+}
+
+impl Transaction {
+    fn stage(&mut self, path: PathBuf, content: &str) -> Result<(), String> {
+        let tmp_path = path.with_extension("remap.tmp");
+        if let Err(e) = fs::write(&tmp_path, content) {
+            let _ = fs::remove_file(&tmp_path);
+            return Err(e.to_string());
+        }
+        self.staged.push((tmp_path, path));
+        Ok(())
+    }
+    
+    fn commit(mut self) -> Result<(), String> {
+        for (tmp_path, path) in std::mem::take(&mut self.staged) {
+            fs::rename(&tmp_path, &path).map_err(|e| e.to_string())?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for Transaction {
+    // Staged files left over from a failed remap
+    fn drop(&mut self) {
+        for (tmp_path, _) in &self.staged {
+            let _ = fs::remove_file(tmp_path);
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct RemapReport {
+    playlists: usize,
+    tracks: usize, // Metadata cache entries
+    folders: usize, // Library folders, including the default folder
+}
+
+// Point everything stored under `old_prefix` at `new_prefix` after the music moved:
+// saved playlists, the metadata cache, the library folders and the default folder.
+// Nothing is written unless every file could be prepared.
+#[tauri::command]
+pub fn remap_library(old_prefix: String, new_prefix: String, state: State<AppState>) -> Result<RemapReport, String> {
+    let trim = |s: &str| s.trim_end_matches(['/', MAIN_SEPARATOR]).to_string();
+    let (old, new) = (trim(&old_prefix), trim(&new_prefix));
+    if old.is_empty() || new.is_empty() {
+        return Err("Both prefixes must be non-empty".to_string());
+    }
+    let remap = |path: &str| remap_path(path, &old, &new);
+    
+    let mut transaction = Transaction::default();
+    let mut report = RemapReport { playlists: 0, tracks: 0, folders: 0 };
+    
+    let old_folders = get_library_folders()?;
+    let folders: Vec<String> = old_folders.iter().map(|f| remap(f).unwrap_or_else(|| f.clone())).collect();
+    report.folders = folders.iter().zip(&old_folders).filter(|(a, b)| a != b).count();
+    if report.folders > 0 {
+        let config_dir = get_config_dir().ok_or("Could not determine config directory")?;
+        let content = serde_json::to_string_pretty(&folders).map_err(|e| e.to_string())?;
+        transaction.stage(config_dir.join("library_folders.json"), &content)?;
+    }
+    
+    let mut config = load_config();
+    if let Some(default_folder) = config.default_folder.as_deref().and_then(remap) {
+        config.default_folder = Some(default_folder);
+        report.folders += 1;
+        let content = serde_json::to_string_pretty(&config).map_err(|e| e.to_string())?;
+        transaction.stage(get_config_path().ok_or("Could not determine config path")?, &content)?;
+    }
+    
+    // Playlists are re-encoded against the new folders, so relative entries follow them
+    if let Some(playlists_dir) = get_playlists_dir() {
+        for entry in fs::read_dir(&playlists_dir).into_iter().flatten().filter_map(|e| e.ok()) {
+            let path = entry.path();
+            if path.extension().map(|e| e != "json").unwrap_or(true) {
+                continue;
+            }
+            let content = match fs::read_to_string(&path) {
+                Ok(c) => c,
+                Err(_) => continue,
+            };
+            let stored: StoredPlaylist = match serde_json::from_str(&content) {
+                Ok(s) => s,
+                Err(_) => continue,
+            };
+            let tracks: Vec<String> = stored.decode().iter().map(|t| remap(t).unwrap_or_else(|| t.clone())).collect();
+            let updated = StoredPlaylist::encode(stored.name.clone(), &tracks, &folders);
+            if updated.roots != stored.roots || updated.tracks != stored.tracks {
+                let content = serde_json::to_string_pretty(&updated).map_err(|e| e.to_string())?;
+                transaction.stage(path, &content)?;
+                report.playlists += 1;
+            }
+        }
+    }
+    
+    // The staged cache is only current while the cache lock is held, up to the commit
+    let cache_lock = state.metadata_cache.lock().unwrap();
+    let mut cache = load_metadata_cache();
+    for meta in cache.tracks.iter_mut() {
+        if let Some(path) = remap(&meta.path) {
+            meta.path = path;
+            report.tracks += 1;
+        }
+    }
+    if report.tracks > 0 {
+        let content = serde_json::to_string_pretty(&cache).map_err(|e| e.to_string())?;
+        transaction.stage(get_metadata_cache_path().ok_or("Could not determine cache path")?, &content)?;
+    }
+    if let Some(marks) = marks::remap_marks(remap) {
+        let content = serde_json::to_string_pretty(&marks).map_err(|e| e.to_string())?;
+        transaction.stage(marks::get_marks_path().ok_or("Could not determine config directory")?, &content)?;
+    }
+    
+    transaction.commit()?;
+    drop(cache_lock);
+    
+    for track in state.playlist.lock().unwrap().iter_mut() {
+        if let Some(path) = remap(track) {
+            *track = path;
+        }
+    }
+    Ok(report)
+}
@@ -0,0 +1,208 @@
+// Finding tracks that went missing from saved playlists: by file name, by tags, or by
+// fingerprint across the library folders. Nothing is rewritten until the plan is confirmed.
+
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use tauri::{AppHandle, Manager, State};
+
+use crate::duplicates::{normalize, DURATION_TOLERANCE};
+use crate::relocate::rewrite_track_paths;
+use crate::{
+    cache_missing_tracks, cue, fingerprint, get_library_folders, get_playlists_dir, load_metadata_cache, paths,
+    scan_folder_recursive, track_name, update_metadata_cache, AppState, SavedPlaylist, TrackInfo, TrackMeta,
+};
+
+#[derive(Serialize)]
+pub struct RepairEntry {
+    path: String,
+    name: String,
+    replacement: Option<String>,
+    method: Option<&'static str>, // "filename", "tags" or "fingerprint"
+}
+
+fn file_name(path: &str) -> String {
+    paths::from_string(path)
+        .file_name()
+        .map(|n| n.to_string_lossy().to_lowercase())
+        .unwrap_or_default()
+}
+
+// How many trailing folder names two paths share, to pick between files of the same name
+fn shared_folders(a: &str, b: &str) -> usize {
+    let a = paths::from_string(a);
+    let b = paths::from_string(b);
+    a.parent()
+        .into_iter()
+        .flat_map(|p| p.iter().rev())
+        .zip(b.parent().into_iter().flat_map(|p| p.iter().rev()))
+        .take_while(|(x, y)| x.to_string_lossy().eq_ignore_ascii_case(&y.to_string_lossy()))
+        .count()
+}
+
+// The single best candidate, or None when the best ones tie
+fn unique_best<'a>(candidates: &[&'a TrackInfo], score: impl Fn(&TrackInfo) -> usize) -> Option<&'a TrackInfo> {
+    let best = candidates.iter().map(|c| score(c)).max()?;
+    let mut top = candidates.iter().filter(|c| score(c) == best);
+    let first = top.next()?;
+    top.next().is_none().then_some(*first)
+}
+
+struct Library {
+    tracks: Vec<TrackInfo>,
+    by_name: HashMap<String, Vec<usize>>,
+    by_tags: HashMap<(String, String), Vec<usize>>,
+}
+
+impl Library {
+    fn scan(state: &AppState) -> Self {
+        let mut tracks = Vec::new();
+        for folder in get_library_folders().unwrap_or_default() {
+            scan_folder_recursive(&paths::from_string(&folder), &mut tracks);
+        }
+        
+        let mut cache = load_metadata_cache();
+        let track_paths: Vec<String> = tracks.iter().map(|t| t.path.clone()).collect();
+        cache_missing_tracks(state, &mut cache, &track_paths);
+        let metas: HashMap<&String, &TrackMeta> = cache.tracks.iter().map(|m| (&m.path, m)).collect();
+        
+        let mut by_name: HashMap<String, Vec<usize>> = HashMap::new();
+        let mut by_tags: HashMap<(String, String), Vec<usize>> = HashMap::new();
+        for (i, track) in tracks.iter().enumerate() {
+            by_name.entry(file_name(&track.path)).or_default().push(i);
+            if let Some(key) = metas.get(&track.path).and_then(|m| tag_key(m)) {
+                by_tags.entry(key).or_default().push(i);
+            }
+        }
+        Self { tracks, by_name, by_tags }
+    }
+    
+    fn locate(&self, state: &AppState, path: &str, meta: Option<&TrackMeta>) -> Option<(String, &'static str)> {
+        let named: Vec<&TrackInfo> = self.by_name.get(&file_name(path)).into_iter().flatten().map(|&i| &self.tracks[i]).collect();
+        let known_duration = meta.and_then(|m| m.fingerprint.as_ref()).map(|f| f.duration);
+        let duration_fits = |track: &TrackInfo| match (known_duration, track.duration) {
+            (Some(a), Some(b)) => a.abs_diff(b) <= DURATION_TOLERANCE,
+            _ => true,
+        };
+        
+        // Same file name and length, in the folder that looks most like the old one. Names like
+        // "01.mp3" are common, so a match sharing no folder only counts when it is the only
+        // file of that name and its length is known to fit.
+        let fitting: Vec<&TrackInfo> = named.iter().copied().filter(|t| duration_fits(t)).collect();
+        if let Some(track) = unique_best(&fitting, |t| shared_folders(path, &t.path)) {
+            let length_checked = known_duration.is_some() && track.duration.is_some();
+            if shared_folders(path, &track.path) > 0 || (named.len() == 1 && length_checked) {
+                return Some((track.path.clone(), "filename"));
+            }
+        }
+        
+        // Same artist and title (and length, when it was recorded), preferring a same-named file
+        if let Some(key) = meta.and_then(tag_key) {
+            let tagged: Vec<&TrackInfo> = self
+                .by_tags
+                .get(&key)
+                .into_iter()
+                .flatten()
+                .map(|&i| &self.tracks[i])
+                .filter(|t| duration_fits(t))
+                .collect();
+            let named: HashSet<&String> = named.iter().map(|t| &t.path).collect();
+            if let Some(track) = unique_best(&tagged, |t| named.contains(&t.path) as usize) {
+                return Some((track.path.clone(), "tags"));
+            }
+        }
+        
+        // Same audio, among tracks of about the same length
+        let target = meta.and_then(|m| m.fingerprint.as_ref()).and_then(fingerprint::raw)?;
+        let candidates: Vec<String> = self
+            .tracks
+            .iter()
+            .filter(|t| t.duration.is_some() && duration_fits(t))
+            .map(|t| t.path.clone())
+            .collect();
+        fingerprint::ensure(state, &candidates)
+            .into_iter()
+            .filter_map(|(p, f)| Some((p, fingerprint::similarity(&target, &fingerprint::raw(&f)?))))
+            .filter(|(_, similarity)| *similarity >= fingerprint::MATCH_THRESHOLD)
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(p, _)| (p, "fingerprint"))
+    }
+}
+
+fn tag_key(meta: &TrackMeta) -> Option<(String, String)> {
+    let artist = normalize(meta.artist.as_ref()?);
+    let title = normalize(meta.title.as_ref()?);
+    if artist.is_empty() || title.is_empty() {
+        return None;
+    }
+    Some((artist, title))
+}
+
+// Missing tracks of every saved playlist
+fn missing_in_playlists() -> Vec<String> {
+    let mut missing = Vec::new();
+    let entries = get_playlists_dir().and_then(|dir| fs::read_dir(dir).ok());
+    for entry in entries.into_iter().flatten().filter_map(|e| e.ok()) {
+        let saved: Option<SavedPlaylist> = fs::read_to_string(entry.path())
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok());
+        for track in saved.into_iter().flat_map(|s| s.tracks) {
+            if !missing.contains(&track) && !cue::exists(&track) {
+                missing.push(track);
+            }
+        }
+    }
+    missing
+}
+
+// Where each missing track among `paths` could be now; empty `paths` checks every saved playlist.
+// Only reports: apply_repair writes the fixes the user accepted.
+#[tauri::command]
+pub async fn plan_repair(paths: Vec<String>, app: AppHandle) -> Result<Vec<RepairEntry>, String> {
+    tauri::async_runtime::spawn_blocking(move || Ok(locate_missing(&app.state::<AppState>(), paths)))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+fn locate_missing(state: &AppState, paths: Vec<String>) -> Vec<RepairEntry> {
+    let missing: Vec<String> = if paths.is_empty() {
+        missing_in_playlists()
+    } else {
+        let mut seen = HashSet::new();
+        paths.into_iter().filter(|p| !cue::exists(p) && seen.insert(p.clone())).collect()
+    };
+    if missing.is_empty() {
+        return Vec::new();
+    }
+    
+    let library = Library::scan(state);
+    let cache = load_metadata_cache();
+    let metas: HashMap<&String, &TrackMeta> = cache.tracks.iter().map(|m| (&m.path, m)).collect();
+    missing
+        .into_iter()
+        .map(|path| {
+            let found = library.locate(state, &path, metas.get(&path).copied());
+            RepairEntry {
+                name: track_name(&path),
+                replacement: found.as_ref().map(|(p, _)| p.clone()),
+                method: found.map(|(_, method)| method),
+                path,
+            }
+        })
+        .collect()
+}
+
+// Point saved playlists, the metadata cache and the loaded list at the relocated files.
+// Returns how many saved playlists changed.
+#[tauri::command]
+pub fn apply_repair(fixes: HashMap<String, String>, state: State<AppState>) -> Result<usize, String> {
+    // A missing track's entry is stale once its new place has been scanned, so it goes
+    // rather than being moved over the fresh one
+    update_metadata_cache(&state, |cache| {
+        let cached: HashSet<String> = cache.tracks.iter().map(|m| m.path.clone()).collect();
+        let count = cache.tracks.len();
+        cache.tracks.retain(|meta| fixes.get(&meta.path).map(|new| !cached.contains(new)).unwrap_or(true));
+        cache.tracks.len() != count
+    })?;
+    rewrite_track_paths(&fixes, &state)
+}
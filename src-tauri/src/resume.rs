@@ -0,0 +1,128 @@
+// Remembered positions of long tracks (podcasts, audiobooks), kept in the metadata cache.
+// Which tracks count as long comes from the resume settings in settings.json.
+
+use crate::{book, cache_missing_tracks, cue, load_metadata_cache, load_settings, paths, update_metadata_cache, AppState, Settings};
+
+const PLAYED_SHARE: f64 = 0.95; // A track past this share of its length counts as played
+const MIN_POSITION_MS: u64 = 5000; // Positions this close to the start aren't worth remembering
+const SAVE_EVERY_TICKS: u32 = 300; // Playback watcher ticks, 30 seconds
+
+// Whether `path` is a book, long enough, or in a resume folder, to remember its position
+fn applies(settings: &Settings, path: &str, duration: Option<u64>) -> bool {
+    if book::is_book(path) {
+        return true;
+    }
+    let long = settings.resumeminutes > 0.0
+        && duration.map(|d| d as f64 >= settings.resumeminutes * 60.0).unwrap_or(false);
+    let file = paths::from_string(&cue::backing_file(path));
+    long || settings
+        .resumefolders
+        .split(',')
+        .map(str::trim)
+        .filter(|f| !f.is_empty())
+        .any(|folder| file.starts_with(paths::from_string(folder)))
+}
+
+fn is_played(elapsed_ms: u64, duration: Option<u64>) -> bool {
+    duration
+        .filter(|d| *d > 0)
+        .map(|d| elapsed_ms as f64 >= d as f64 * 1000.0 * PLAYED_SHARE)
+        .unwrap_or(false)
+}
+
+// Where playback of `path` starts: the requested second, else its remembered position
+pub fn start_position(path: &str, duration: Option<u64>, requested: Option<u64>) -> u64 {
+    if let Some(secs) = requested {
+        return secs;
+    }
+    if !applies(&load_settings(), path, duration) {
+        return 0;
+    }
+    load_metadata_cache()
+        .tracks
+        .iter()
+        .find(|m| m.path == path)
+        .and_then(|m| m.resume_ms)
+        .map(|ms| ms / 1000)
+        .unwrap_or(0)
+}
+
+// Remember how far `path` got; past 95% it is marked played and starts over next time
+pub fn record(state: &AppState, path: &str, elapsed_ms: u64, duration: Option<u64>) {
+    if applies(&load_settings(), path, duration) {
+        save(state, path, elapsed_ms, duration);
+    }
+}
+
+// Like `record`, for a track already known to remember its position
+fn save(state: &AppState, path: &str, elapsed_ms: u64, duration: Option<u64>) {
+    let played = is_played(elapsed_ms, duration);
+    if !played && elapsed_ms < MIN_POSITION_MS {
+        return;
+    }
+    
+    cache_missing_tracks(state, &mut load_metadata_cache(), &[path.to_string()]);
+    let _ = update_metadata_cache(state, |cache| {
+        let Some(meta) = cache.tracks.iter_mut().find(|m| m.path == path) else { return false };
+        let resume_ms = if played { None } else { Some(elapsed_ms) };
+        if meta.resume_ms == resume_ms && (meta.played || !played) {
+            return false;
+        }
+        meta.resume_ms = resume_ms;
+        meta.played |= played;
+        true
+    });
+}
+
+// Remember the playing track's position now, e.g. on exit
+pub fn record_current(state: &AppState) {
+    if let Some(path) = state.player.get_current_path() {
+        record(state, &path, state.player.get_elapsed_millis(), *state.current_duration.lock().unwrap());
+    }
+}
+
+// Saves the playing track's position from the playback watcher: every 30 seconds, when it
+// crosses the played mark, and once more when another track takes over
+#[derive(Default)]
+pub struct ResumeWatch {
+    path: String,
+    duration: Option<u64>,
+    remembers: bool, // Whether the track remembers its position, settled when it starts
+    elapsed_ms: u64,
+    saved_ms: u64,
+    played: bool,
+    ticks: u32,
+}
+
+impl ResumeWatch {
+    pub fn tick(&mut self, state: &AppState, path: &str, elapsed_ms: u64) {
+        if path != self.path {
+            if self.remembers && self.elapsed_ms != self.saved_ms {
+                save(state, &self.path, self.elapsed_ms, self.duration);
+            }
+            let duration = *state.current_duration.lock().unwrap();
+            *self = Self {
+                path: path.to_string(),
+                duration,
+                remembers: !path.is_empty() && applies(&load_settings(), path, duration),
+                ..Self::default()
+            };
+        }
+        if !self.remembers {
+            return;
+        }
+        
+        self.elapsed_ms = elapsed_ms;
+        self.ticks += 1;
+        let crossed = !self.played && is_played(elapsed_ms, self.duration);
+        let due = self.ticks >= SAVE_EVERY_TICKS;
+        if due {
+            self.ticks = 0;
+        }
+        if crossed || (due && elapsed_ms != self.saved_ms) {
+            save(state, path, elapsed_ms, self.duration);
+            self.played |= crossed;
+            self.saved_ms = elapsed_ms;
+        }
+    }
+}
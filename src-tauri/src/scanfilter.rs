@@ -0,0 +1,272 @@
+// Which files folder scans pick up: .vimusicignore files (gitignore syntax), global
+// exclude patterns and the scan settings from settings.json
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::{cue, load_settings, paths, Settings};
+
+const IGNORE_FILE: &str = ".vimusicignore";
+
+#[derive(Clone)]
+struct Rule {
+    pattern: Vec<char>,
+    base: PathBuf,
+    negated: bool,
+    dir_only: bool,
+    anchored: bool, // Contains a slash, so it matches from the base rather than any name
+}
+
+impl Rule {
+    fn parse(line: &str, base: &Path) -> Option<Self> {
+        let line = line.trim_end();
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+        let (negated, line) = match line.strip_prefix('!') {
+            Some(rest) => (true, rest),
+            None => (false, line.strip_prefix('\\').unwrap_or(line)),
+        };
+        let (dir_only, line) = match line.strip_suffix('/') {
+            Some(rest) => (true, rest),
+            None => (false, line),
+        };
+        let anchored = line.contains('/');
+        let line = line.strip_prefix('/').unwrap_or(line);
+        if line.is_empty() {
+            return None;
+        }
+        Some(Self {
+            pattern: line.chars().collect(),
+            base: base.to_path_buf(),
+            negated,
+            dir_only,
+            anchored,
+        })
+    }
+    
+    fn matches(&self, path: &Path, is_dir: bool) -> bool {
+        if self.dir_only && !is_dir {
+            return false;
+        }
+        let relative = match path.strip_prefix(&self.base) {
+            Ok(relative) => relative,
+            Err(_) => return false,
+        };
+        let text: Vec<char> = if self.anchored {
+            relative
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy().to_string())
+                .collect::<Vec<_>>()
+                .join("/")
+                .chars()
+                .collect()
+        } else {
+            match relative.file_name() {
+                Some(name) => name.to_string_lossy().chars().collect(),
+                None => return false,
+            }
+        };
+        glob_match(&self.pattern, &text)
+    }
+}
+
+// Character class body after '[', returning whether `c` matches and the pattern after ']'
+fn match_class(pattern: &[char], c: char) -> Option<(bool, &[char])> {
+    let (negated, mut rest) = match pattern.first() {
+        Some('!') | Some('^') => (true, &pattern[1..]),
+        _ => (false, pattern),
+    };
+    let mut matched = false;
+    let mut first = true;
+    while let Some(&start) = rest.first() {
+        if start == ']' && !first {
+            return Some((matched != negated, &rest[1..]));
+        }
+        first = false;
+        if rest.len() > 2 && rest[1] == '-' && rest[2] != ']' {
+            matched |= start <= c && c <= rest[2];
+            rest = &rest[3..];
+        } else {
+            matched |= start == c;
+            rest = &rest[1..];
+        }
+    }
+    None
+}
+
+// gitignore-style glob: '*' and '?' stay within a folder, "**" spans folders
+fn glob_match(pattern: &[char], text: &[char]) -> bool {
+    match pattern.first() {
+        None => text.is_empty(),
+        Some('*') if pattern.get(1) == Some(&'*') => {
+            let rest = &pattern[2..];
+            match rest.first() {
+                // "**/" matches zero or more leading folders
+                Some('/') => {
+                    let rest = &rest[1..];
+                    (0..=text.len()).any(|i| (i == 0 || text[i - 1] == '/') && glob_match(rest, &text[i..]))
+                }
+                // Trailing "**" matches everything inside
+                None => true,
+                _ => (0..=text.len()).any(|i| glob_match(rest, &text[i..])),
+            }
+        }
+        Some('*') => {
+            let rest = &pattern[1..];
+            for i in 0..=text.len() {
+                if glob_match(rest, &text[i..]) {
+                    return true;
+                }
+                if i < text.len() && text[i] == '/' {
+                    break;
+                }
+            }
+            false
+        }
+        Some('?') => !text.is_empty() && text[0] != '/' && glob_match(&pattern[1..], &text[1..]),
+        Some('[') => match (text.first(), match_class(&pattern[1..], text.first().copied().unwrap_or('/'))) {
+            (Some(&c), Some((true, rest))) if c != '/' => glob_match(rest, &text[1..]),
+            (_, Some(_)) => false,
+            // Unterminated class: treat '[' literally
+            (_, None) => text.first() == Some(&'[') && glob_match(&pattern[1..], &text[1..]),
+        },
+        Some('\\') if pattern.len() > 1 => text.first() == Some(&pattern[1]) && glob_match(&pattern[2..], &text[1..]),
+        Some(&c) => text.first() == Some(&c) && glob_match(&pattern[1..], &text[1..]),
+    }
+}
+
+fn read_ignore_file(dir: &Path) -> Vec<Rule> {
+    fs::read_to_string(dir.join(IGNORE_FILE))
+        .map(|content| content.lines().filter_map(|line| Rule::parse(line, dir)).collect())
+        .unwrap_or_default()
+}
+
+fn is_hidden(path: &Path) -> bool {
+    path.file_name()
+        .map(|n| n.to_string_lossy().starts_with('.'))
+        .unwrap_or(false)
+}
+
+fn parse_excludes(exclude: &str, root: &Path) -> Vec<Rule> {
+    exclude.split(',').filter_map(|pattern| Rule::parse(pattern.trim(), root)).collect()
+}
+
+pub struct Scanner {
+    options: Settings,
+    rules: Vec<Rule>, // Global excludes plus the ignore files above the scanned folder
+}
+
+impl Scanner {
+    // Filters for scanning `dir`, including ignore files in `root` and the folders between
+    pub fn new(root: &Path, dir: &Path) -> Self {
+        let options = load_settings();
+        let mut rules = parse_excludes(&options.exclude, root);
+        // The scanned folder's own ignore file is read when it is listed
+        if let Ok(relative) = dir.strip_prefix(root) {
+            let mut current = root.to_path_buf();
+            for component in relative.components() {
+                rules.extend(read_ignore_file(&current));
+                current.push(component);
+            }
+        }
+        Self { options, rules }
+    }
+    
+    // Filters for a subfolder of the folder this scanner was made for
+    pub fn descend(&self, parent: &Path) -> Self {
+        let mut rules = self.rules.clone();
+        rules.extend(read_ignore_file(parent));
+        Self { options: self.options.clone(), rules }
+    }
+    
+    // Whether an audio file passes the size and duration minimums
+    fn is_large_enough(&self, path: &Path) -> bool {
+        if self.options.minsize > 0.0 {
+            let size = fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+            if (size as f64) < self.options.minsize * 1024.0 {
+                return false;
+            }
+        }
+        if self.options.minduration > 0.0 {
+            let duration = crate::get_audio_duration(&paths::to_string(path)).unwrap_or(0);
+            if (duration as f64) < self.options.minduration {
+                return false;
+            }
+        }
+        true
+    }
+    
+    fn list(&self, dir: &Path, rules: &[Rule]) -> (Vec<PathBuf>, Vec<PathBuf>) {
+        let mut folders = Vec::new();
+        let mut files = Vec::new();
+        let entries = match fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(_) => return (folders, files),
+        };
+        for entry in entries.filter_map(|e| e.ok()) {
+            let path = entry.path();
+            let is_dir = path.is_dir();
+            // Last matching rule wins, so later "!pattern" lines re-include
+            let ignored = rules
+                .iter()
+                .rev()
+                .find(|rule| rule.matches(&path, is_dir))
+                .map(|rule| !rule.negated)
+                .unwrap_or(false);
+            if ignored {
+                continue;
+            }
+            if is_dir {
+                if !(self.options.skiphidden && is_hidden(&path)) {
+                    folders.push(path);
+                }
+            } else if cue::is_cue_file(&path) || (crate::is_audio_file(&path) && self.is_large_enough(&path)) {
+                files.push(path);
+            }
+        }
+        (folders, files)
+    }
+    
+    // Subfolders and audio/.cue files directly inside `dir` that pass the filters
+    pub fn entries(&self, dir: &Path) -> (Vec<PathBuf>, Vec<PathBuf>) {
+        let mut rules = self.rules.clone();
+        rules.extend(read_ignore_file(dir));
+        self.list(dir, &rules)
+    }
+    
+    // Every audio/.cue file below `dir` that passes the filters. Symlinks are followed,
+    // but each folder and file is taken once, by where it really lives.
+    pub fn files(&self, dir: &Path) -> Vec<PathBuf> {
+        let mut rules = self.rules.clone();
+        let mut visited = HashSet::new();
+        let mut files = Vec::new();
+        self.walk(dir, 0, &mut rules, &mut visited, &mut files);
+        
+        let mut seen = HashSet::new();
+        files.retain(|f| fs::canonicalize(f).map(|real| seen.insert(real)).unwrap_or(false));
+        files
+    }
+    
+    fn walk(&self, dir: &Path, depth: usize, rules: &mut Vec<Rule>, visited: &mut HashSet<PathBuf>, out: &mut Vec<PathBuf>) {
+        // A folder already scanned means a symlink loop or a second link to it;
+        // canonicalize also fails on links that loop back on themselves
+        if !fs::canonicalize(dir).map(|real| visited.insert(real)).unwrap_or(false) {
+            return;
+        }
+        
+        let inherited = rules.len();
+        rules.extend(read_ignore_file(dir));
+        let (folders, files) = self.list(dir, rules);
+        out.extend(files);
+        
+        let max_depth = self.options.maxdepth as usize;
+        if max_depth == 0 || depth < max_depth {
+            for folder in folders {
+                self.walk(&folder, depth + 1, rules, visited, out);
+            }
+        }
+        rules.truncate(inherited);
+    }
+}
@@ -0,0 +1,191 @@
+// The listening session: loaded playlist, position, queue and playback modes, kept in
+// session.json so the next launch picks up where this one stopped
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use tauri::State;
+
+use crate::relocate::write_atomic;
+use crate::{cue, get_audio_duration, get_config_dir, track_name, AppState, AudioCommand, TrackInfo};
+
+const SAVE_EVERY_TICKS: u32 = 50; // Playback watcher ticks, 5 seconds
+
+// State only the frontend tracks, sent over with set_session_state
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct SessionModes {
+    #[serde(default)]
+    queue: Vec<usize>, // Playlist indices to play next
+    #[serde(default)]
+    repeat: String, // "off", "one" or "all"
+    #[serde(default)]
+    shuffle: bool,
+    #[serde(default)]
+    root_folder: Option<String>, // What the list was loaded from, e.g. "Playlist: name"
+}
+
+#[derive(Serialize, Deserialize)]
+struct SessionFile {
+    playlist: Vec<String>,
+    index: usize,
+    elapsed_ms: u64,
+    playing: bool,
+    paused: bool,
+    volume: f32,
+    speed: f32,
+    #[serde(flatten)]
+    modes: SessionModes,
+}
+
+#[derive(Clone, Copy)]
+struct Resume {
+    elapsed_ms: u64,
+    playing: bool,
+    paused: bool,
+}
+
+#[derive(Default)]
+pub struct Session {
+    modes: SessionModes,
+    resume: Option<Resume>, // Where the last run stopped, until the frontend picks it up
+    written: String,        // Last content saved, so an idle session isn't rewritten
+}
+
+#[derive(Serialize)]
+pub struct RestoredSession {
+    tracks: Vec<TrackInfo>,
+    index: usize,
+    elapsed_ms: u64,
+    playing: bool,
+    paused: bool,
+    #[serde(flatten)]
+    modes: SessionModes,
+}
+
+fn session_path() -> Option<PathBuf> {
+    get_config_dir().map(|p| p.join("session.json"))
+}
+
+// Load the last session into the backend at startup; get_session hands the rest to the frontend
+pub fn restore(state: &AppState) {
+    let saved: Option<SessionFile> = session_path()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|content| serde_json::from_str(&content).ok());
+    let Some(saved) = saved else { return };
+    
+    let volume = saved.volume.clamp(0.0, 1.0);
+    *state.volume.lock().unwrap() = volume;
+    state.player.send(AudioCommand::SetVolume(volume));
+    state.player.send(AudioCommand::SetSpeed(saved.speed.clamp(0.25, 3.0)));
+    
+    let index = saved.index.min(saved.playlist.len().saturating_sub(1));
+    *state.playlist.lock().unwrap() = saved.playlist;
+    *state.current_index.lock().unwrap() = index;
+    
+    let mut session = state.session.lock().unwrap();
+    session.modes = saved.modes;
+    session.resume = Some(Resume {
+        elapsed_ms: saved.elapsed_ms,
+        playing: saved.playing,
+        paused: saved.paused,
+    });
+}
+
+// Write the session if it changed since the last write
+pub fn save(state: &AppState) {
+    let mut session = state.session.lock().unwrap();
+    // Nothing has been restored into the frontend yet; keep the last run's session as it was
+    if session.resume.is_some() {
+        return;
+    }
+    
+    let playing = *state.is_playing.lock().unwrap() && !state.player.is_finished();
+    let file = SessionFile {
+        playlist: state.playlist.lock().unwrap().clone(),
+        index: *state.current_index.lock().unwrap(),
+        elapsed_ms: if playing { state.player.get_elapsed_millis() } else { 0 },
+        playing,
+        paused: playing && *state.is_paused.lock().unwrap(),
+        volume: *state.volume.lock().unwrap(),
+        speed: state.player.get_speed(),
+        modes: session.modes.clone(),
+    };
+    let Ok(content) = serde_json::to_string_pretty(&file) else { return };
+    if content == session.written {
+        return;
+    }
+    if let Some(path) = session_path() {
+        let _ = fs::create_dir_all(path.parent().unwrap());
+        if write_atomic(&path, &content).is_ok() {
+            session.written = content;
+        }
+    }
+}
+
+// Whether the frontend has tracks queued to play next
+pub fn has_queue(state: &AppState) -> bool {
+    !state.session.lock().unwrap().modes.queue.is_empty()
+}
+
+// Saves the session every few seconds from the playback watcher
+#[derive(Default)]
+pub struct SessionWatch {
+    ticks: u32,
+}
+
+impl SessionWatch {
+    pub fn tick(&mut self, state: &AppState) {
+        self.ticks += 1;
+        if self.ticks >= SAVE_EVERY_TICKS {
+            self.ticks = 0;
+            save(state);
+        }
+    }
+}
+
+// The session restored at startup, once; None when there was nothing to restore
+#[tauri::command]
+pub fn get_session(state: State<AppState>) -> Option<RestoredSession> {
+    let mut session = state.session.lock().unwrap();
+    let resume = session.resume.take()?;
+    let playlist = state.playlist.lock().unwrap().clone();
+    if playlist.is_empty() {
+        return None;
+    }
+    
+    let tracks = playlist
+        .iter()
+        .enumerate()
+        .map(|(index, path)| {
+            let missing = !cue::exists(path);
+            TrackInfo {
+                path: path.clone(),
+                name: track_name(path),
+                index,
+                duration: if missing { None } else { get_audio_duration(path) },
+                missing,
+            }
+        })
+        .collect();
+    Some(RestoredSession {
+        tracks,
+        index: *state.current_index.lock().unwrap(),
+        elapsed_ms: resume.elapsed_ms,
+        playing: resume.playing,
+        paused: resume.paused,
+        modes: session.modes.clone(),
+    })
+}
+
+// The frontend's playlist order (with unsaved edits), playing index, queue and modes
+#[tauri::command]
+pub fn set_session_state(playlist: Vec<String>, index: Option<usize>, modes: SessionModes, state: State<AppState>) {
+    *state.playlist.lock().unwrap() = playlist;
+    if let Some(index) = index {
+        *state.current_index.lock().unwrap() = index;
+    }
+    // The frontend has taken over, so a session nobody restored is replaced from now on
+    let mut session = state.session.lock().unwrap();
+    session.modes = modes;
+    session.resume = None;
+}
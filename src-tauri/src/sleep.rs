@@ -0,0 +1,260 @@
+// Sleep timer, run by the playback watcher so it keeps time while the webview is throttled.
+// It stops playback after some minutes or at a track boundary, fading the volume out over
+// the last minutes before it does.
+
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager, State};
+
+use crate::{book, load_metadata_cache, load_settings, paths, session, stop_playback, AppState, AudioCommand};
+
+const MAX_MINUTES: f64 = 24.0 * 60.0; // Longest timer, and largest adjustment, a day
+
+#[derive(Clone, Copy)]
+enum Target {
+    Time(Instant),
+    Tracks(u32),              // Track ends still to come, the playing track's included
+    QueueEnd { queue: bool }, // End of the queue when one was playing, else of the album
+}
+
+#[derive(Default)]
+pub struct SleepTimer {
+    target: Option<Target>,
+    fade_ms: u64,
+    finish_seen: bool,                 // The playing track's end was counted, and didn't stop it
+    fading: bool,                      // The volume is lowered and has to be restored
+    album_end: Option<(String, bool)>, // Whether the album ends with the track at this path
+}
+
+// What :sleep asks for
+#[derive(Deserialize)]
+#[serde(tag = "mode", rename_all = "lowercase")]
+pub enum SleepRequest {
+    Off,
+    Minutes { minutes: f64 },
+    Adjust { minutes: f64 }, // Add to (or take from) a timed sleep
+    Track,
+    Tracks { count: u32 },
+    Queue,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct SleepStatus {
+    mode: String,                // "time", "tracks" or "queue"
+    remaining_secs: Option<u64>, // Until playback stops, when known
+    tracks_left: Option<u32>,
+    fading: bool,
+}
+
+// Whether the track after `index` belongs to another album, or there is none. Tracks
+// without an album tag go by their folder.
+fn album_ends(state: &AppState, path: &str) -> bool {
+    let next = {
+        let playlist = state.playlist.lock().unwrap();
+        playlist.get(*state.current_index.lock().unwrap() + 1).cloned()
+    };
+    let Some(next) = next else { return true };
+    
+    let cache = load_metadata_cache();
+    let album = |p: &str| {
+        cache
+            .tracks
+            .iter()
+            .find(|m| m.path == p)
+            .and_then(|m| m.album.clone())
+            .or_else(|| paths::from_string(p).parent().map(paths::to_string))
+    };
+    album(path) != album(&next)
+}
+
+impl SleepTimer {
+    fn start(&mut self, target: Target) {
+        *self = Self {
+            target: Some(target),
+            fade_ms: (load_settings().sleepfade.max(0.0) * 60_000.0) as u64,
+            fading: self.fading,
+            ..Self::default()
+        };
+    }
+    
+    // Whether playback stops when the track at `path` ends
+    fn on_last_track(&mut self, state: &AppState, path: &str) -> bool {
+        match self.target {
+            Some(Target::Tracks(count)) => count <= 1,
+            Some(Target::QueueEnd { queue: true }) => !session::has_queue(state),
+            Some(Target::QueueEnd { queue: false }) => {
+                if let Some((album_path, ends)) = &self.album_end {
+                    if album_path == path {
+                        return *ends;
+                    }
+                }
+                let ends = album_ends(state, path);
+                self.album_end = Some((path.to_string(), ends));
+                ends
+            }
+            _ => false,
+        }
+    }
+    
+    fn remaining_ms(&mut self, state: &AppState, path: &str, elapsed_ms: u64) -> Option<u64> {
+        match self.target? {
+            Target::Time(end) => Some(end.saturating_duration_since(Instant::now()).as_millis() as u64),
+            _ if !path.is_empty() && self.on_last_track(state, path) => {
+                let duration = (*state.current_duration.lock().unwrap())?;
+                Some((duration * 1000).saturating_sub(elapsed_ms))
+            }
+            _ => None,
+        }
+    }
+    
+    // Counts the end of the playing track once, from the watcher or get_status, whichever
+    // sees it first; true when playback stops there
+    fn count_track_end(&mut self, state: &AppState, path: &str) -> bool {
+        if self.finish_seen || matches!(self.target, None | Some(Target::Time(_))) {
+            return false;
+        }
+        if self.on_last_track(state, path) {
+            return true;
+        }
+        self.finish_seen = true;
+        if let Some(Target::Tracks(count)) = self.target {
+            self.target = Some(Target::Tracks(count - 1));
+        }
+        false
+    }
+    
+    // Put the volume back once a fade is over or cancelled
+    fn restore_volume(&mut self, state: &AppState) {
+        if self.fading {
+            self.fading = false;
+            state.player.send(AudioCommand::SetVolume(*state.volume.lock().unwrap()));
+        }
+    }
+    
+    // Runs every playback watcher tick
+    pub fn tick(app: &AppHandle, state: &AppState, path: &str, elapsed_ms: u64) {
+        let mut timer = state.sleep.lock().unwrap();
+        let Some(target) = timer.target else { return };
+        let playing = *state.is_playing.lock().unwrap() && !path.is_empty();
+        
+        let mut expired = false;
+        if playing && state.player.is_finished() {
+            expired = timer.count_track_end(state, path);
+        } else {
+            timer.finish_seen = false;
+        }
+        
+        let remaining = timer.remaining_ms(state, path, elapsed_ms);
+        if matches!(target, Target::Time(_)) && remaining == Some(0) {
+            expired = true;
+        }
+        if expired {
+            timer.target = None;
+            drop(timer);
+            if playing {
+                stop_playback(state);
+                let _ = app.emit_all("sleep-expired", ());
+            }
+            state.sleep.lock().unwrap().restore_volume(state);
+            return;
+        }
+        
+        match remaining.filter(|r| playing && *r < timer.fade_ms) {
+            Some(remaining) => {
+                let volume = *state.volume.lock().unwrap() * remaining as f32 / timer.fade_ms as f32;
+                state.player.send(AudioCommand::SetVolume(volume));
+                timer.fading = true;
+            }
+            None => timer.restore_volume(state),
+        }
+    }
+}
+
+// For get_status once the playing track has ended: whether the timer stops playback there,
+// in which case the frontend mustn't start the next track
+pub fn holds_track_end(state: &AppState) -> bool {
+    let Some(path) = state.player.get_current_path() else { return false };
+    state.sleep.lock().unwrap().count_track_end(state, &path)
+}
+
+pub fn status(state: &AppState) -> Option<SleepStatus> {
+    let path = state.player.get_current_path().unwrap_or_default();
+    let elapsed_ms = state.player.get_elapsed_millis();
+    let mut timer = state.sleep.lock().unwrap();
+    let remaining = timer.remaining_ms(state, &path, elapsed_ms);
+    let (mode, tracks_left) = match timer.target? {
+        Target::Time(_) => ("time", None),
+        Target::Tracks(count) => ("tracks", Some(count)),
+        Target::QueueEnd { .. } => ("queue", None),
+    };
+    Some(SleepStatus {
+        mode: mode.to_string(),
+        remaining_secs: remaining.map(|ms| ms.div_ceil(1000)),
+        tracks_left,
+        fading: timer.fading,
+    })
+}
+
+// `minutes` (their size, for adjustments) as a duration, or an error for what no timer can run
+fn minutes_duration(minutes: f64) -> Result<Duration, String> {
+    if !minutes.is_finite() || minutes.abs() > MAX_MINUTES {
+        return Err(format!("Sleep time must be at most {} minutes", MAX_MINUTES));
+    }
+    Duration::try_from_secs_f64(minutes.abs() * 60.0).map_err(|e| e.to_string())
+}
+
+// A book's own sleep timer, started with it unless a timer is already running
+pub fn start_for_book(state: &AppState, minutes: u32) {
+    let mut timer = state.sleep.lock().unwrap();
+    let end = Instant::now().checked_add(Duration::from_secs(minutes as u64 * 60));
+    if let (None, Some(end)) = (timer.target, end) {
+        timer.start(Target::Time(end));
+    }
+}
+
+#[tauri::command]
+pub fn set_sleep_timer(request: SleepRequest, state: State<AppState>) -> Result<Option<SleepStatus>, String> {
+    let mut timer = state.sleep.lock().unwrap();
+    let now = Instant::now();
+    let out_of_range = || "Sleep time out of range".to_string();
+    match request {
+        SleepRequest::Off => timer.target = None,
+        SleepRequest::Minutes { minutes } if minutes <= 0.0 => timer.target = None,
+        SleepRequest::Minutes { minutes } => {
+            let end = now.checked_add(minutes_duration(minutes)?).ok_or_else(out_of_range)?;
+            timer.start(Target::Time(end));
+        }
+        SleepRequest::Adjust { minutes } => {
+            let delta = minutes_duration(minutes)?;
+            match timer.target {
+                Some(Target::Time(end)) if minutes >= 0.0 => {
+                    timer.target = Some(Target::Time(end.checked_add(delta).ok_or_else(out_of_range)?));
+                }
+                Some(Target::Time(end)) => {
+                    timer.target = end.checked_sub(delta).filter(|end| *end > now).map(Target::Time);
+                }
+                None if minutes > 0.0 => timer.start(Target::Time(now.checked_add(delta).ok_or_else(out_of_range)?)),
+                None => return Err("No sleep timer to adjust".to_string()),
+                Some(_) => return Err("Only a timed sleep timer can be adjusted".to_string()),
+            }
+        }
+        SleepRequest::Track => timer.start(Target::Tracks(1)),
+        SleepRequest::Tracks { count } => timer.start(Target::Tracks(count.max(1))),
+        SleepRequest::Queue => timer.start(Target::QueueEnd { queue: session::has_queue(&state) }),
+    }
+    if timer.target.is_none() {
+        timer.restore_volume(&state);
+    }
+    
+    // A book remembers the minutes it was set to sleep after
+    let minutes = match (&request, timer.target) {
+        (SleepRequest::Minutes { minutes }, _) => Some(minutes.round().max(0.0) as u32),
+        (SleepRequest::Off, _) | (_, None) => Some(0),
+        _ => None,
+    };
+    drop(timer);
+    if let Some(minutes) = minutes {
+        book::remember_sleep(&state, minutes);
+    }
+    Ok(status(&state))
+}
@@ -0,0 +1,140 @@
+// Sort keys shared by every listing: natural numeric order, case and accent folding,
+// and optionally ignoring leading articles so "The Beatles" sorts under B
+
+use std::collections::HashMap;
+use tauri::State;
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
+
+use crate::{analysis, paths, AppState, TrackInfo, TrackMeta};
+
+const ARTICLES: [&str; 3] = ["the", "a", "an"];
+
+// Letters that compatibility decomposition leaves as they are
+fn fold_special(c: char) -> Option<&'static str> {
+    Some(match c {
+        'ß' => "ss",
+        'æ' => "ae",
+        'œ' => "oe",
+        'ø' => "o",
+        'đ' | 'ð' => "d",
+        'ł' => "l",
+        'þ' => "th",
+        'ı' => "i",
+        _ => return None,
+    })
+}
+
+// Lowercase, compatibility-decompose and drop the combining marks, so "É", "é" and "e" compare equal
+pub fn fold(s: &str) -> String {
+    let mut folded = String::with_capacity(s.len());
+    for c in s.nfkd().filter(|c| !is_combining_mark(*c)).flat_map(char::to_lowercase) {
+        match fold_special(c) {
+            Some(replacement) => folded.push_str(replacement),
+            None => folded.push(c),
+        }
+    }
+    folded
+}
+
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone)]
+enum Part {
+    Number(usize, String), // Digit count without leading zeros, then the digits, so 2 < 10
+    Text(String),
+}
+
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone)]
+pub struct SortKey {
+    parts: Vec<Part>,
+    original: String, // Keeps the order stable between names that fold to the same key
+}
+
+#[derive(Clone, Copy)]
+pub struct Sorter {
+    ignore_articles: bool,
+}
+
+impl Sorter {
+    pub fn from_config() -> Self {
+        Self { ignore_articles: crate::load_config().sort_ignore_articles }
+    }
+    
+    pub fn key(&self, name: &str) -> SortKey {
+        let folded = fold(name.trim());
+        let mut text = folded.as_str();
+        if self.ignore_articles {
+            for article in ARTICLES {
+                if let Some(rest) = text.strip_prefix(article).and_then(|r| r.strip_prefix(' ')) {
+                    if !rest.trim().is_empty() {
+                        text = rest.trim_start();
+                        break;
+                    }
+                }
+            }
+        }
+        
+        let mut parts = Vec::new();
+        let mut chars = text.chars().peekable();
+        while let Some(&c) = chars.peek() {
+            let is_digit = c.is_ascii_digit();
+            let mut run = String::new();
+            while let Some(&c) = chars.peek() {
+                if c.is_ascii_digit() != is_digit {
+                    break;
+                }
+                run.push(c);
+                chars.next();
+            }
+            parts.push(if is_digit {
+                let digits = run.trim_start_matches('0').to_string();
+                Part::Number(digits.len(), digits)
+            } else {
+                Part::Text(run)
+            });
+        }
+        
+        SortKey { parts, original: name.to_string() }
+    }
+    
+    // Paths compare folder by folder, so "Album 2/01.flac" stays after everything in "Album/"
+    pub fn path_key(&self, path: &str) -> Vec<SortKey> {
+        paths::from_string(path)
+            .components()
+            .map(|c| self.key(&c.as_os_str().to_string_lossy()))
+            .collect()
+    }
+}
+
+// Turn ignoring leading articles on or off for every listing
+#[tauri::command]
+pub fn set_sort_ignore_articles(ignore: bool) -> Result<(), String> {
+    let mut config = crate::load_config();
+    config.sort_ignore_articles = ignore;
+    crate::save_config(&config)
+}
+
+// Sort tracks by "name" or "path" with the shared sort keys, or by "bpm" or "key" (Camelot
+// wheel order) from the metadata cache. Tracks without a tempo or key go last.
+#[tauri::command]
+pub fn sort_tracks(mut tracks: Vec<TrackInfo>, field: String, state: State<AppState>) -> Result<Vec<TrackInfo>, String> {
+    let sorter = Sorter::from_config();
+    match field.as_str() {
+        "name" => tracks.sort_by_cached_key(|t| sorter.key(&t.name)),
+        "path" => tracks.sort_by_cached_key(|t| sorter.path_key(&t.path)),
+        "bpm" | "key" => {
+            let mut cache = crate::load_metadata_cache();
+            let paths: Vec<String> = tracks.iter().map(|t| t.path.clone()).collect();
+            crate::cache_missing_tracks(&state, &mut cache, &paths);
+            let metas: HashMap<&String, &TrackMeta> = cache.tracks.iter().map(|m| (&m.path, m)).collect();
+            if field == "bpm" {
+                let bpm = |t: &TrackInfo| metas.get(&t.path).and_then(|m| m.bpm).unwrap_or(f64::INFINITY);
+                tracks.sort_by(|a, b| bpm(a).total_cmp(&bpm(b)));
+            } else {
+                let camelot = |t: &TrackInfo| metas.get(&t.path).and_then(|m| analysis::camelot(m.key.as_deref()?));
+                tracks.sort_by_cached_key(|t| (camelot(t).is_none(), camelot(t)));
+            }
+        }
+        _ => return Err(format!("Unknown sort field: {}", field)),
+    }
+    Ok(tracks)
+}
@@ -0,0 +1,248 @@
+// Real-time spectrum of the playing audio: a tap in the playback chain copies samples into
+// a lock-free ring buffer, and a worker thread turns the latest window into frequency bands
+
+use rodio::source::SeekError;
+use rodio::Source;
+use rustfft::num_complex::Complex;
+use rustfft::FftPlanner;
+use serde::Serialize;
+use std::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+use tauri::{AppHandle, Manager, State};
+
+use crate::{analysis, AppState};
+
+const RING_SIZE: usize = 16384;
+const WINDOW: usize = 2048;
+const MIN_FREQUENCY: f32 = 30.0;
+const MAX_FREQUENCY: f32 = 16000.0;
+const FLOOR_DB: f32 = -60.0;
+
+// Mono samples written by the output thread, read by whoever draws the spectrum.
+// Readers may see a slot overwritten mid-copy, which only costs one frame of accuracy.
+pub struct SampleRing {
+    slots: Vec<AtomicU32>,
+    written: AtomicUsize,
+    sample_rate: AtomicU32,
+    volume: AtomicU32,
+}
+
+impl SampleRing {
+    pub fn new() -> Self {
+        Self {
+            slots: (0..RING_SIZE).map(|_| AtomicU32::new(0)).collect(),
+            written: AtomicUsize::new(0),
+            sample_rate: AtomicU32::new(44100),
+            volume: AtomicU32::new(1.0f32.to_bits()),
+        }
+    }
+    
+    // Sink volume applies after the tap, so the tap scales its copies the same way
+    pub fn set_volume(&self, volume: f32) {
+        self.volume.store(volume.to_bits(), Ordering::Relaxed);
+    }
+    
+    fn push(&self, sample: f32) {
+        let index = self.written.load(Ordering::Relaxed);
+        let volume = f32::from_bits(self.volume.load(Ordering::Relaxed));
+        self.slots[index % RING_SIZE].store((sample * volume).to_bits(), Ordering::Relaxed);
+        self.written.store(index.wrapping_add(1), Ordering::Release);
+    }
+    
+    // The last `count` samples, oldest first
+    fn latest(&self, count: usize) -> Vec<f32> {
+        let end = self.written.load(Ordering::Acquire);
+        (0..count)
+            .map(|i| end.wrapping_sub(count - i))
+            .map(|index| f32::from_bits(self.slots[index % RING_SIZE].load(Ordering::Relaxed)))
+            .collect()
+    }
+}
+
+// Passes samples through unchanged, copying each frame's channel average into the ring
+pub struct Tap<S> {
+    inner: S,
+    ring: Arc<SampleRing>,
+    sum: f32,
+    count: u16,
+}
+
+impl<S: Source<Item = i16>> Tap<S> {
+    pub fn new(inner: S, ring: &Arc<SampleRing>) -> Self {
+        ring.sample_rate.store(inner.sample_rate(), Ordering::Relaxed);
+        Self { inner, ring: ring.clone(), sum: 0.0, count: 0 }
+    }
+}
+
+impl<S: Source<Item = i16>> Iterator for Tap<S> {
+    type Item = i16;
+    
+    fn next(&mut self) -> Option<i16> {
+        let sample = self.inner.next()?;
+        self.sum += sample as f32 / 32768.0;
+        self.count += 1;
+        let channels = self.inner.channels().max(1);
+        if self.count >= channels {
+            self.ring.push(self.sum / channels as f32);
+            self.sum = 0.0;
+            self.count = 0;
+        }
+        Some(sample)
+    }
+}
+
+impl<S: Source<Item = i16>> Source for Tap<S> {
+    fn current_frame_len(&self) -> Option<usize> {
+        self.inner.current_frame_len()
+    }
+    
+    fn channels(&self) -> u16 {
+        self.inner.channels()
+    }
+    
+    fn sample_rate(&self) -> u32 {
+        self.inner.sample_rate()
+    }
+    
+    fn total_duration(&self) -> Option<Duration> {
+        self.inner.total_duration()
+    }
+    
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        self.sum = 0.0;
+        self.count = 0;
+        self.inner.try_seek(pos)
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct SpectrumConfig {
+    bands: usize,
+    fps: u32,
+    events: bool, // Emit "spectrum" events; off until a visualizer asks for them
+}
+
+impl Default for SpectrumConfig {
+    fn default() -> Self {
+        Self { bands: 32, fps: 30, events: false }
+    }
+}
+
+#[derive(Clone, Serialize)]
+pub struct SpectrumFrame {
+    bands: Vec<f32>, // 0.0..1.0 per band, low to high, log-spaced from 30 Hz to 16 kHz
+    peak: f32,       // Level meters over the same window, 0.0..1.0
+    rms: f32,
+}
+
+impl SpectrumFrame {
+    fn silent(bands: usize) -> Self {
+        Self { bands: vec![0.0; bands], peak: 0.0, rms: 0.0 }
+    }
+}
+
+// Decibels mapped so FLOOR_DB and below is 0 and full scale is 1
+fn level(magnitude: f32) -> f32 {
+    let db = 20.0 * magnitude.max(1e-9).log10();
+    ((db - FLOOR_DB) / -FLOOR_DB).clamp(0.0, 1.0)
+}
+
+struct Analyzer {
+    fft: Arc<dyn rustfft::Fft<f32>>,
+    window: Vec<f32>,
+}
+
+impl Analyzer {
+    fn new() -> Self {
+        Self {
+            fft: FftPlanner::<f32>::new().plan_fft_forward(WINDOW),
+            window: analysis::hann(WINDOW),
+        }
+    }
+    
+    fn frame(&self, ring: &SampleRing, bands: usize) -> SpectrumFrame {
+        let samples = ring.latest(WINDOW);
+        let sample_rate = ring.sample_rate.load(Ordering::Relaxed).max(1) as f32;
+        
+        let peak = samples.iter().fold(0.0f32, |max, s| max.max(s.abs()));
+        let rms = (samples.iter().map(|s| s * s).sum::<f32>() / WINDOW as f32).sqrt();
+        
+        let mut buffer: Vec<Complex<f32>> = samples
+            .iter()
+            .zip(&self.window)
+            .map(|(s, w)| Complex::new(s * w, 0.0))
+            .collect();
+        self.fft.process(&mut buffer);
+        // Hann window halves the amplitude, so a full-scale sine reads about 0 dB
+        let magnitudes: Vec<f32> = buffer[..WINDOW / 2].iter().map(|c| c.norm() * 4.0 / WINDOW as f32).collect();
+        
+        // Log-spaced band edges; each band takes its loudest bin, narrow low bands at least one
+        let top = MAX_FREQUENCY.min(sample_rate / 2.0);
+        let bin_width = sample_rate / WINDOW as f32;
+        let edge = |b: usize| MIN_FREQUENCY * (top / MIN_FREQUENCY).powf(b as f32 / bands as f32) / bin_width;
+        let bands = (0..bands)
+            .map(|b| {
+                let start = (edge(b) as usize).min(magnitudes.len() - 1);
+                let end = (edge(b + 1).ceil() as usize).clamp(start + 1, magnitudes.len());
+                level(magnitudes[start..end].iter().cloned().fold(0.0, f32::max))
+            })
+            .collect();
+        
+        SpectrumFrame { bands, peak: level(peak), rms: level(rms) }
+    }
+}
+
+// Emits "spectrum" events at the configured rate while enabled and audio is flowing.
+// One silent frame follows when playback pauses or stops, so meters fall back to zero.
+pub fn spawn_worker(app: AppHandle) {
+    thread::spawn(move || {
+        let analyzer = Analyzer::new();
+        let mut last_written = 0;
+        let mut silent = true;
+        loop {
+            let state = app.state::<AppState>();
+            let config = *state.spectrum.lock().unwrap();
+            if !config.events {
+                thread::sleep(Duration::from_millis(200));
+                continue;
+            }
+            
+            let ring = &state.player.spectrum;
+            let written = ring.written.load(Ordering::Acquire);
+            if written != last_written {
+                let _ = app.emit_all("spectrum", analyzer.frame(ring, config.bands));
+                silent = false;
+            } else if !silent {
+                let _ = app.emit_all("spectrum", SpectrumFrame::silent(config.bands));
+                silent = true;
+            }
+            last_written = written;
+            thread::sleep(Duration::from_millis(1000 / config.fps as u64));
+        }
+    });
+}
+
+// Turn spectrum events on or off and set their band count and rate
+#[tauri::command]
+pub fn set_spectrum(events: bool, bands: Option<usize>, fps: Option<u32>, state: State<AppState>) {
+    let mut config = state.spectrum.lock().unwrap();
+    config.events = events;
+    if let Some(bands) = bands {
+        config.bands = bands.clamp(1, 256);
+    }
+    if let Some(fps) = fps {
+        config.fps = fps.clamp(1, 60);
+    }
+}
+
+// The current spectrum on demand, for callers that poll instead of listening
+#[tauri::command]
+pub fn get_spectrum(bands: Option<usize>, state: State<AppState>) -> SpectrumFrame {
+    let bands = bands.unwrap_or(state.spectrum.lock().unwrap().bands).clamp(1, 256);
+    if state.player.get_current_path().is_none() {
+        return SpectrumFrame::silent(bands);
+    }
+    Analyzer::new().frame(&state.player.spectrum, bands)
+}
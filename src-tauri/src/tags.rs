@@ -0,0 +1,458 @@
+// Tag editing through lofty: batch writes, filename pattern fills, undo,
+// and inference of missing tags from file and folder names
+
+use lofty::{ItemKey, Tag, TagExt, TaggedFileExt};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use tauri::State;
+
+use crate::paths;
+use crate::{
+    extract_track_meta, load_config, load_metadata_cache, save_config, update_metadata_cache, AppState, TrackMeta,
+};
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct TagChange {
+    field: String,
+    value: Option<String>, // None clears the field
+    #[serde(default)]
+    pattern: Option<String>, // e.g. "%track% - %title%", matched against the file stem
+}
+
+#[derive(Serialize, Clone)]
+pub struct TagFailure {
+    path: String,
+    error: String,
+}
+
+#[derive(Serialize, Clone)]
+pub struct TagBatchResult {
+    updated: Vec<TrackMeta>,
+    failed: Vec<TagFailure>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct InferConfig {
+    patterns: Vec<String>,
+    write_back: bool,
+}
+
+// Previous values of every field touched in one file, used to undo a batch
+#[derive(Clone)]
+pub struct TagSnapshot {
+    path: String,
+    values: Vec<(ItemKey, Option<String>)>,
+}
+
+// Point undo snapshots at files that were moved since the batch was written
+pub fn relocate_undo(moves: &HashMap<String, String>, state: &AppState) {
+    for snapshot in state.tag_undo.lock().unwrap().iter_mut() {
+        if let Some(new_path) = moves.get(&snapshot.path) {
+            snapshot.path = new_path.clone();
+        }
+    }
+}
+
+// Map a user-facing field name to the generic lofty key
+pub fn field_key(field: &str) -> Option<ItemKey> {
+    let key = match field.to_lowercase().as_str() {
+        "artist" => ItemKey::TrackArtist,
+        "title" => ItemKey::TrackTitle,
+        "album" => ItemKey::AlbumTitle,
+        "albumartist" => ItemKey::AlbumArtist,
+        "genre" => ItemKey::Genre,
+        "year" | "date" => ItemKey::Year,
+        "track" => ItemKey::TrackNumber,
+        "tracktotal" => ItemKey::TrackTotal,
+        "disc" => ItemKey::DiscNumber,
+        "disctotal" => ItemKey::DiscTotal,
+        "comment" => ItemKey::Comment,
+        "composer" => ItemKey::Composer,
+        _ => return None,
+    };
+    Some(key)
+}
+
+fn is_numeric_key(key: &ItemKey) -> bool {
+    matches!(
+        key,
+        ItemKey::TrackNumber | ItemKey::TrackTotal | ItemKey::DiscNumber | ItemKey::DiscTotal | ItemKey::Year
+    )
+}
+
+// Normalize a value before writing; numeric fields keep only their leading
+// number, so "03/12" becomes "3" and "2019-04-01" becomes "2019"
+fn normalize_value(key: &ItemKey, value: &str) -> Result<String, String> {
+    let value = value.trim();
+    if !is_numeric_key(key) {
+        return Ok(value.to_string());
+    }
+    let number: String = value.chars().take_while(|c| c.is_ascii_digit()).collect();
+    number
+        .parse::<u32>()
+        .map(|n| n.to_string())
+        .map_err(|_| format!("'{}' is not a number", value))
+}
+
+// Match text against a pattern made of literals and %name% placeholders.
+// Placeholders are non-empty and never span a '/', so the same matcher works
+// for file stems and for relative paths.
+pub fn match_pattern(pattern: &str, text: &str) -> Option<HashMap<String, String>> {
+    enum Segment {
+        Literal(String),
+        Field(String),
+    }
+
+    let mut segments = Vec::new();
+    let mut rest = pattern;
+    while let Some(start) = rest.find('%') {
+        if start > 0 {
+            segments.push(Segment::Literal(rest[..start].to_string()));
+        }
+        let after = &rest[start + 1..];
+        let end = after.find('%')?;
+        segments.push(Segment::Field(after[..end].to_lowercase()));
+        rest = &after[end + 1..];
+    }
+    if !rest.is_empty() {
+        segments.push(Segment::Literal(rest.to_string()));
+    }
+
+    fn walk(segments: &[Segment], text: &str, captures: &mut HashMap<String, String>) -> bool {
+        match segments.first() {
+            None => text.is_empty(),
+            Some(Segment::Literal(lit)) => {
+                text.starts_with(lit.as_str()) && walk(&segments[1..], &text[lit.len()..], captures)
+            }
+            Some(Segment::Field(name)) => {
+                if text.is_empty() {
+                    return false;
+                }
+                // Shortest capture first, backtracking on failure
+                let ends = text.char_indices().map(|(i, _)| i).skip(1).chain(std::iter::once(text.len()));
+                for i in ends {
+                    if text[..i].contains('/') {
+                        break;
+                    }
+                    if walk(&segments[1..], &text[i..], captures) {
+                        captures.insert(name.clone(), text[..i].trim().to_string());
+                        return true;
+                    }
+                }
+                false
+            }
+        }
+    }
+
+    let mut captures = HashMap::new();
+    if walk(&segments, text, &mut captures) {
+        Some(captures)
+    } else {
+        None
+    }
+}
+
+// Write the given key/value pairs to the file's primary tag, creating it if
+// needed. Returns the values that were there before.
+pub fn write_fields(path: &str, fields: &[(ItemKey, Option<String>)]) -> Result<Vec<(ItemKey, Option<String>)>, String> {
+    let mut tagged_file = lofty::read_from_path(paths::from_string(path)).map_err(|e| e.to_string())?;
+
+    if tagged_file.primary_tag().is_none() {
+        let tag_type = tagged_file.primary_tag_type();
+        tagged_file.insert_tag(Tag::new(tag_type));
+    }
+    let tag = tagged_file
+        .primary_tag_mut()
+        .ok_or("File format does not support tags")?;
+
+    let mut previous = Vec::new();
+    for (key, value) in fields {
+        previous.push((key.clone(), tag.get_string(key).map(|s| s.to_string())));
+        match value {
+            Some(v) => {
+                if !tag.insert_text(key.clone(), v.clone()) {
+                    return Err(format!("Field {:?} is not supported by this format", key));
+                }
+            }
+            None => tag.remove_key(key),
+        }
+    }
+
+    tag.save_to_path(path).map_err(|e| e.to_string())?;
+    Ok(previous)
+}
+
+// Re-read tags for the given files and swap them into the metadata cache in one save
+pub fn refresh_cached_meta(state: &AppState, paths: &[String]) -> Result<Vec<TrackMeta>, String> {
+    let patterns = load_config().infer_patterns;
+    let mut updated: Vec<TrackMeta> = paths.iter().map(|p| extract_track_meta(p, &patterns)).collect();
+
+    update_metadata_cache(state, |cache| {
+        // Writing tags leaves the audio, and so its fingerprint, analysis and resume position, unchanged
+        for meta in updated.iter_mut() {
+            if let Some(cached) = cache.tracks.iter().find(|t| t.path == meta.path) {
+                meta.fingerprint = cached.fingerprint.clone();
+                meta.resume_ms = cached.resume_ms;
+                meta.played = cached.played;
+                meta.book = cached.book.clone();
+                meta.bpm = meta.bpm.or(cached.bpm);
+                meta.key = meta.key.take().or_else(|| cached.key.clone());
+            }
+        }
+
+        let touched: HashSet<&String> = paths.iter().collect();
+        cache.tracks.retain(|t| !touched.contains(&t.path));
+        cache.tracks.extend(updated.iter().cloned());
+        true
+    })?;
+
+    Ok(updated)
+}
+
+// Fill fields the tags left empty from the first path pattern that matches.
+// A pattern with N slashes is matched against the last N+1 path components,
+// with the extension dropped from the file name.
+pub fn infer_missing(meta: &mut TrackMeta, patterns: &[String]) {
+    if meta.artist.is_some() && meta.title.is_some() && meta.album.is_some() && meta.track_number.is_some() {
+        return;
+    }
+    
+    let path = &paths::from_string(&meta.path);
+    let mut components: Vec<String> = path
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy().to_string())
+        .collect();
+    if let (Some(last), Some(stem)) = (components.last_mut(), path.file_stem()) {
+        *last = stem.to_string_lossy().to_string();
+    }
+    
+    for pattern in patterns {
+        let pattern = pattern.replace('\\', "/");
+        let depth = pattern.matches('/').count() + 1;
+        if components.len() < depth {
+            continue;
+        }
+        let text = components[components.len() - depth..].join("/");
+        let captures = match match_pattern(&pattern, &text) {
+            Some(c) => c,
+            None => continue,
+        };
+        
+        for (field, value) in captures {
+            if value.is_empty() {
+                continue;
+            }
+            let filled = match field.as_str() {
+                "artist" if meta.artist.is_none() => {
+                    meta.artist = Some(value);
+                    true
+                }
+                "title" if meta.title.is_none() => {
+                    meta.title = Some(value);
+                    true
+                }
+                "album" if meta.album.is_none() => {
+                    meta.album = Some(value);
+                    true
+                }
+                "track" if meta.track_number.is_none() => {
+                    meta.track_number = normalize_value(&ItemKey::TrackNumber, &value)
+                        .ok()
+                        .and_then(|n| n.parse().ok());
+                    meta.track_number.is_some()
+                }
+                _ => false,
+            };
+            if filled {
+                meta.inferred.push(field);
+            }
+        }
+        meta.inferred.sort();
+        return;
+    }
+}
+
+// Drop inferred values so the entry reflects only what the tags say
+fn clear_inferred(meta: &mut TrackMeta) {
+    for field in std::mem::take(&mut meta.inferred) {
+        match field.as_str() {
+            "artist" => meta.artist = None,
+            "title" => meta.title = None,
+            "album" => meta.album = None,
+            "track" => meta.track_number = None,
+            _ => {}
+        }
+    }
+}
+
+// Write a track's inferred values into its tags
+pub fn write_inferred(meta: &TrackMeta) -> Result<Vec<(ItemKey, Option<String>)>, String> {
+    let mut fields = Vec::new();
+    for field in &meta.inferred {
+        let value = match field.as_str() {
+            "artist" => meta.artist.clone(),
+            "title" => meta.title.clone(),
+            "album" => meta.album.clone(),
+            "track" => meta.track_number.map(|n| n.to_string()),
+            _ => None,
+        };
+        if let (Some(key), Some(value)) = (field_key(field), value) {
+            fields.push((key, Some(value)));
+        }
+    }
+    if fields.is_empty() {
+        return Ok(Vec::new());
+    }
+    write_fields(&meta.path, &fields)
+}
+
+fn resolve_changes(path: &str, changes: &[TagChange]) -> Result<Vec<(ItemKey, Option<String>)>, String> {
+    let stem = paths::from_string(path)
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+    
+    let mut fields = Vec::new();
+    for change in changes {
+        let key = field_key(&change.field).ok_or_else(|| format!("Unknown tag field: {}", change.field))?;
+        let value = if let Some(ref pattern) = change.pattern {
+            let captures = match_pattern(pattern, &stem)
+                .ok_or_else(|| format!("Filename does not match pattern '{}'", pattern))?;
+            let captured = captures
+                .get(&change.field.to_lowercase())
+                .ok_or_else(|| format!("Pattern '{}' has no %{}% field", pattern, change.field))?;
+            Some(normalize_value(&key, captured)?)
+        } else {
+            match change.value {
+                Some(ref v) if !v.trim().is_empty() => Some(normalize_value(&key, v)?),
+                _ => None,
+            }
+        };
+        fields.push((key, value));
+    }
+    Ok(fields)
+}
+
+#[tauri::command]
+pub fn write_tags(paths: Vec<String>, changes: Vec<TagChange>, state: State<AppState>) -> Result<TagBatchResult, String> {
+    if changes.is_empty() {
+        return Err("No tag changes given".to_string());
+    }
+    
+    let mut snapshots = Vec::new();
+    let mut written = Vec::new();
+    let mut failed = Vec::new();
+    
+    for path in &paths {
+        let result = resolve_changes(path, &changes).and_then(|fields| write_fields(path, &fields));
+        match result {
+            Ok(previous) => {
+                snapshots.push(TagSnapshot {
+                    path: path.clone(),
+                    values: previous,
+                });
+                written.push(path.clone());
+            }
+            Err(error) => failed.push(TagFailure {
+                path: path.clone(),
+                error,
+            }),
+        }
+    }
+    
+    // Only a batch that actually changed something replaces the undo buffer
+    if !snapshots.is_empty() {
+        *state.tag_undo.lock().unwrap() = snapshots;
+    }
+    
+    let updated = refresh_cached_meta(&state, &written)?;
+    Ok(TagBatchResult { updated, failed })
+}
+
+#[tauri::command]
+pub fn undo_tag_batch(state: State<AppState>) -> Result<TagBatchResult, String> {
+    let snapshots = std::mem::take(&mut *state.tag_undo.lock().unwrap());
+    if snapshots.is_empty() {
+        return Err("Nothing to undo".to_string());
+    }
+    
+    let mut restored = Vec::new();
+    let mut failed = Vec::new();
+    
+    for snapshot in snapshots.iter().rev() {
+        match write_fields(&snapshot.path, &snapshot.values) {
+            Ok(_) => restored.push(snapshot.path.clone()),
+            Err(error) => failed.push(TagFailure {
+                path: snapshot.path.clone(),
+                error,
+            }),
+        }
+    }
+    
+    let updated = refresh_cached_meta(&state, &restored)?;
+    Ok(TagBatchResult { updated, failed })
+}
+
+#[tauri::command]
+pub fn get_infer_config() -> InferConfig {
+    let config = load_config();
+    InferConfig {
+        patterns: config.infer_patterns,
+        write_back: config.write_inferred_tags,
+    }
+}
+
+// Save new patterns and re-run inference over the whole cache so the artist
+// view picks them up without a rescan. Returns how many tracks have inferred values.
+#[tauri::command]
+pub fn set_infer_config(patterns: Vec<String>, write_back: bool, state: State<AppState>) -> Result<usize, String> {
+    let mut config = load_config();
+    config.infer_patterns = patterns.into_iter().filter(|p| !p.trim().is_empty()).collect();
+    config.write_inferred_tags = write_back;
+    save_config(&config)?;
+    
+    let mut inferred = 0;
+    update_metadata_cache(&state, |cache| {
+        for meta in cache.tracks.iter_mut() {
+            clear_inferred(meta);
+            infer_missing(meta, &config.infer_patterns);
+        }
+        inferred = cache.tracks.iter().filter(|t| !t.inferred.is_empty()).count();
+        true
+    })?;
+    
+    Ok(inferred)
+}
+
+#[tauri::command]
+pub fn write_inferred_tags(tracks: Vec<String>, state: State<AppState>) -> Result<TagBatchResult, String> {
+    let cache = load_metadata_cache();
+    let requested: HashSet<&String> = tracks.iter().collect();
+    
+    let mut snapshots = Vec::new();
+    let mut written = Vec::new();
+    let mut failed = Vec::new();
+    
+    for meta in cache.tracks.iter().filter(|t| requested.contains(&t.path) && !t.inferred.is_empty()) {
+        match write_inferred(meta) {
+            Ok(previous) => {
+                snapshots.push(TagSnapshot {
+                    path: meta.path.clone(),
+                    values: previous,
+                });
+                written.push(meta.path.clone());
+            }
+            Err(error) => failed.push(TagFailure {
+                path: meta.path.clone(),
+                error,
+            }),
+        }
+    }
+    
+    if !snapshots.is_empty() {
+        *state.tag_undo.lock().unwrap() = snapshots;
+    }
+    
+    let updated = refresh_cached_meta(&state, &written)?;
+    Ok(TagBatchResult { updated, failed })
+}
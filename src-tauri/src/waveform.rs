@@ -0,0 +1,129 @@
+// Waveform overview of a track for the seek bar, cached on disk per file and mtime
+
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::PathBuf;
+use std::sync::atomic::Ordering;
+use tauri::{AppHandle, Manager, State};
+
+use crate::{book, cue, decode, paths, AppState};
+
+// Frames summarized together before they are grouped into buckets
+const BLOCK_FRAMES: usize = 256;
+const MAX_BUCKETS: usize = 4096;
+
+#[derive(Serialize, Deserialize)]
+pub struct Waveform {
+    min: Vec<f32>,
+    max: Vec<f32>,
+    rms: Vec<f32>,
+}
+
+#[derive(Clone, Copy)]
+struct Block {
+    min: f32,
+    max: f32,
+    squares: f64,
+    frames: usize,
+}
+
+fn get_waveforms_dir() -> Option<PathBuf> {
+    dirs::cache_dir().map(|p| p.join("vi-music").join("waveforms"))
+}
+
+// Cache file for a track, keyed by path, the backing file's size and mtime, and bucket count
+fn cache_file(path: &str, buckets: usize) -> Option<PathBuf> {
+    let metadata = fs::metadata(paths::from_string(&cue::backing_file(path))).ok()?;
+    let mut hasher = DefaultHasher::new();
+    path.hash(&mut hasher);
+    metadata.len().hash(&mut hasher);
+    metadata.modified().ok().hash(&mut hasher);
+    buckets.hash(&mut hasher);
+    Some(get_waveforms_dir()?.join(format!("{:016x}.json", hasher.finish())))
+}
+
+// Group blocks into `buckets` evenly sized buckets; short tracks repeat blocks
+fn into_buckets(blocks: &[Block], buckets: usize) -> Waveform {
+    let mut waveform = Waveform { min: Vec::new(), max: Vec::new(), rms: Vec::new() };
+    for b in 0..buckets {
+        let start = b * blocks.len() / buckets;
+        let end = ((b + 1) * blocks.len() / buckets).max(start + 1);
+        let group = &blocks[start..end];
+        let frames: usize = group.iter().map(|block| block.frames).sum();
+        let squares: f64 = group.iter().map(|block| block.squares).sum();
+        waveform.min.push(group.iter().map(|block| block.min).fold(0.0, f32::min));
+        waveform.max.push(group.iter().map(|block| block.max).fold(0.0, f32::max));
+        waveform.rms.push((squares / frames.max(1) as f64).sqrt() as f32);
+    }
+    waveform
+}
+
+// Per-bucket min, max and RMS of a track, from the cache when the file is unchanged.
+// Every call supersedes the previous one: a decode still running for an earlier track
+// stops and returns None, so skipping quickly through tracks never queues up work.
+#[tauri::command]
+pub async fn get_waveform(path: String, buckets: usize, app: AppHandle) -> Result<Option<Waveform>, String> {
+    let request = app.state::<AppState>().waveform_request.fetch_add(1, Ordering::SeqCst) + 1;
+    tauri::async_runtime::spawn_blocking(move || compute_waveform(&app.state::<AppState>(), &path, buckets, request))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+fn compute_waveform(state: &AppState, path: &str, buckets: usize, request: u64) -> Result<Option<Waveform>, String> {
+    // A book's parts would take too long to decode as one
+    if book::is_book(path) {
+        return Ok(None);
+    }
+    let buckets = buckets.clamp(1, MAX_BUCKETS);
+    
+    let cached = cache_file(path, buckets);
+    if let Some(waveform) = cached
+        .as_ref()
+        .and_then(|file| fs::read_to_string(file).ok())
+        .and_then(|content| serde_json::from_str(&content).ok())
+    {
+        return Ok(Some(waveform));
+    }
+    
+    let frames = decode::stream_mono(path, None).ok_or("Could not decode track")?;
+    let mut blocks = Vec::new();
+    let mut block = Block { min: 0.0, max: 0.0, squares: 0.0, frames: 0 };
+    for sample in frames {
+        block.min = block.min.min(sample);
+        block.max = block.max.max(sample);
+        block.squares += (sample * sample) as f64;
+        block.frames += 1;
+        if block.frames == BLOCK_FRAMES {
+            blocks.push(block);
+            block = Block { min: 0.0, max: 0.0, squares: 0.0, frames: 0 };
+            if state.waveform_request.load(Ordering::SeqCst) != request {
+                return Ok(None);
+            }
+        }
+    }
+    if block.frames > 0 {
+        blocks.push(block);
+    }
+    if blocks.is_empty() {
+        return Err("Track has no audio".to_string());
+    }
+    
+    let waveform = into_buckets(&blocks, buckets);
+    if let Some(file) = cached {
+        if let Some(parent) = file.parent() {
+            let _ = fs::create_dir_all(parent);
+        }
+        if let Ok(content) = serde_json::to_string(&waveform) {
+            let _ = fs::write(file, content);
+        }
+    }
+    Ok(Some(waveform))
+}
+
+// Stop a waveform decode in progress, e.g. when playback stops
+#[tauri::command]
+pub fn cancel_waveform(state: State<AppState>) {
+    state.waveform_request.fetch_add(1, Ordering::SeqCst);
+}
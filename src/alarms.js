@@ -0,0 +1,160 @@
+import { invoke } from './tauri.js';
+import { state } from './state.js';
+import { updateStatus, updateNowPlaying, updateTrackArt, updatePlayButton, updateProgressDisplay } from './ui.js';
+import { renderPlaylist } from './views/playlist.js';
+import { updateViewModeIndicator } from './views/folder.js';
+import { updateQueueDisplay } from './queue.js';
+import { loadWaveform } from './waveform.js';
+import { stop } from './playback.js';
+import { showReport } from './report.js';
+
+const REPEATS = ['once', 'daily', 'weekdays'];
+
+// What an alarm plays when :alarm names nothing: the loaded saved playlist, else the selected track
+function defaultTarget() {
+    if (state.rootFolder?.startsWith('Playlist: ')) {
+        return state.rootFolder.slice('Playlist: '.length);
+    }
+    return state.playlist[state.selectedIndex]?.path ?? null;
+}
+
+// :alarm HH:MM [once|daily|weekdays] [fade=<seconds>] [playlist name | track path]
+export async function handleAlarmCommand(args) {
+    if (!args) {
+        await showAlarms();
+        return;
+    }
+    
+    const words = args.split(/\s+/);
+    const time = words.shift();
+    let repeat = 'once';
+    let fadeSecs = null;
+    while (words.length > 0) {
+        const word = words[0].toLowerCase();
+        if (REPEATS.includes(word)) {
+            repeat = word;
+        } else if (/^fade=\d+$/.test(word)) {
+            fadeSecs = parseInt(word.slice('fade='.length));
+        } else {
+            break;
+        }
+        words.shift();
+    }
+    
+    const target = words.join(' ') || defaultTarget();
+    if (!target) {
+        updateStatus('Usage: :alarm HH:MM [once|daily|weekdays] [fade=<seconds>] [playlist | track path]');
+        return;
+    }
+    // Paths name tracks, anything else a saved playlist
+    const playlist = !/[\\/]/.test(target);
+    
+    try {
+        const alarms = await invoke('add_alarm', { time, repeat, target, playlist, fadeSecs });
+        const alarm = alarms[alarms.length - 1];
+        updateStatus(`Alarm ${alarm.id} set: ${alarm.name} ${repeat} at ${time}` + (alarm.next ? ` (next: ${alarm.next})` : ''));
+    } catch (err) {
+        updateStatus(`Error: ${err}`);
+    }
+}
+
+// :alarms — list alarms; Enter turns the selected one on or off
+export async function showAlarms() {
+    try {
+        const alarms = await invoke('list_alarms');
+        if (alarms.length === 0) {
+            updateStatus('No alarms set. Usage: :alarm HH:MM [daily|weekdays] [playlist | track path]');
+            return;
+        }
+        
+        const pad = n => n.toString().padStart(2, '0');
+        showReport({
+            title: 'Alarms',
+            summary: `${alarms.length} alarm${alarms.length !== 1 ? 's' : ''} — :delalarm <n> removes one`,
+            items: alarms.map(alarm => ({
+                label: `${alarm.id}. ${pad(alarm.hour)}:${pad(alarm.minute)} ${alarm.repeat} — ${alarm.name}`,
+                detail: `${alarm.playlist ? 'playlist' : alarm.target}, fades in over ${alarm.fade_secs}s` + (alarm.next ? `, next ${alarm.next}` : ''),
+                status: alarm.enabled ? 'on' : 'off',
+                statusText: alarm.enabled ? 'on' : 'off',
+                alarm,
+            })),
+            onConfirm: item => toggleAlarm(item.alarm.id),
+            confirmLabel: 'turn on/off',
+        });
+    } catch (err) {
+        updateStatus(`Error: ${err}`);
+    }
+}
+
+async function toggleAlarm(id) {
+    try {
+        const alarms = await invoke('toggle_alarm', { id });
+        const alarm = alarms.find(a => a.id === id);
+        updateStatus(`Alarm ${id} ${alarm.enabled ? `on (next: ${alarm.next})` : 'off'}`);
+    } catch (err) {
+        updateStatus(`Error: ${err}`);
+    }
+}
+
+export async function removeAlarm(args) {
+    const id = parseInt(args);
+    if (isNaN(id)) {
+        updateStatus('Usage: :delalarm <n> (see :alarms)');
+        return;
+    }
+    try {
+        await invoke('remove_alarm', { id });
+        updateStatus(`Alarm ${id} removed`);
+    } catch (err) {
+        updateStatus(`Error: ${err}`);
+    }
+}
+
+// :snooze [minutes] — stop the alarm that is ringing and ring it again later
+export async function snoozeAlarm(args) {
+    const minutes = args ? parseInt(args) : null;
+    if (args && (isNaN(minutes) || minutes < 1)) {
+        updateStatus('Usage: :snooze [minutes]');
+        return;
+    }
+    try {
+        const at = await invoke('snooze_alarm', { minutes });
+        await stop();
+        updateStatus(`Snoozed until ${at}`);
+    } catch (err) {
+        updateStatus(`${err}`);
+    }
+}
+
+// The backend starts alarms itself; this brings the list and player bar up to date
+export async function setupAlarmListener(listen) {
+    if (!listen) return;
+    
+    await listen('alarm-started', (event) => {
+        const { name, playlist, tracks, index } = event.payload;
+        const track = tracks[index];
+        state.playlist = tracks;
+        state.selectedIndex = index;
+        state.playingIndex = index;
+        state.queue = [];
+        state.rootFolder = playlist ? `Playlist: ${name}` : `Alarm: ${name}`;
+        state.viewMode = 'list';
+        state.isPlaying = true;
+        state.isPaused = false;
+        state.duration = track.duration;
+        state.elapsed = 0;
+        updateNowPlaying(track.name);
+        updateTrackArt(track.path);
+        loadWaveform(track.path);
+        renderPlaylist();
+        updateViewModeIndicator();
+        updateQueueDisplay();
+        updatePlayButton();
+        updateProgressDisplay();
+        updateStatus(`Alarm: ${name} (:snooze to snooze)`);
+    });
+    
+    await listen('alarm-failed', (event) => {
+        updateStatus(event.payload);
+    });
+}
@@ -0,0 +1,69 @@
+import { invoke } from './tauri.js';
+import { state } from './state.js';
+import { updateStatus, showLoading, hideLoading } from './ui.js';
+import { renderPlaylist } from './views/playlist.js';
+
+function listedTracks() {
+    return state.filteredPlaylist.length > 0
+        ? state.filteredPlaylist.map(f => f.track)
+        : state.playlist;
+}
+
+// :analyze [write] — estimate BPM and key of the listed tracks, ! re-analyzes known ones
+export async function analyzeTracks(args, force) {
+    const tracks = listedTracks();
+    if (tracks.length === 0) {
+        updateStatus('No tracks to analyze');
+        return;
+    }
+    
+    const writeTags = args.trim().toLowerCase() === 'write';
+    try {
+        showLoading(`Analyzing ${tracks.length} track${tracks.length !== 1 ? 's' : ''}...`);
+        const report = await invoke('analyze_tracks', { paths: tracks.map(t => t.path), writeTags, force });
+        hideLoading();
+        
+        let message = `Analyzed ${report.analyzed} track${report.analyzed !== 1 ? 's' : ''}`;
+        if (writeTags) message += `, wrote ${report.written} tag${report.written !== 1 ? 's' : ''}`;
+        if (report.failed > 0) message += `, ${report.failed} failed`;
+        updateStatus(message);
+    } catch (err) {
+        hideLoading();
+        updateStatus(`Error: ${err}`);
+    }
+}
+
+// :bpm <min>-<max> — list the loaded tracks within a tempo range, slowest first
+export async function showTempoRange(range) {
+    const match = range.match(/^(\d+(?:\.\d+)?)\s*-\s*(\d+(?:\.\d+)?)$/);
+    if (!match) {
+        updateStatus('Usage: :bpm <min>-<max> (e.g. :bpm 120-130)');
+        return;
+    }
+    const min = parseFloat(match[1]);
+    const max = parseFloat(match[2]);
+    
+    try {
+        let tracks = await invoke('tracks_in_tempo', { tracks: state.playlist, min, max });
+        if (tracks.length === 0) {
+            updateStatus(`No analyzed tracks between ${min} and ${max} BPM (try :analyze)`);
+            return;
+        }
+        tracks = await invoke('sort_tracks', { tracks, field: 'bpm' });
+        tracks.forEach((track, i) => track.index = i);
+        await invoke('set_playlist', { paths: tracks.map(t => t.path) });
+        
+        if (state.rootFolder) {
+            state.previousRootFolder = state.rootFolder;
+        }
+        state.playlist = tracks;
+        state.selectedIndex = 0;
+        state.playingIndex = -1;
+        state.viewMode = 'list';
+        state.rootFolder = `BPM ${min}-${max}`;
+        renderPlaylist();
+        updateStatus(`${tracks.length} track${tracks.length !== 1 ? 's' : ''} between ${min} and ${max} BPM`);
+    } catch (err) {
+        updateStatus(`Error: ${err}`);
+    }
+}
@@ -8,9 +8,15 @@ import { loadKeybindings } from './keybindings.js';
 import { loadSettings } from './settings.js';
 import { refreshStatus, startProgressUpdater, setupMediaControlListener, togglePause, prevTrack, nextTrack, stop, seekTo } from './playback.js';
 import { handleKeyDown } from './keyboard.js';
+import { setupLyricsListener } from './lyrics.js';
+import { setupChapterListener } from './chapters.js';
+import { setupSleepListener } from './features.js';
+import { setupAlarmListener } from './alarms.js';
+import { setupSpectrumListener } from './spectrum.js';
 import { handleCommandInput, exitCommandMode } from './commands.js';
 import { handleFilterInput, handleFilterKeydown, exitFilterMode } from './filter.js';
 import { loadFolder } from './views/folder.js';
+import { restoreSession, startSessionSync } from './session.js';
 
 // Initialize
 async function init() {
@@ -19,10 +25,18 @@ async function init() {
     updateVolumeDisplay();
     setupEventListeners();
     setupMediaControlListener(listen);
+    setupLyricsListener(listen);
+    setupChapterListener(listen);
+    setupSleepListener(listen);
+    setupAlarmListener(listen);
+    setupSpectrumListener(listen);
     setupHelpTabs();
     await refreshStatus();
     startProgressUpdater();
-    await loadDefaultFolder();
+    if (!(await restoreSession())) {
+        await loadDefaultFolder();
+    }
+    startSessionSync();
 }
 
 async function loadDefaultFolder() {
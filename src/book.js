@@ -0,0 +1,41 @@
+import { invoke } from './tauri.js';
+import { state } from './state.js';
+import { updateStatus } from './ui.js';
+import { playTrack } from './playback.js';
+import { renderPlaylist } from './views/playlist.js';
+import { updateViewModeIndicator } from './views/folder.js';
+
+// Folder for :book with no argument: the selected folder in the folder view, else the one
+// being browsed, else the selected track's folder
+function bookFolder() {
+    if (state.viewMode === 'folder') {
+        const item = state.folderContents[state.folderSelectedIndex];
+        return item?.is_folder ? item.path : state.currentFolder;
+    }
+    const track = state.playlist[state.selectedIndex];
+    return track ? track.path.replace(/[\\/][^\\/]*$/, '') : null;
+}
+
+// :book [folder] — play a folder as one continuous audiobook
+export async function loadBook(args) {
+    const folder = args.trim() || bookFolder();
+    if (!folder) {
+        updateStatus('Usage: :book <folder> (or select a folder or track)');
+        return;
+    }
+    
+    try {
+        const tracks = await invoke('load_book', { path: folder });
+        state.playlist = tracks;
+        state.selectedIndex = 0;
+        state.playingIndex = -1;
+        state.queue = [];
+        state.rootFolder = `Book: ${tracks[0].name}`;
+        state.viewMode = 'list';
+        renderPlaylist();
+        updateViewModeIndicator();
+        await playTrack(0, null, false);
+    } catch (err) {
+        updateStatus(`Error: ${err}`);
+    }
+}
@@ -0,0 +1,91 @@
+import { invoke } from './tauri.js';
+import { state } from './state.js';
+import { updateStatus, updateProgressDisplay } from './ui.js';
+import { renderPlaylist } from './views/playlist.js';
+
+function reportJump(jump) {
+    state.elapsed = jump.position;
+    updateProgressDisplay();
+    updateStatus(`Chapter ${jump.index + 1}: ${jump.title}`);
+}
+
+export async function nextChapter() {
+    try {
+        reportJump(await invoke('next_chapter'));
+    } catch (err) {
+        updateStatus(`${err}`);
+    }
+}
+
+export async function prevChapter() {
+    try {
+        reportJump(await invoke('prev_chapter'));
+    } catch (err) {
+        updateStatus(`${err}`);
+    }
+}
+
+// Play a chapter from the expanded playlist, starting its track first if needed
+export async function playChapter(trackIndex, chapterIndex) {
+    const track = state.playlist[trackIndex];
+    const chapter = state.expandedChapters[track?.path]?.[chapterIndex];
+    if (!chapter) return;
+    
+    if (trackIndex === state.playingIndex && state.isPlaying) {
+        try {
+            reportJump(await invoke('play_chapter', { index: chapterIndex }));
+        } catch (err) {
+            updateStatus(`Error: ${err}`);
+        }
+    } else {
+        const { playTrack } = await import('./playback.js');
+        await playTrack(trackIndex, Math.ceil(chapter.start_ms / 1000), false);
+    }
+}
+
+// za / zo / zc — show the selected track's chapters as sub-entries in the playlist
+export async function toggleChapters(mode = 'toggle') {
+    if (state.viewMode !== 'list') return;
+    const track = state.filteredPlaylist.length > 0
+        ? state.filteredPlaylist[state.selectedIndex]?.track
+        : state.playlist[state.selectedIndex];
+    if (!track) return;
+    
+    const expanded = Boolean(state.expandedChapters[track.path]);
+    if (mode === 'close' || (mode === 'toggle' && expanded)) {
+        delete state.expandedChapters[track.path];
+        renderPlaylist();
+        return;
+    }
+    if (expanded) return;
+    
+    try {
+        const chapters = await invoke('get_chapters', { path: track.path });
+        if (chapters.length === 0) {
+            updateStatus('Track has no chapters');
+            return;
+        }
+        state.expandedChapters[track.path] = chapters;
+        renderPlaylist();
+    } catch (err) {
+        updateStatus(`Error: ${err}`);
+    }
+}
+
+export async function setupChapterListener(listen) {
+    if (!listen) return;
+    
+    await listen('chapter-changed', (event) => {
+        const { path, index, title } = event.payload;
+        const changed = state.currentChapter.path !== path || state.currentChapter.index !== index;
+        state.currentChapter = { path, index };
+        if (!changed) return;
+        
+        if (index !== null && title) {
+            updateStatus(`Chapter ${index + 1}: ${title}`);
+        }
+        if (state.expandedChapters[path] && state.viewMode === 'list') {
+            renderPlaylist();
+        }
+    });
+}
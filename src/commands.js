@@ -1,16 +1,26 @@
 import { state, elements } from './state.js';
 import { updateStatus, updateModeIndicators, toggleHelp } from './ui.js';
 import { playTrack, playSelected, togglePause, stop, nextTrack, prevTrack, setVolume, seekTo, jumpToPercent } from './playback.js';
-import { openFolder, loadFolder, reloadContent } from './views/folder.js';
+import { openFolder, openPlaylistFile, loadFolder, reloadContent } from './views/folder.js';
 import { openArtistView } from './views/artist.js';
 import { renderPlaylist } from './views/playlist.js';
 import { moveSelectionRelative } from './navigation.js';
 import { handleSetCommand, showCurrentSettings } from './settings.js';
-import { setSleepTimer, adjustSleepTimer, setBookmark, showBookmarks, deleteBookmark } from './features.js';
-import { getLibraryFolders, addLibraryFolder, removeLibraryFolder, scanLibrary, showLibraryFolders } from './library.js';
+import { handleSleepCommand, setBookmark, showBookmarks, deleteBookmark } from './features.js';
+import { getLibraryFolders, addLibraryFolder, removeLibraryFolder, scanLibrary, showLibraryFolders, remapLibrary } from './library.js';
 import { showAudioDevices, setAudioDevice, setAudioDeviceByIndex } from './devices.js';
-import { savePlaylist, loadSavedPlaylist, renamePlaylist, deletePlaylist, showPlaylistManager } from './playlists.js';
+import { savePlaylist, loadSavedPlaylist, renamePlaylist, deletePlaylist, showPlaylistManager, importPlaylistFile, exportPlaylistFile } from './playlists.js';
 import { deleteTrackRange } from './visual.js';
+import { writeTags, fillTagsFromFilename, undoTagBatch, handleInferCommand, writeInferredTags } from './tags.js';
+import { organizeFiles } from './organize.js';
+import { repairPlaylists } from './repair.js';
+import { loadBook } from './book.js';
+import { handleAlarmCommand, showAlarms, removeAlarm, snoozeAlarm } from './alarms.js';
+import { findDuplicates, findRecording, exportFingerprints } from './duplicates.js';
+import { analyzeTracks, showTempoRange } from './analysis.js';
+import { toggleSpectrum } from './spectrum.js';
+import { handleLyricsCommand } from './lyrics.js';
+import { nextChapter, prevChapter, toggleChapters } from './chapters.js';
 import { invoke, open } from './tauri.js';
 
 // Command Mode
@@ -74,6 +84,8 @@ export function executeCommand(cmd) {
     
     const parts = trimmed.toLowerCase().split(/\s+/);
     const command = parts[0];
+    // Arguments with their original case (tag values, patterns, paths)
+    const rawArgs = trimmed.slice(command.length).trim();
     
     switch (command) {
         case 'q':
@@ -84,6 +96,10 @@ export function executeCommand(cmd) {
         case 'o':
             openFolder();
             break;
+        case 'openlist':
+        case 'ol':
+            openPlaylistFile();
+            break;
         case 'reload':
         case 'r':
             reloadContent();
@@ -148,6 +164,18 @@ export function executeCommand(cmd) {
         case 'pl':
             showPlaylistManager();
             break;
+        case 'import':
+            importPlaylistFile();
+            break;
+        case 'remap':
+            remapLibrary(rawArgs);
+            break;
+        case 'repair':
+            repairPlaylists(rawArgs);
+            break;
+        case 'export':
+            exportPlaylistFile(rawArgs);
+            break;
         case 'delplaylist':
         case 'dp':
             if (parts[1]) {
@@ -179,53 +207,44 @@ export function executeCommand(cmd) {
                 updateStatus('Usage: :rename <old name> > <new name>');
             }
             break;
+        case 'book':
+            loadBook(rawArgs);
+            break;
         case 'sleep':
-            if (parts[1]) {
-                const arg = parts[1];
-                // Check for +N or -N syntax to add/subtract time
-                if (arg.startsWith('+') || arg.startsWith('-')) {
-                    const delta = parseInt(arg);
-                    if (!isNaN(delta)) {
-                        adjustSleepTimer(delta);
-                    } else {
-                        updateStatus('Usage: :sleep +<minutes> or :sleep -<minutes>');
-                    }
-                } else {
-                    const minutes = parseInt(arg);
-                    if (!isNaN(minutes) && minutes >= 0) {
-                        setSleepTimer(minutes);
-                    } else {
-                        updateStatus('Usage: :sleep <minutes> (0 to cancel)');
-                    }
-                }
-            } else {
-                // Show current timer status
-                if (state.sleepTimerEnd) {
-                    const remaining = Math.ceil((state.sleepTimerEnd - Date.now()) / 60000);
-                    updateStatus(`Sleep timer: ${remaining} minute${remaining !== 1 ? 's' : ''} remaining`);
-                } else {
-                    updateStatus('No sleep timer set. Usage: :sleep <minutes>');
-                }
-            }
+            handleSleepCommand(parts.slice(1));
             break;
-        case 'mark':
-            if (parts[1] && parts[1].length === 1 && /[a-z]/i.test(parts[1])) {
-                setBookmark(parts[1].toLowerCase());
+        case 'mark': {
+            const [key, ...label] = rawArgs.split(/\s+/);
+            if (/^[a-z]$/i.test(key)) {
+                setBookmark(key, label.join(' '));
             } else {
-                updateStatus('Usage: :mark <a-z>');
+                updateStatus('Usage: :mark <a-z|A-Z> [label]');
             }
             break;
+        }
         case 'marks':
             showBookmarks();
             break;
         case 'delmark':
         case 'dm':
-            if (parts[1] && parts[1].length === 1 && /[a-z]/i.test(parts[1])) {
-                deleteBookmark(parts[1].toLowerCase());
+            if (/^[a-z]$/i.test(rawArgs)) {
+                deleteBookmark(rawArgs);
             } else {
-                updateStatus('Usage: :delmark <a-z>');
+                updateStatus('Usage: :delmark <a-z|A-Z>');
             }
             break;
+        case 'alarm':
+            handleAlarmCommand(rawArgs);
+            break;
+        case 'alarms':
+            showAlarms();
+            break;
+        case 'delalarm':
+            removeAlarm(rawArgs);
+            break;
+        case 'snooze':
+            snoozeAlarm(rawArgs);
+            break;
         case 'jump':
         case 'j':
             if (parts[1]) {
@@ -322,9 +341,75 @@ export function executeCommand(cmd) {
             if (parts[1]) {
                 sortPlaylist(parts[1]);
             } else {
-                updateStatus('Usage: :sort name | duration | path (append ! to reverse)');
+                updateStatus('Usage: :sort name | duration | path | bpm | key (append ! to reverse) | articles | noarticles');
+            }
+            break;
+        case 'tag':
+        case 'tagall':
+            writeTags(rawArgs, command === 'tagall');
+            break;
+        case 'tagfill':
+        case 'tagfillall':
+            if (rawArgs) {
+                fillTagsFromFilename(rawArgs, command === 'tagfillall');
+            } else {
+                updateStatus('Usage: :tagfill <pattern> (e.g. %track% - %title%)');
             }
             break;
+        case 'tagundo':
+            undoTagBatch();
+            break;
+        case 'infer':
+            handleInferCommand(rawArgs);
+            break;
+        case 'writeinferred':
+            writeInferredTags();
+            break;
+        case 'organize':
+            if (rawArgs) {
+                organizeFiles(rawArgs);
+            } else {
+                updateStatus('Usage: :organize <template> (e.g. {albumartist}/{year} - {album}/{track:02} {title}.{ext})');
+            }
+            break;
+        case 'duplicates':
+        case 'dupes':
+            findDuplicates();
+            break;
+        case 'recording':
+        case 'rec':
+            findRecording();
+            break;
+        case 'fpexport':
+            exportFingerprints();
+            break;
+        case 'analyze':
+        case 'analyze!':
+            analyzeTracks(rawArgs, command === 'analyze!');
+            break;
+        case 'bpm':
+            showTempoRange(rawArgs);
+            break;
+        case 'spectrum':
+        case 'spec':
+            toggleSpectrum(rawArgs);
+            break;
+        case 'chapters':
+        case 'ch':
+            toggleChapters();
+            break;
+        case 'nextchapter':
+        case 'nch':
+            nextChapter();
+            break;
+        case 'prevchapter':
+        case 'pch':
+            prevChapter();
+            break;
+        case 'lyrics':
+        case 'ly':
+            handleLyricsCommand(rawArgs);
+            break;
         case 'set':
             if (parts[1]) {
                 handleSetCommand(parts.slice(1).join(' '));
@@ -369,7 +454,23 @@ async function clearDefaultFolder() {
     }
 }
 
-function sortPlaylist(field) {
+async function sortPlaylist(field) {
+    if (field === 'articles' || field === 'noarticles') {
+        const ignore = field === 'articles';
+        try {
+            await invoke('set_sort_ignore_articles', { ignore });
+        } catch (err) {
+            updateStatus(`Error: ${err}`);
+            return;
+        }
+        // Sort again by the last field so the list reflects the new setting
+        if (state.sortField && state.playlist.length > 0) {
+            await sortPlaylist(state.sortField);
+        }
+        updateStatus(ignore ? 'Sorting ignores leading articles (The, A, An)' : 'Sorting includes leading articles');
+        return;
+    }
+    
     if (state.playlist.length === 0) {
         updateStatus('No tracks to sort');
         return;
@@ -382,29 +483,31 @@ function sortPlaylist(field) {
     const playingPath = state.playingIndex >= 0 ? state.playlist[state.playingIndex]?.path : null;
     const selectedPath = state.playlist[state.selectedIndex]?.path;
     
-    let compareFn;
     switch (key) {
         case 'name':
-            compareFn = (a, b) => a.name.localeCompare(b.name, undefined, { sensitivity: 'base' });
+        case 'path':
+        case 'bpm':
+        case 'key':
+            // Natural, accent-insensitive order shared with the backend listings
+            try {
+                state.playlist = await invoke('sort_tracks', { tracks: state.playlist, field: key });
+            } catch (err) {
+                updateStatus(`Error: ${err}`);
+                return;
+            }
             break;
         case 'duration':
-            compareFn = (a, b) => (a.duration || 0) - (b.duration || 0);
-            break;
-        case 'path':
-            compareFn = (a, b) => a.path.localeCompare(b.path, undefined, { sensitivity: 'base' });
+            state.playlist.sort((a, b) => (a.duration || 0) - (b.duration || 0));
             break;
         default:
-            updateStatus(`Unknown sort field: ${key}. Use name, duration, or path`);
+            updateStatus(`Unknown sort field: ${key}. Use name, duration, path, bpm, or key`);
             return;
     }
     
     if (reverse) {
-        const original = compareFn;
-        compareFn = (a, b) => original(b, a);
+        state.playlist.reverse();
     }
     
-    state.playlist.sort(compareFn);
-    
     // Restore playingIndex and selectedIndex by path
     if (playingPath) {
         state.playingIndex = state.playlist.findIndex(t => t.path === playingPath);
@@ -423,6 +526,7 @@ function sortPlaylist(field) {
         updateStatus(`Sorted by ${key}${reverse ? ' (reversed)' : ''}`);
     }
     
+    state.sortField = field;
     renderPlaylist();
 }
 
@@ -0,0 +1,154 @@
+import { invoke, open } from './tauri.js';
+import { state } from './state.js';
+import { updateStatus, showLoading, hideLoading } from './ui.js';
+import { renderPlaylist } from './views/playlist.js';
+import { showReport } from './report.js';
+
+const REASONS = {
+    identical: 'identical files',
+    tags: 'same artist, title and length',
+    audio: 'same audio in another format',
+};
+
+function describeCopy(copy) {
+    const parts = [copy.format];
+    if (copy.lossless) {
+        parts.push(copy.bit_depth && copy.sample_rate ? `${copy.bit_depth}/${copy.sample_rate / 1000} kHz` : 'lossless');
+    } else if (copy.bitrate) {
+        parts.push(`${copy.bitrate} kbps`);
+    }
+    parts.push(`${(copy.size / (1024 * 1024)).toFixed(1)} MB`);
+    return parts.join(' · ');
+}
+
+// :duplicates — find likely duplicates in the library, Enter trashes all but the best copy of each
+export async function findDuplicates() {
+    try {
+        showLoading('Looking for duplicates...');
+        const groups = await invoke('find_duplicates', { paths: [] });
+        hideLoading();
+        
+        const extra = groups.reduce((n, g) => n + g.copies.length - 1, 0);
+        const items = [];
+        groups.forEach((group, i) => {
+            group.copies.forEach((copy, index) => {
+                const keep = index === group.keep;
+                items.push({
+                    label: `${i + 1}. ${copy.path.split(/[\\/]/).pop()}`,
+                    detail: `${describeCopy(copy)} · ${REASONS[group.reason] || group.reason} · ${copy.path}`,
+                    status: keep ? 'keep' : 'trash',
+                    statusText: keep ? 'keep' : 'trash',
+                });
+            });
+        });
+        
+        const choices = groups.map(group => ({
+            keep: group.copies[group.keep].path,
+            remove: group.copies.filter((_, index) => index !== group.keep).map(c => c.path),
+        }));
+        showReport({
+            title: 'Duplicates',
+            summary: `${groups.length} group${groups.length !== 1 ? 's' : ''}, ${extra} extra cop${extra !== 1 ? 'ies' : 'y'} — Enter moves them to the trash and keeps the best copy`,
+            items,
+            onConfirm: extra > 0 ? () => trashDuplicates(choices) : null,
+        });
+        updateStatus(`Duplicates: ${groups.length} group${groups.length !== 1 ? 's' : ''}`);
+    } catch (err) {
+        hideLoading();
+        updateStatus(`Error: ${err}`);
+    }
+}
+
+async function trashDuplicates(choices) {
+    try {
+        updateStatus('Moving duplicates to the trash...');
+        const result = await invoke('trash_duplicates', { choices });
+        
+        // Loaded entries of trashed copies now play the kept one, as saved playlists do
+        const trashed = new Set(result.trashed);
+        const kept = new Map();
+        for (const choice of choices) {
+            for (const path of choice.remove.filter(p => trashed.has(p))) kept.set(path, choice.keep);
+        }
+        for (const track of state.playlist) {
+            const newPath = kept.get(track.path);
+            if (newPath) {
+                track.path = newPath;
+                track.name = newPath.split(/[\\/]/).pop();
+            }
+        }
+        renderPlaylist();
+        
+        let message = `Trashed ${trashed.size} file${trashed.size !== 1 ? 's' : ''}, updated ${result.playlists_updated} playlist${result.playlists_updated !== 1 ? 's' : ''}`;
+        if (result.failed.length > 0) {
+            message += ` (${result.failed.length} failed: ${result.failed[0]})`;
+        }
+        updateStatus(message);
+    } catch (err) {
+        updateStatus(`Error: ${err}`);
+    }
+}
+
+function getSelectedTrack() {
+    return state.filteredPlaylist.length > 0
+        ? state.filteredPlaylist[state.selectedIndex]?.track
+        : state.playlist[state.selectedIndex];
+}
+
+// :recording — other library files with the same audio as the selected track, by fingerprint
+export async function findRecording() {
+    const track = getSelectedTrack();
+    if (!track) {
+        updateStatus('No track selected');
+        return;
+    }
+    
+    try {
+        showLoading('Fingerprinting library...');
+        const matches = await invoke('find_recording', { path: track.path });
+        hideLoading();
+        
+        showReport({
+            title: `Same recording as ${track.name}`,
+            summary: `${matches.length} match${matches.length !== 1 ? 'es' : ''} in the library`,
+            items: matches.map(m => ({
+                label: m.name,
+                detail: m.path,
+                status: 'match',
+                statusText: `${Math.round(m.similarity * 100)}%`,
+            })),
+        });
+        updateStatus(`Recording: ${matches.length} match${matches.length !== 1 ? 'es' : ''}`);
+    } catch (err) {
+        hideLoading();
+        updateStatus(`Error: ${err}`);
+    }
+}
+
+// :fpexport — write fingerprints of the listed tracks to fingerprints.txt for AcoustID lookups
+export async function exportFingerprints() {
+    const tracks = state.filteredPlaylist.length > 0
+        ? state.filteredPlaylist.map(f => f.track)
+        : state.playlist;
+    if (tracks.length === 0) {
+        updateStatus('No tracks to fingerprint');
+        return;
+    }
+    
+    const folder = await open({
+        directory: true,
+        multiple: false,
+        title: 'Select Folder for fingerprints.txt'
+    });
+    if (!folder) return;
+    
+    try {
+        showLoading('Fingerprinting tracks...');
+        const count = await invoke('export_fingerprints', { paths: tracks.map(t => t.path), folder });
+        hideLoading();
+        updateStatus(`Exported ${count} fingerprint${count !== 1 ? 's' : ''} to fingerprints.txt`);
+    } catch (err) {
+        hideLoading();
+        updateStatus(`Error: ${err}`);
+    }
+}
@@ -1,136 +1,185 @@
 import { state } from './state.js';
+import { invoke } from './tauri.js';
 import { formatDuration } from './utils.js';
 import { updateStatus, updateSleepTimerDisplay, updateLoopDisplay } from './ui.js';
 import { seekTo, playTrack, stop, stopLoopMonitor, startLoopMonitor } from './playback.js';
+import { renderPlaylist } from './views/playlist.js';
+import { showReport } from './report.js';
 
-// Sleep Timer
-export function setSleepTimer(minutes) {
-    clearSleepTimer();
-    
-    if (minutes <= 0) {
+// Sleep Timer — kept by the backend, which fades out and stops playback itself
+async function requestSleep(request) {
+    try {
+        state.sleep = await invoke('set_sleep_timer', { request });
+        updateSleepTimerDisplay(state.sleep);
+        return state.sleep;
+    } catch (err) {
+        updateStatus(`${err}`);
+        return undefined;
+    }
+}
+
+export async function setSleepTimer(minutes) {
+    if (await requestSleep({ mode: 'minutes', minutes }) === undefined) return;
+    updateStatus(minutes > 0 ? `Sleep timer set for ${minutes} minute${minutes > 1 ? 's' : ''}` : 'Sleep timer cleared');
+}
+
+export async function adjustSleepTimer(deltaMinutes) {
+    const sleep = await requestSleep({ mode: 'adjust', minutes: deltaMinutes });
+    if (sleep === undefined) return;
+    if (!sleep) {
         updateStatus('Sleep timer cleared');
         return;
     }
-    
-    state.sleepTimerEnd = Date.now() + (minutes * 60 * 1000);
-    
-    // Update display every second
-    state.sleepTimerInterval = setInterval(() => {
-        const remaining = state.sleepTimerEnd - Date.now();
-        
-        if (remaining <= 0) {
-            // Timer expired - stop playback
-            clearSleepTimer();
-            stop();
-            updateStatus('Sleep timer: Playback stopped');
-        } else {
-            updateSleepTimerDisplay(remaining);
-        }
-    }, 1000);
-    
-    updateSleepTimerDisplay(minutes * 60 * 1000);
-    updateStatus(`Sleep timer set for ${minutes} minute${minutes > 1 ? 's' : ''}`);
+    const mins = Math.ceil(sleep.remaining_secs / 60);
+    updateStatus(`Sleep timer: ${deltaMinutes > 0 ? '+' : ''}${deltaMinutes} min (${mins} min remaining)`);
 }
 
-export function adjustSleepTimer(deltaMinutes) {
-    if (!state.sleepTimerEnd) {
-        // No timer running, start a new one if positive
-        if (deltaMinutes > 0) {
-            setSleepTimer(deltaMinutes);
-        } else {
-            updateStatus('No sleep timer to adjust');
-        }
+function describeSleep(sleep) {
+    if (!sleep) return 'No sleep timer set. Usage: :sleep <minutes|track|tracks N|queue>';
+    const left = sleep.remaining_secs !== null ? ` (${formatDuration(sleep.remaining_secs)} left)` : '';
+    if (sleep.mode === 'time') return `Sleep timer: stops in ${formatDuration(sleep.remaining_secs)}`;
+    if (sleep.mode === 'queue') return `Sleep timer: stops at the end of the queue or album${left}`;
+    if (sleep.tracks_left === 1) return `Sleep timer: stops at the end of this track${left}`;
+    return `Sleep timer: stops after ${sleep.tracks_left} more tracks`;
+}
+
+// :sleep [minutes | +N | -N | track | tracks N | queue | off]
+export async function handleSleepCommand(args) {
+    const [arg, count] = args;
+    if (!arg) {
+        const status = await invoke('get_status');
+        updateStatus(describeSleep(status.sleep));
         return;
     }
     
-    // Add/subtract time from existing timer
-    const newEnd = state.sleepTimerEnd + (deltaMinutes * 60 * 1000);
-    const remaining = newEnd - Date.now();
-    
-    if (remaining <= 0) {
-        clearSleepTimer();
-        updateStatus('Sleep timer cleared');
+    const modes = {
+        off: { mode: 'off' },
+        track: { mode: 'track' },
+        queue: { mode: 'queue' },
+        album: { mode: 'queue' },
+    };
+    if (modes[arg]) {
+        const sleep = await requestSleep(modes[arg]);
+        if (sleep !== undefined) updateStatus(sleep ? describeSleep(sleep) : 'Sleep timer cleared');
+    } else if (arg === 'tracks') {
+        const n = parseInt(count);
+        if (isNaN(n) || n < 1) {
+            updateStatus('Usage: :sleep tracks <count>');
+            return;
+        }
+        const sleep = await requestSleep({ mode: 'tracks', count: n });
+        if (sleep) updateStatus(describeSleep(sleep));
+    } else if (arg.startsWith('+') || arg.startsWith('-')) {
+        // +N or -N adds or takes time from a running timer
+        const delta = parseInt(arg);
+        if (!isNaN(delta)) {
+            adjustSleepTimer(delta);
+        } else {
+            updateStatus('Usage: :sleep +<minutes> or :sleep -<minutes>');
+        }
     } else {
-        state.sleepTimerEnd = newEnd;
-        const mins = Math.ceil(remaining / 60000);
-        updateSleepTimerDisplay(remaining);
-        updateStatus(`Sleep timer: ${deltaMinutes > 0 ? '+' : ''}${deltaMinutes} min (${mins} min remaining)`);
+        const minutes = parseInt(arg);
+        if (!isNaN(minutes) && minutes >= 0) {
+            setSleepTimer(minutes);
+        } else {
+            updateStatus('Usage: :sleep <minutes|track|tracks N|queue|off> (0 to cancel)');
+        }
     }
 }
 
-export function clearSleepTimer() {
-    if (state.sleepTimerInterval) {
-        clearInterval(state.sleepTimerInterval);
-        state.sleepTimerInterval = null;
-    }
-    state.sleepTimerEnd = null;
-    updateSleepTimerDisplay(0);
+export async function setupSleepListener(listen) {
+    if (!listen) return;
+    
+    await listen('sleep-expired', async () => {
+        state.sleep = null;
+        updateSleepTimerDisplay(null);
+        await stop();
+        updateStatus('Sleep timer: Playback stopped');
+    });
 }
 
-// Bookmarks
-export function setBookmark(key) {
-    if (!state.isPlaying) {
-        updateStatus('No track playing to bookmark');
-        return;
+// Marks: lowercase a-z belong to a track, uppercase A-Z are global and remember their track
+// Lowercase marks are those of the playing track, or of the selected one when nothing plays
+function markTrackPath() {
+    const track = state.isPlaying ? state.playlist[state.playingIndex] : state.playlist[state.selectedIndex];
+    return track?.path ?? null;
+}
+
+export async function setBookmark(key, label = '') {
+    try {
+        const mark = await invoke('set_mark', { key, label: label || null });
+        const at = formatDuration(Math.floor(mark.position_ms / 1000));
+        updateStatus(`Mark '${key}' set at ${at}${mark.label ? ` (${mark.label})` : ''}`);
+    } catch (err) {
+        updateStatus(`Error: ${err}`);
     }
-    
-    const track = state.playlist[state.playingIndex];
-    state.bookmarks[key] = {
-        track: track.path,
-        trackIndex: state.playingIndex,
-        position: state.elapsed
-    };
-    
-    updateStatus(`Bookmark '${key}' set at ${formatDuration(state.elapsed)}`);
 }
 
-export function jumpToBookmark(key) {
-    const bookmark = state.bookmarks[key];
-    if (!bookmark) {
-        updateStatus(`No bookmark '${key}'`);
-        return;
+export async function jumpToBookmark(key) {
+    try {
+        const mark = await invoke('get_mark', { key, path: markTrackPath() });
+        await jumpToMark(mark);
+    } catch (err) {
+        updateStatus(`${err}`);
     }
-    
-    // Check if it's the same track
-    if (state.isPlaying && state.playlist[state.playingIndex]?.path === bookmark.track) {
-        // Just seek to position
-        seekTo(bookmark.position);
-        updateStatus(`Jumped to bookmark '${key}' at ${formatDuration(bookmark.position)}`);
+}
+
+async function jumpToMark(mark) {
+    const position = Math.floor(mark.position_ms / 1000);
+    if (state.isPlaying && state.playlist[state.playingIndex]?.path === mark.path) {
+        await seekTo(position);
     } else {
-        // Find the track in playlist and play it
-        const trackIndex = state.playlist.findIndex(t => t.path === bookmark.track);
-        if (trackIndex >= 0) {
-            playTrack(trackIndex, bookmark.position);
-            updateStatus(`Jumped to bookmark '${key}' at ${formatDuration(bookmark.position)}`);
-        } else {
-            updateStatus(`Bookmark '${key}' track not in playlist`);
+        let index = state.playlist.findIndex(t => t.path === mark.path);
+        if (index < 0) {
+            if (mark.missing) {
+                updateStatus(`Mark '${mark.key}': ${mark.name} is unavailable`);
+                return;
+            }
+            // The marked track isn't in the list: bring it in at the end
+            state.playlist.push({ path: mark.path, name: mark.name, index: state.playlist.length, duration: null, missing: false });
+            await invoke('set_playlist', { paths: state.playlist.map(t => t.path) });
+            index = state.playlist.length - 1;
+            renderPlaylist();
         }
+        await playTrack(index, position, false);
     }
+    updateStatus(`Jumped to mark '${mark.key}' at ${formatDuration(position)}`);
 }
 
-export function deleteBookmark(key) {
-    if (state.bookmarks[key]) {
-        delete state.bookmarks[key];
-        updateStatus(`Bookmark '${key}' deleted`);
-    } else {
-        updateStatus(`No bookmark '${key}'`);
+export async function deleteBookmark(key) {
+    try {
+        await invoke('delete_mark', { key, path: markTrackPath() });
+        updateStatus(`Mark '${key}' deleted`);
+    } catch (err) {
+        updateStatus(`${err}`);
     }
 }
 
-export function showBookmarks() {
-    const keys = Object.keys(state.bookmarks).sort();
-    if (keys.length === 0) {
-        updateStatus('No bookmarks set');
-        return;
+// :marks — every mark in the library; Enter jumps to the selected one
+export async function showBookmarks() {
+    try {
+        const marks = await invoke('list_marks');
+        if (marks.length === 0) {
+            updateStatus('No marks set');
+            return;
+        }
+        
+        showReport({
+            title: 'Marks',
+            summary: `${marks.length} mark${marks.length !== 1 ? 's' : ''} — uppercase marks are global`,
+            items: marks.map(mark => ({
+                label: `'${mark.key}  ${mark.name} @ ${formatDuration(Math.floor(mark.position_ms / 1000))}`,
+                detail: mark.label ? `${mark.label} — ${mark.path}` : mark.path,
+                status: mark.missing ? 'missing' : null,
+                statusText: mark.missing ? 'missing' : null,
+                mark,
+            })),
+            onConfirm: item => jumpToMark(item.mark),
+            confirmLabel: 'jump',
+        });
+    } catch (err) {
+        updateStatus(`Error: ${err}`);
     }
-    
-    const list = keys.map(k => {
-        const b = state.bookmarks[k];
-        const trackName = state.playlist.find(t => t.path === b.track)?.name || 'Unknown';
-        return `'${k}': ${trackName} @ ${formatDuration(b.position)}`;
-    }).join(', ');
-    
-    updateStatus(`Bookmarks: ${list}`);
 }
 
 // A-B Loop
@@ -59,6 +59,10 @@ const defaultKeybindings = {
     'b': 'setLoopA',
     'B': 'setLoopB',
     'C': 'clearLoop',
+    // Chapters
+    '}': 'nextChapter',
+    '{': 'prevChapter',
+    'z': 'pendingZ',
 };
 
 // User keybindings (loaded from config)
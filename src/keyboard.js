@@ -7,6 +7,9 @@ import { enterCommandMode, goBack, revealInExplorer } from './commands.js';
 import { enterFilterMode, clearFilter, jumpToNextMatch, jumpToPrevMatch } from './filter.js';
 import { enterVisualMode, handleVisualModeKeyDown, deleteSelectedTracks } from './visual.js';
 import { handleQueueViewKeyDown, addToQueue, toggleQueueView } from './queue.js';
+import { handleReportKeyDown } from './report.js';
+import { handleLyricsKeyDown } from './lyrics.js';
+import { nextChapter, prevChapter, toggleChapters } from './chapters.js';
 import { handlePlaylistManagerKeyDown, handleAddToPlaylistKeyDown, showPlaylistManager, showAddToPlaylistPicker, getSelectedTrackPaths } from './playlists.js';
 import { handleArtistViewKeyDown } from './views/artist.js';
 import { toggleViewMode, openFolder, reloadContent, navigateFolderUp } from './views/folder.js';
@@ -97,6 +100,11 @@ export function executeAction(action, e) {
         case 'pendingD': state.pendingKey = 'd'; return true;
         case 'pendingM': state.pendingKey = 'm'; return true;
         case 'pendingQuote': state.pendingKey = "'"; return true;
+        case 'pendingZ': state.pendingKey = 'z'; return true;
+        
+        // Chapters
+        case 'nextChapter': clearCount(); nextChapter(); return true;
+        case 'prevChapter': clearCount(); prevChapter(); return true;
         
         // A-B Loop
         case 'setLoopA': clearCount(); setLoopA(); return true;
@@ -114,6 +122,18 @@ export function handleKeyDown(e) {
         return;
     }
     
+    // Report modal sits above everything else
+    if (state.reportOpen) {
+        handleReportKeyDown(e);
+        return;
+    }
+    
+    // Lyrics view has its own key handling
+    if (state.lyricsOpen) {
+        handleLyricsKeyDown(e);
+        return;
+    }
+    
     // Queue view has its own key handling
     if (state.queueViewOpen) {
         handleQueueViewKeyDown(e);
@@ -153,6 +173,11 @@ export function handleKeyDown(e) {
         }
     }
     
+    // Modifiers arrive on their own first; keep a pending key for the key they modify ('A, mA)
+    if (['Shift', 'Control', 'Alt', 'Meta'].includes(e.key)) {
+        return;
+    }
+    
     // Handle multi-key commands
     if (state.pendingKey === 'g') {
         if (e.key === 'g') {
@@ -185,8 +210,19 @@ export function handleKeyDown(e) {
     
     if (state.pendingKey === "'") {
         // 'a - jump to bookmark a
-        if (/[a-z]/i.test(e.key)) {
-            jumpToBookmark(e.key.toLowerCase());
+        if (/^[a-z]$/i.test(e.key)) {
+            jumpToBookmark(e.key);
+        }
+        state.pendingKey = null;
+        state.countPrefix = '';
+        return;
+    }
+    
+    if (state.pendingKey === 'z') {
+        // za / zo / zc - toggle, open or close the selected track's chapters
+        const modes = { a: 'toggle', o: 'open', c: 'close' };
+        if (modes[e.key]) {
+            toggleChapters(modes[e.key]);
         }
         state.pendingKey = null;
         state.countPrefix = '';
@@ -195,8 +231,8 @@ export function handleKeyDown(e) {
     
     if (state.pendingKey === 'm') {
         // ma - set bookmark a (alternative to :mark a)
-        if (/[a-z]/i.test(e.key)) {
-            setBookmark(e.key.toLowerCase());
+        if (/^[a-z]$/i.test(e.key)) {
+            setBookmark(e.key);
         }
         state.pendingKey = null;
         state.countPrefix = '';
@@ -74,7 +74,7 @@ export async function scanLibrary() {
         });
         
         // Sort by name and update indices
-        allTracks.sort((a, b) => a.name.toLowerCase().localeCompare(b.name.toLowerCase()));
+        allTracks = await invoke('sort_tracks', { tracks: allTracks, field: 'name' });
         allTracks.forEach((track, i) => track.index = i);
         
         // Update backend playlist with the paths
@@ -103,3 +103,35 @@ export async function showLibraryFolders() {
         updateStatus(`Library folders:\n${list}`);
     }
 }
+
+// :remap <old> <new> — the music moved: point playlists, cache and library folders at the new place
+export async function remapLibrary(args) {
+    // "old > new" allows spaces in either prefix
+    const [oldPrefix, newPrefix] = args.includes('>')
+        ? args.split('>').map(s => s.trim())
+        : args.split(/\s+/);
+    if (!oldPrefix || !newPrefix) {
+        updateStatus('Usage: :remap <old prefix> <new prefix> (or <old> > <new> for paths with spaces)');
+        return;
+    }
+    
+    try {
+        const report = await invoke('remap_library', { oldPrefix, newPrefix });
+        // Same whole-component match as the backend, for the loaded tracks
+        const [from, to] = [oldPrefix, newPrefix].map(p => p.replace(/[\\/]+$/, ''));
+        const remap = (path) => {
+            if (!path?.startsWith(from)) return path;
+            const rest = path.slice(from.length);
+            return rest === '' || /^[\\/#]/.test(rest) ? to + rest : path;
+        };
+        for (const track of state.playlist) {
+            track.path = remap(track.path);
+        }
+        state.rootFolder = remap(state.rootFolder);
+        state.currentFolder = remap(state.currentFolder);
+        renderPlaylist();
+        updateStatus(`Remapped ${report.playlists} playlist${report.playlists !== 1 ? 's' : ''}, ${report.tracks} cached track${report.tracks !== 1 ? 's' : ''}, ${report.folders} folder${report.folders !== 1 ? 's' : ''}`);
+    } catch (err) {
+        updateStatus(`Error: ${err}`);
+    }
+}
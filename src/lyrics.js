@@ -0,0 +1,212 @@
+import { invoke } from './tauri.js';
+import { state } from './state.js';
+import { updateStatus } from './ui.js';
+import { escapeHtml } from './utils.js';
+
+const OFFSET_STEP_MS = 100;
+
+// Lyrics follow the playing track, or the selected one when nothing plays
+function getLyricsTrackPath() {
+    if (state.isPlaying && state.playingIndex >= 0) {
+        return state.playlist[state.playingIndex]?.path || null;
+    }
+    const track = state.filteredPlaylist.length > 0
+        ? state.filteredPlaylist[state.selectedIndex]?.track
+        : state.playlist[state.selectedIndex];
+    return track?.path || null;
+}
+
+export async function setupLyricsListener(listen) {
+    if (!listen) return;
+    
+    await listen('lyrics-line', (event) => {
+        const { path, index } = event.payload;
+        if (!state.lyricsOpen) return;
+        
+        // The playing track changed under an open view: follow it
+        if (path && path !== state.lyricsPath && !state.lyricsEditing) {
+            loadLyrics(path);
+            return;
+        }
+        if (path === state.lyricsPath && index !== state.lyricsLine) {
+            state.lyricsLine = index;
+            renderLyrics();
+        }
+    });
+}
+
+async function loadLyrics(path) {
+    state.lyricsPath = path;
+    state.lyricsLine = null;
+    try {
+        state.lyrics = await invoke('get_lyrics', { path });
+    } catch (err) {
+        state.lyrics = null;
+        updateStatus(`Error: ${err}`);
+    }
+    renderLyrics();
+}
+
+export async function openLyrics() {
+    const path = getLyricsTrackPath();
+    if (!path) {
+        updateStatus('No track selected');
+        return;
+    }
+    state.lyricsOpen = true;
+    state.lyricsEditing = false;
+    document.getElementById('lyricsModal').classList.add('visible');
+    await loadLyrics(path);
+}
+
+export function closeLyrics() {
+    state.lyricsOpen = false;
+    state.lyricsEditing = false;
+    document.getElementById('lyricsModal').classList.remove('visible');
+}
+
+export function renderLyrics() {
+    const list = document.getElementById('lyricsList');
+    const editor = document.getElementById('lyricsEditor');
+    const info = document.getElementById('lyricsInfo');
+    const footer = document.getElementById('lyricsFooter');
+    const lyrics = state.lyrics;
+    
+    list.style.display = state.lyricsEditing ? 'none' : '';
+    editor.style.display = state.lyricsEditing ? '' : 'none';
+    footer.innerHTML = state.lyricsEditing
+        ? '<kbd>Ctrl+S</kbd> save to .lrc · <kbd>Esc</kbd> cancel'
+        : '<kbd>j</kbd>/<kbd>k</kbd> scroll · <kbd>+</kbd>/<kbd>-</kbd> offset · <kbd>e</kbd> edit · <kbd>q</kbd> close';
+    
+    if (!lyrics) {
+        info.textContent = 'No lyrics found';
+        list.innerHTML = `
+            <div class="empty-playlist">
+                <p>No lyrics for this track</p>
+                <p class="hint">Press <kbd>e</kbd> to write an .lrc file</p>
+            </div>
+        `;
+        return;
+    }
+    
+    const source = lyrics.source === 'lrc' ? '.lrc file' : 'embedded tags';
+    info.textContent = lyrics.synced
+        ? `Synced, from ${source} · offset ${lyrics.offset_ms >= 0 ? '+' : ''}${lyrics.offset_ms} ms`
+        : `Unsynced, from ${source}`;
+    
+    if (state.lyricsEditing) return;
+    
+    list.innerHTML = lyrics.lines.map((line, index) => {
+        const classes = ['lyrics-line'];
+        if (index === state.lyricsLine) classes.push('current');
+        return `<div class="${classes.join(' ')}">${escapeHtml(line.text) || '&nbsp;'}</div>`;
+    }).join('');
+    
+    const current = list.querySelector('.lyrics-line.current');
+    if (current) {
+        current.scrollIntoView({ block: 'center', behavior: 'smooth' });
+    }
+}
+
+function startEditing() {
+    const editor = document.getElementById('lyricsEditor');
+    editor.value = state.lyrics?.raw || '';
+    state.lyricsEditing = true;
+    renderLyrics();
+    editor.focus();
+}
+
+function stopEditing() {
+    state.lyricsEditing = false;
+    document.getElementById('lyricsEditor').blur();
+    renderLyrics();
+}
+
+async function saveEditedLyrics() {
+    const content = document.getElementById('lyricsEditor').value;
+    try {
+        state.lyrics = await invoke('save_lyrics', { path: state.lyricsPath, content });
+        updateStatus(`Saved ${state.lyrics.lrc_path}`);
+        stopEditing();
+    } catch (err) {
+        updateStatus(`Error: ${err}`);
+    }
+}
+
+// Adjust (or with absolute = true, set) the offset and write it to the .lrc file
+export async function setLyricsOffset(value, absolute = false) {
+    const path = state.lyricsOpen ? state.lyricsPath : getLyricsTrackPath();
+    if (!path) {
+        updateStatus('No track selected');
+        return;
+    }
+    try {
+        const lyrics = path === state.lyricsPath && state.lyrics
+            ? state.lyrics
+            : await invoke('get_lyrics', { path });
+        const offsetMs = absolute ? value : (lyrics?.offset_ms || 0) + value;
+        state.lyrics = await invoke('set_lyrics_offset', { path, offsetMs });
+        state.lyricsPath = path;
+        updateStatus(`Lyrics offset ${offsetMs >= 0 ? '+' : ''}${offsetMs} ms`);
+        if (state.lyricsOpen) renderLyrics();
+    } catch (err) {
+        updateStatus(`Error: ${err}`);
+    }
+}
+
+// :lyrics [offset <ms>]
+export async function handleLyricsCommand(args) {
+    const match = args.match(/^offset\s+([+-]?\d+)$/);
+    if (match) {
+        const value = parseInt(match[1]);
+        // "+200" / "-200" adjust, a bare number sets
+        await setLyricsOffset(value, !/^[+-]/.test(match[1]));
+    } else if (args) {
+        updateStatus('Usage: :lyrics [offset <ms>]');
+    } else {
+        await openLyrics();
+    }
+}
+
+export function handleLyricsKeyDown(e) {
+    if (state.lyricsEditing) {
+        if (e.key === 'Escape') {
+            e.preventDefault();
+            stopEditing();
+        } else if (e.key === 's' && (e.ctrlKey || e.metaKey)) {
+            e.preventDefault();
+            saveEditedLyrics();
+        }
+        return;
+    }
+    
+    const list = document.getElementById('lyricsList');
+    switch (e.key) {
+        case 'j':
+            e.preventDefault();
+            list.scrollBy({ top: 40 });
+            break;
+        case 'k':
+            e.preventDefault();
+            list.scrollBy({ top: -40 });
+            break;
+        case '+':
+        case '=':
+            e.preventDefault();
+            setLyricsOffset(OFFSET_STEP_MS);
+            break;
+        case '-':
+            e.preventDefault();
+            setLyricsOffset(-OFFSET_STEP_MS);
+            break;
+        case 'e':
+            e.preventDefault();
+            startEditing();
+            break;
+        case 'Escape':
+        case 'q':
+            e.preventDefault();
+            closeLyrics();
+            break;
+    }
+}
@@ -0,0 +1,95 @@
+import { invoke, open } from './tauri.js';
+import { state } from './state.js';
+import { updateStatus } from './ui.js';
+import { renderPlaylist } from './views/playlist.js';
+import { showReport } from './report.js';
+
+const STATUS_LABELS = {
+    move: 'move',
+    moved: 'moved',
+    unchanged: 'unchanged',
+    exists: 'target exists',
+    duplicate: 'duplicate target',
+    missing: 'missing',
+    failed: 'failed',
+};
+
+// Files to organize: every listed track, narrowed by the active filter
+function getOrganizeTargets() {
+    const tracks = state.filteredPlaylist.length > 0
+        ? state.filteredPlaylist.map(f => f.track)
+        : state.playlist;
+    return tracks.map(t => t.path);
+}
+
+function relativeTo(path, root) {
+    return root && path.startsWith(root) ? path.slice(root.length).replace(/^[\\/]/, '') : path;
+}
+
+// :organize <template> — preview moving listed files into a tag-based layout, Enter applies
+export async function organizeFiles(template) {
+    const paths = getOrganizeTargets();
+    if (paths.length === 0) {
+        updateStatus('No tracks to organize');
+        return;
+    }
+    
+    // Organize in place when a real folder is open, otherwise ask where to
+    let destination = state.rootFolder;
+    if (!destination || destination === 'Library' || destination.startsWith('Playlist: ') || destination.startsWith('Book: ') || destination.startsWith('Alarm: ')) {
+        destination = await open({
+            directory: true,
+            multiple: false,
+            title: 'Select Destination Folder'
+        });
+        if (!destination) return;
+    }
+    
+    try {
+        updateStatus('Planning moves...');
+        const report = await invoke('organize_files', { paths, template, destination, dryRun: true });
+        const toMove = report.entries.filter(e => e.status === 'move').length;
+        
+        showReport({
+            title: 'Organize Preview',
+            summary: `${toMove} to move, ${report.conflicts} conflict${report.conflicts !== 1 ? 's' : ''} — conflicting files are left in place`,
+            items: report.entries.map(entry => ({
+                label: relativeTo(entry.to || entry.from, destination),
+                detail: entry.from,
+                status: entry.status,
+                statusText: STATUS_LABELS[entry.status] || entry.status,
+            })),
+            onConfirm: toMove > 0 ? () => applyOrganize(paths, template, destination) : null,
+        });
+        updateStatus(`Organize: ${toMove} file${toMove !== 1 ? 's' : ''} to move`);
+    } catch (err) {
+        updateStatus(`Error: ${err}`);
+    }
+}
+
+async function applyOrganize(paths, template, destination) {
+    try {
+        updateStatus('Moving files...');
+        const report = await invoke('organize_files', { paths, template, destination, dryRun: false });
+        
+        // Point loaded tracks at their new locations
+        const moved = new Map(report.entries.filter(e => e.status === 'moved').map(e => [e.from, e.to]));
+        for (const track of state.playlist) {
+            const newPath = moved.get(track.path);
+            if (newPath) {
+                track.path = newPath;
+                track.name = newPath.split(/[\\/]/).pop();
+            }
+        }
+        renderPlaylist();
+        
+        const failed = report.entries.filter(e => e.status === 'failed');
+        let message = `Moved ${report.moved} file${report.moved !== 1 ? 's' : ''}, updated ${report.playlists_updated} playlist${report.playlists_updated !== 1 ? 's' : ''}`;
+        if (failed.length > 0) {
+            message += ` (${failed.length} failed: ${failed[0].error})`;
+        }
+        updateStatus(message);
+    } catch (err) {
+        updateStatus(`Error: ${err}`);
+    }
+}
@@ -1,10 +1,12 @@
 import { invoke } from './tauri.js';
 import { state, elements } from './state.js';
-import { updateNowPlaying, updateStatus, updatePlayButton, updateVolumeDisplay, updateSpeedDisplay, updateProgressDisplay, resetProgressDisplay, updateModeIndicators, updateLoopDisplay } from './ui.js';
+import { updateNowPlaying, updateTrackArt, updateStatus, updatePlayButton, updateVolumeDisplay, updateSpeedDisplay, updateProgressDisplay, resetProgressDisplay, updateModeIndicators, updateLoopDisplay, updateSleepTimerDisplay } from './ui.js';
 import { scrollToSelected } from './navigation.js';
 import { renderPlaylist } from './views/playlist.js';
 import { handleFolderItemAction, renderFolderView } from './views/folder.js';
 import { updateQueueDisplay } from './queue.js';
+import { loadWaveform } from './waveform.js';
+import { formatDuration } from './utils.js';
 
 // View-aware render: re-renders whichever view is currently active
 function renderCurrentView() {
@@ -28,15 +30,16 @@ export async function playSelected() {
     await playTrack(state.selectedIndex);
 }
 
+// null lets the backend pick: a long track's remembered position, or the start
 function getTrackStartPosition(index, seekPosition, allowCarryPosition) {
     if (typeof seekPosition === 'number') {
         return seekPosition;
     }
-    if (!allowCarryPosition || !state.settings.carryposition) {
+    if (state.isPlaying && state.playingIndex === index) {
         return 0;
     }
-    if (!state.isPlaying || state.playingIndex < 0 || state.playingIndex === index) {
-        return 0;
+    if (!allowCarryPosition || !state.settings.carryposition || !state.isPlaying || state.playingIndex < 0) {
+        return null;
     }
     const targetDuration = state.playlist[index]?.duration;
     if (typeof targetDuration === 'number') {
@@ -61,11 +64,16 @@ export async function playTrack(index, seekPosition = null, allowCarryPosition =
         state.isPlaying = true;
         state.isPaused = false;
         state.duration = result.duration;
-        state.elapsed = startPosition;
+        state.elapsed = result.start_secs;
         updateNowPlaying(result.name);
+        updateTrackArt(result.path);
+        loadWaveform(result.path);
         renderCurrentView();
         updatePlayButton();
         updateProgressDisplay();
+        if (startPosition === null && result.start_secs > 0) {
+            updateStatus(`Resumed at ${formatDuration(result.start_secs)}`);
+        }
     } catch (err) {
         console.error('Failed to play track:', err);
         updateStatus(`Error: ${err}`);
@@ -94,6 +102,8 @@ export async function stop() {
         state.playingIndex = -1;
         state.duration = null;
         updateNowPlaying('No track selected');
+        updateTrackArt(null);
+        loadWaveform(null);
         updateStatus('Stopped');
         renderCurrentView();
         updatePlayButton();
@@ -103,6 +113,16 @@ export async function stop() {
     }
 }
 
+// The nearest track from `index` in `direction` (1 or -1) that isn't missing, or -1 if none is
+function availableIndex(index, direction) {
+    const count = state.playlist.length;
+    for (let step = 1; step <= count; step++) {
+        const i = (((index + direction * step) % count) + count) % count;
+        if (!state.playlist[i].missing) return i;
+    }
+    return -1;
+}
+
 export async function nextTrack() {
     if (state.shuffleMode && state.isPlaying) {
         playNextShuffle();
@@ -112,7 +132,8 @@ export async function nextTrack() {
     
     try {
         const currentIndex = state.playingIndex >= 0 ? state.playingIndex : state.selectedIndex;
-        const nextIndex = (currentIndex + 1) % state.playlist.length;
+        const nextIndex = availableIndex(currentIndex, 1);
+        if (nextIndex < 0) return;
         state.selectedIndex = nextIndex;
         await playTrack(nextIndex);
         scrollToSelected();
@@ -130,7 +151,8 @@ export async function prevTrack() {
     
     try {
         const currentIndex = state.playingIndex >= 0 ? state.playingIndex : state.selectedIndex;
-        const prevIndex = currentIndex === 0 ? state.playlist.length - 1 : currentIndex - 1;
+        const prevIndex = availableIndex(currentIndex, -1);
+        if (prevIndex < 0) return;
         state.selectedIndex = prevIndex;
         await playTrack(prevIndex);
         scrollToSelected();
@@ -258,7 +280,7 @@ export function handleTrackEnd() {
     // Check queue first (unless repeat-one is active)
     if (state.repeatMode !== 'one' && state.queue.length > 0) {
         const nextIndex = state.queue.shift();
-        playTrack(nextIndex, 0, false);
+        playTrack(nextIndex, null, false);
         updateQueueDisplay();
         return;
     }
@@ -272,11 +294,11 @@ export function handleTrackEnd() {
     } else if (state.repeatMode === 'all') {
         // Play next, wrap around
         const nextIndex = (state.playingIndex + 1) % state.playlist.length;
-        playTrack(nextIndex, 0, false);
+        playTrack(nextIndex, null, false);
     } else {
         // No repeat - play next if not at end
         if (state.playingIndex < state.playlist.length - 1) {
-            playTrack(state.playingIndex + 1, 0, false);
+            playTrack(state.playingIndex + 1, null, false);
         } else {
             // End of playlist
             state.isPlaying = false;
@@ -297,15 +319,11 @@ export function playNextShuffle(allowCarryPosition = true) {
         return;
     }
     
-    // Pick a random track (avoid current if possible)
-    let nextIndex;
-    if (state.playlist.length === 1) {
-        nextIndex = 0;
-    } else {
-        do {
-            nextIndex = Math.floor(Math.random() * state.playlist.length);
-        } while (nextIndex === state.playingIndex);
-    }
+    // Pick a random available track (avoid current if possible)
+    const available = state.playlist.map((track, i) => i).filter(i => !state.playlist[i].missing);
+    if (available.length === 0) return;
+    const others = available.length > 1 ? available.filter(i => i !== state.playingIndex) : available;
+    const nextIndex = others[Math.floor(Math.random() * others.length)];
     
     state.shuffleHistory.push(nextIndex);
     state.shuffleIndex = state.shuffleHistory.length - 1;
@@ -346,6 +364,11 @@ export async function updateProgress() {
             updateSpeedDisplay();
         }
         
+        if (status.sleep || state.sleep) {
+            state.sleep = status.sleep;
+            updateSleepTimerDisplay(state.sleep);
+        }
+        
         // Check if track finished
         if (status.is_finished) {
             handleTrackEnd();
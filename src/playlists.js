@@ -1,4 +1,4 @@
-import { invoke } from './tauri.js';
+import { invoke, open } from './tauri.js';
 import { state } from './state.js';
 import { escapeHtml } from './utils.js';
 import { updateStatus } from './ui.js';
@@ -44,7 +44,8 @@ export async function loadSavedPlaylist(name) {
         state.viewMode = 'list';
         state.rootFolder = `Playlist: ${name}`;
         renderPlaylist();
-        updateStatus(`Loaded "${name}" (${tracks.length} tracks)`);
+        const missing = tracks.filter(t => t.missing).length;
+        updateStatus(`Loaded "${name}" (${tracks.length} tracks` + (missing > 0 ? `, ${missing} unavailable — :repair to locate them)` : ')'));
     } catch (err) {
         console.error('Failed to load playlist:', err);
         updateStatus(`Error: ${err}`);
@@ -404,3 +405,55 @@ export function handleAddToPlaylistKeyDown(e) {
             break;
     }
 }
+
+// :import — copy an M3U/M3U8, PLS or XSPF file into the saved playlists and load it
+export async function importPlaylistFile() {
+    const selected = await open({
+        multiple: false,
+        title: 'Import Playlist',
+        filters: [{ name: 'Playlists', extensions: ['m3u', 'm3u8', 'pls', 'xspf'] }]
+    });
+    if (!selected) return;
+    
+    try {
+        const imported = await invoke('import_playlist', { path: selected });
+        await loadSavedPlaylist(imported.name);
+        let message = `Imported "${imported.name}" (${imported.track_count} tracks)`;
+        if (imported.missing > 0) {
+            message += `, ${imported.missing} not found`;
+        }
+        updateStatus(message);
+        if (state.playlistManagerOpen) {
+            refreshPlaylistManager();
+        }
+    } catch (err) {
+        console.error('Failed to import playlist:', err);
+        updateStatus(`Error: ${err}`);
+    }
+}
+
+// :export <format> [name] — write a saved playlist, or the loaded one, for other players
+export async function exportPlaylistFile(args) {
+    const [format, ...rest] = args.split(/\s+/);
+    const loaded = state.rootFolder?.startsWith('Playlist: ') ? state.rootFolder.slice('Playlist: '.length) : null;
+    const name = rest.join(' ') || loaded;
+    if (!['m3u', 'm3u8', 'pls', 'xspf'].includes(format?.toLowerCase()) || !name) {
+        updateStatus('Usage: :export m3u | m3u8 | pls | xspf [playlist name]');
+        return;
+    }
+    
+    const folder = await open({
+        directory: true,
+        multiple: false,
+        title: `Select Folder for ${name}.${format.toLowerCase()}`
+    });
+    if (!folder) return;
+    
+    try {
+        const written = await invoke('export_playlist', { name, format, path: folder });
+        updateStatus(`Exported to ${written}`);
+    } catch (err) {
+        console.error('Failed to export playlist:', err);
+        updateStatus(`Error: ${err}`);
+    }
+}
@@ -0,0 +1,74 @@
+import { invoke } from './tauri.js';
+import { state } from './state.js';
+import { updateStatus, showLoading, hideLoading } from './ui.js';
+import { renderPlaylist } from './views/playlist.js';
+import { showReport } from './report.js';
+
+const METHODS = {
+    filename: 'same file name',
+    tags: 'same artist and title',
+    fingerprint: 'same audio',
+};
+
+// :repair [all] — look for the missing tracks of the loaded list (or of every saved playlist)
+// in the library folders; Enter points playlists at the files that were found
+export async function repairPlaylists(args) {
+    const all = args.trim().toLowerCase() === 'all';
+    const paths = state.playlist.filter(t => t.missing).map(t => t.path);
+    if (!all && paths.length === 0) {
+        updateStatus('No missing tracks in this list (:repair all checks every saved playlist)');
+        return;
+    }
+    
+    try {
+        showLoading('Looking for missing tracks...');
+        const entries = await invoke('plan_repair', { paths: all ? [] : paths });
+        hideLoading();
+        
+        const fixes = {};
+        for (const entry of entries.filter(e => e.replacement)) {
+            fixes[entry.path] = entry.replacement;
+        }
+        const fixed = Object.keys(fixes).length;
+        const unfixable = entries.length - fixed;
+        
+        showReport({
+            title: all ? 'Repair saved playlists' : 'Repair playlist',
+            summary: `${entries.length} missing, ${fixed} found, ${unfixable} not found` + (fixed > 0 ? ' — Enter updates the playlists' : ''),
+            items: entries.map(entry => ({
+                label: entry.name,
+                detail: entry.replacement
+                    ? `${entry.path} → ${entry.replacement} (${METHODS[entry.method] || entry.method})`
+                    : entry.path,
+                status: entry.replacement ? 'fixed' : 'missing',
+                statusText: entry.replacement ? 'found' : 'not found',
+            })),
+            onConfirm: fixed > 0 ? () => applyRepair(fixes) : null,
+        });
+        updateStatus(`Repair: ${fixed} of ${entries.length} missing track${entries.length !== 1 ? 's' : ''} found`);
+    } catch (err) {
+        hideLoading();
+        updateStatus(`Error: ${err}`);
+    }
+}
+
+async function applyRepair(fixes) {
+    try {
+        const playlistsUpdated = await invoke('apply_repair', { fixes });
+        
+        for (const track of state.playlist) {
+            const newPath = fixes[track.path];
+            if (newPath) {
+                track.path = newPath;
+                track.name = newPath.split(/[\\/]/).pop();
+                track.missing = false;
+            }
+        }
+        renderPlaylist();
+        
+        const count = Object.keys(fixes).length;
+        updateStatus(`Relocated ${count} track${count !== 1 ? 's' : ''}, updated ${playlistsUpdated} playlist${playlistsUpdated !== 1 ? 's' : ''}`);
+    } catch (err) {
+        updateStatus(`Error: ${err}`);
+    }
+}
@@ -0,0 +1,114 @@
+import { state } from './state.js';
+import { escapeHtml } from './utils.js';
+
+// Generic scrollable report, used to preview bulk operations before they run.
+// Items are { label, detail, status, statusText }; onConfirm runs on Enter when given,
+// with the selected item, and confirmLabel names what it does in the footer.
+export function showReport({ title, summary = '', items, onConfirm = null, confirmLabel = 'apply' }) {
+    state.report = { title, summary, items, onConfirm, confirmLabel };
+    state.reportOpen = true;
+    state.reportIndex = 0;
+    renderReport();
+}
+
+export function renderReport() {
+    const modal = document.getElementById('reportModal');
+    const list = document.getElementById('reportList');
+    const { title, summary, items, onConfirm, confirmLabel } = state.report;
+    
+    document.getElementById('reportTitle').textContent = title;
+    document.getElementById('reportSummary').textContent = summary;
+    document.getElementById('reportFooter').innerHTML = onConfirm
+        ? `<kbd>j</kbd>/<kbd>k</kbd> navigate · <kbd>Enter</kbd> ${escapeHtml(confirmLabel)} · <kbd>q</kbd> cancel`
+        : '<kbd>j</kbd>/<kbd>k</kbd> navigate · <kbd>q</kbd> close';
+    
+    if (items.length === 0) {
+        list.innerHTML = `
+            <div class="empty-playlist">
+                <p>Nothing to report</p>
+            </div>
+        `;
+    } else {
+        list.innerHTML = items.map((item, index) => {
+            const classes = ['report-item'];
+            if (index === state.reportIndex) classes.push('selected');
+            if (item.status) classes.push(`status-${item.status}`);
+            
+            return `
+                <div class="${classes.join(' ')}" data-index="${index}">
+                    <div class="report-item-text">
+                        <span class="report-item-label">${escapeHtml(item.label)}</span>
+                        ${item.detail ? `<span class="report-item-detail">${escapeHtml(item.detail)}</span>` : ''}
+                    </div>
+                    ${item.status ? `<span class="report-item-status">${escapeHtml(item.statusText || item.status)}</span>` : ''}
+                </div>
+            `;
+        }).join('');
+        
+        list.querySelectorAll('.report-item').forEach(item => {
+            item.addEventListener('click', () => {
+                state.reportIndex = parseInt(item.dataset.index);
+                renderReport();
+            });
+        });
+    }
+    
+    modal.classList.add('visible');
+    
+    const selected = list.querySelector('.report-item.selected');
+    if (selected) {
+        selected.scrollIntoView({ block: 'nearest', behavior: 'smooth' });
+    }
+}
+
+export function closeReport() {
+    state.reportOpen = false;
+    state.report = null;
+    document.getElementById('reportModal').classList.remove('visible');
+}
+
+export function handleReportKeyDown(e) {
+    const count = state.report.items.length;
+    switch (e.key) {
+        case 'j':
+            e.preventDefault();
+            if (state.reportIndex < count - 1) {
+                state.reportIndex++;
+                renderReport();
+            }
+            break;
+        case 'k':
+            e.preventDefault();
+            if (state.reportIndex > 0) {
+                state.reportIndex--;
+                renderReport();
+            }
+            break;
+        case 'g':
+            if (state.pendingKey === 'g') {
+                state.reportIndex = 0;
+                renderReport();
+                state.pendingKey = null;
+            } else {
+                state.pendingKey = 'g';
+            }
+            break;
+        case 'G':
+            state.reportIndex = Math.max(0, count - 1);
+            renderReport();
+            break;
+        case 'Enter': {
+            e.preventDefault();
+            const { onConfirm, items } = state.report;
+            const item = items[state.reportIndex];
+            closeReport();
+            if (onConfirm) onConfirm(item);
+            break;
+        }
+        case 'Escape':
+        case 'q':
+            e.preventDefault();
+            closeReport();
+            break;
+    }
+}
@@ -0,0 +1,69 @@
+import { invoke } from './tauri.js';
+import { state } from './state.js';
+import { updateStatus, updateModeIndicators } from './ui.js';
+import { playTrack, togglePause } from './playback.js';
+import { renderPlaylist } from './views/playlist.js';
+import { updateViewModeIndicator } from './views/folder.js';
+import { updateQueueDisplay } from './queue.js';
+import { scrollToSelected } from './navigation.js';
+
+const SYNC_INTERVAL_MS = 2000;
+
+let lastSynced = null;
+
+// Pick up the playlist, position, queue and modes of the last run; false when there was none
+export async function restoreSession() {
+    try {
+        const session = await invoke('get_session');
+        if (!session) return false;
+        
+        state.playlist = session.tracks;
+        state.selectedIndex = session.index;
+        state.playingIndex = -1;
+        state.queue = session.queue.filter(i => i < session.tracks.length);
+        state.repeatMode = session.repeat || 'off';
+        state.shuffleMode = session.shuffle;
+        state.rootFolder = session.root_folder;
+        state.viewMode = 'list';
+        renderPlaylist();
+        updateViewModeIndicator();
+        updateModeIndicators();
+        updateQueueDisplay();
+        scrollToSelected();
+        
+        const track = session.tracks[session.index];
+        if (session.playing && track && !track.missing) {
+            await playTrack(session.index, Math.floor(session.elapsed_ms / 1000), false);
+            if (session.paused) {
+                await togglePause();
+            }
+        }
+        updateStatus(`Restored session (${session.tracks.length} tracks)`);
+        return true;
+    } catch (err) {
+        console.error('Failed to restore session:', err);
+        return false;
+    }
+}
+
+// Keep the backend's copy of the session current; it writes session.json itself
+export function startSessionSync() {
+    setInterval(syncSession, SYNC_INTERVAL_MS);
+}
+
+function syncSession() {
+    const session = {
+        playlist: state.playlist.map(t => t.path),
+        index: state.playingIndex >= 0 ? state.playingIndex : null,
+        modes: {
+            queue: state.queue,
+            repeat: state.repeatMode,
+            shuffle: state.shuffleMode,
+            root_folder: state.rootFolder,
+        },
+    };
+    const json = JSON.stringify(session);
+    if (json === lastSynced) return;
+    lastSynced = json;
+    invoke('set_session_state', session).catch(err => console.error('Failed to sync session:', err));
+}
@@ -35,8 +35,13 @@ export function handleSetCommand(arg) {
         'ss': 'speedstep',
         'vs': 'volumestep',
         'cp': 'carryposition',
+        'ex': 'exclude',
+        'sh': 'skiphidden',
     };
     
+    // Scan filters, resumeminutes and sleepfade use 0 to switch them off
+    const zeroAllowed = ['minsize', 'minduration', 'maxdepth', 'resumeminutes', 'sleepfade'];
+    
     // Handle "no" prefix to disable (e.g., "norelativenumber")
     if (trimmed.startsWith('no')) {
         const setting = trimmed.slice(2);
@@ -83,7 +88,7 @@ export function handleSetCommand(arg) {
                 state.settings[resolvedSetting] = value === 'true' || value === '1';
             } else if (currentType === 'number') {
                 const num = parseFloat(value);
-                if (!isNaN(num) && num > 0) {
+                if (!isNaN(num) && (num > 0 || (num === 0 && zeroAllowed.includes(resolvedSetting)))) {
                     state.settings[resolvedSetting] = num;
                 } else {
                     updateStatus(`Invalid value for ${resolvedSetting}: must be a positive number`);
@@ -0,0 +1,61 @@
+import { invoke } from './tauri.js';
+import { state, elements } from './state.js';
+import { updateStatus } from './ui.js';
+
+export async function setupSpectrumListener(listen) {
+    if (!listen) return;
+    
+    await listen('spectrum', (event) => {
+        if (state.spectrumOpen) {
+            drawSpectrum(event.payload);
+        }
+    });
+}
+
+// :spectrum [bands] — toggle the analyzer in the player bar; a band count shows it with that many bars
+export async function toggleSpectrum(args) {
+    const bands = parseInt(args, 10);
+    const open = Number.isNaN(bands) ? !state.spectrumOpen : true;
+    try {
+        await invoke('set_spectrum', { events: open, bands: Number.isNaN(bands) ? null : bands, fps: null });
+        state.spectrumOpen = open;
+        elements.spectrumCanvas.classList.toggle('visible', open);
+        if (!open) {
+            elements.spectrumCanvas.getContext('2d').clearRect(0, 0, elements.spectrumCanvas.width, elements.spectrumCanvas.height);
+        }
+        updateStatus(open ? 'Spectrum on' : 'Spectrum off');
+    } catch (err) {
+        updateStatus(`Error: ${err}`);
+    }
+}
+
+// Band bars on the left, an RMS meter with a peak line on the right
+function drawSpectrum(frame) {
+    const canvas = elements.spectrumCanvas;
+    const ratio = window.devicePixelRatio || 1;
+    const width = Math.round(canvas.clientWidth * ratio);
+    const height = Math.round(canvas.clientHeight * ratio);
+    if (canvas.width !== width || canvas.height !== height) {
+        canvas.width = width;
+        canvas.height = height;
+    }
+    
+    const styles = getComputedStyle(document.documentElement);
+    const ctx = canvas.getContext('2d');
+    ctx.clearRect(0, 0, width, height);
+    
+    const meterWidth = 4 * ratio;
+    const barsWidth = width - meterWidth * 2;
+    const step = barsWidth / frame.bands.length;
+    ctx.fillStyle = styles.getPropertyValue('--accent').trim();
+    frame.bands.forEach((value, i) => {
+        const barHeight = Math.max(value * height, ratio);
+        ctx.fillRect(i * step, height - barHeight, Math.max(step - ratio, 1), barHeight);
+    });
+    
+    const meterX = width - meterWidth;
+    ctx.fillStyle = styles.getPropertyValue('--success').trim();
+    ctx.fillRect(meterX, height - frame.rms * height, meterWidth, frame.rms * height);
+    ctx.fillStyle = styles.getPropertyValue('--warning').trim();
+    ctx.fillRect(meterX, height - frame.peak * height, meterWidth, ratio);
+}
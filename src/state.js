@@ -17,10 +17,12 @@ export const state = {
     elapsed: 0,
     duration: null,
     progressInterval: null,
+    waveform: null, // { min, max, rms } per bucket for the seek bar
     repeatMode: 'off', // 'off' | 'one' | 'all'
     shuffleMode: false,
     shuffleHistory: [],
     shuffleIndex: -1,
+    sortField: null, // Last :sort field, applied again when the article setting changes
     queue: [], // Array of playlist indices to play next
     queueViewOpen: false,
     queueSelectedIndex: 0,
@@ -48,11 +50,22 @@ export const state = {
     addToPlaylistOpen: false,
     addToPlaylistIndex: 0,
     addToPlaylistTracks: [], // Track paths to add
-    // Sleep timer
-    sleepTimerEnd: null, // Timestamp when playback should stop
-    sleepTimerInterval: null,
-    // Bookmarks: { 'a': { track: 'path', position: seconds }, ... }
-    bookmarks: {},
+    // Report modal (previews of bulk operations)
+    reportOpen: false,
+    reportIndex: 0,
+    report: null, // { title, summary, items, onConfirm }
+    // Lyrics view
+    lyricsOpen: false,
+    spectrumOpen: false,
+    lyricsEditing: false,
+    lyricsPath: null, // Track the lyrics belong to
+    lyrics: null, // { source, lrc_path, synced, offset_ms, lines, raw }
+    lyricsLine: null, // Index of the current synced line
+    // Chapters: { [trackPath]: [{ title, start_ms, end_ms }] } for tracks expanded in the playlist
+    expandedChapters: {},
+    currentChapter: { path: null, index: null },
+    // Sleep timer, as last reported by the backend: { mode, remaining_secs, tracks_left, fading }
+    sleep: null,
     // A-B Loop
     loopA: null, // Start position in seconds
     loopB: null, // End position in seconds
@@ -66,6 +79,16 @@ export const state = {
         speedstep: 0.25, // Speed change step (]/[)
         volumestep: 0.05, // Volume change step (+/-)
         carryposition: false, // Keep current playback position when manually changing tracks
+        // Folder scans (read by the backend from settings.json)
+        exclude: '', // Comma-separated gitignore patterns skipped in every folder, e.g. "@eaDir/,*stems*"
+        skiphidden: false, // Skip folders starting with a dot
+        minsize: 0, // Skip audio files smaller than this many KB
+        minduration: 0, // Skip audio files shorter than this many seconds
+        maxdepth: 0, // Folder levels to descend below the scanned folder (0 = no limit)
+        // Resume positions (read by the backend from settings.json)
+        resumeminutes: 20, // Tracks at least this long resume where they were left (0 = off)
+        resumefolders: '', // Comma-separated folders whose tracks always resume
+        sleepfade: 1, // Minutes the sleep timer fades the volume out over (0 = off, read by the backend)
     },
 };
 
@@ -75,6 +98,7 @@ export const elements = {
     trackName: document.getElementById('trackName'),
     trackStatus: document.getElementById('trackStatus'),
     trackArt: document.querySelector('.track-art'),
+    trackArtImage: document.getElementById('trackArtImage'),
     playBtn: document.getElementById('playBtn'),
     playIcon: document.getElementById('playIcon'),
     pauseIcon: document.getElementById('pauseIcon'),
@@ -93,6 +117,8 @@ export const elements = {
     helpModal: document.getElementById('helpModal'),
     progressBar: document.getElementById('progressBar'),
     progressFill: document.getElementById('progressFill'),
+    progressWaveform: document.getElementById('progressWaveform'),
+    spectrumCanvas: document.getElementById('spectrumCanvas'),
     timeElapsed: document.getElementById('timeElapsed'),
     timeTotal: document.getElementById('timeTotal'),
     queueModal: document.getElementById('queueModal'),
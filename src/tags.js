@@ -0,0 +1,139 @@
+import { invoke } from './tauri.js';
+import { state } from './state.js';
+import { updateStatus } from './ui.js';
+
+// Paths targeted by a tag command: the selected track, or every visible track with `all`
+function getTagTargets(all) {
+    if (all) {
+        const tracks = state.filteredPlaylist.length > 0
+            ? state.filteredPlaylist.map(f => f.track)
+            : state.playlist;
+        return tracks.map(t => t.path);
+    }
+    if (state.viewMode === 'artist' && state.artistViewMode === 'tracks') {
+        const tracks = state.filteredArtistItems.length > 0
+            ? state.filteredArtistItems.map(f => state.artistTracks[f.index])
+            : state.artistTracks;
+        const track = tracks[state.artistSelectedIndex];
+        return track ? [track.path] : [];
+    }
+    const track = state.filteredPlaylist.length > 0
+        ? state.filteredPlaylist[state.selectedIndex]?.track
+        : state.playlist[state.selectedIndex];
+    return track ? [track.path] : [];
+}
+
+function reportTagResult(result, verb) {
+    const count = result.updated.length;
+    let message = `${verb} tags on ${count} file${count !== 1 ? 's' : ''}`;
+    if (result.failed.length > 0) {
+        message += ` (${result.failed.length} failed: ${result.failed[0].error})`;
+    }
+    updateStatus(message);
+    if (state.viewMode === 'artist') {
+        import('./views/artist.js').then(m => m.openArtistView());
+    }
+}
+
+// :tag field=value [field=value ...] — an empty value clears the field
+export async function writeTags(args, all = false) {
+    const changes = [];
+    for (const arg of args.match(/\w+=("[^"]*"|\S*)/g) || []) {
+        const eq = arg.indexOf('=');
+        const field = arg.slice(0, eq).toLowerCase();
+        const value = arg.slice(eq + 1).replace(/^"(.*)"$/, '$1');
+        changes.push({ field, value: value === '' ? null : value });
+    }
+    if (changes.length === 0) {
+        updateStatus('Usage: :tag <field>=<value> (empty value clears the field)');
+        return;
+    }
+    await applyTagChanges(changes, all);
+}
+
+// :tagfill %track% - %title% — fill every field named in the pattern from the filename.
+// Placeholders starting with an underscore (e.g. %_%) match text without writing it.
+export async function fillTagsFromFilename(pattern, all = false) {
+    const fields = [...pattern.matchAll(/%(\w+)%/g)]
+        .map(m => m[1].toLowerCase())
+        .filter(field => !field.startsWith('_'));
+    if (fields.length === 0) {
+        updateStatus('Usage: :tagfill <pattern> (e.g. %track% - %title%)');
+        return;
+    }
+    const changes = fields.map(field => ({ field, value: null, pattern }));
+    await applyTagChanges(changes, all);
+}
+
+async function applyTagChanges(changes, all) {
+    const paths = getTagTargets(all);
+    if (paths.length === 0) {
+        updateStatus('No tracks selected');
+        return;
+    }
+    try {
+        const result = await invoke('write_tags', { paths, changes });
+        reportTagResult(result, 'Updated');
+    } catch (err) {
+        updateStatus(`Error: ${err}`);
+    }
+}
+
+export async function undoTagBatch() {
+    try {
+        const result = await invoke('undo_tag_batch');
+        reportTagResult(result, 'Restored');
+    } catch (err) {
+        updateStatus(`Error: ${err}`);
+    }
+}
+
+// :infer [pattern | clear | write | nowrite] — manage path patterns used to fill missing tags
+export async function handleInferCommand(args) {
+    try {
+        const config = await invoke('get_infer_config');
+        const arg = args.trim();
+        if (!arg) {
+            if (config.patterns.length === 0) {
+                updateStatus('No infer patterns. Usage: :infer %artist%/%album%/%track% - %title%');
+            } else {
+                const list = config.patterns.map((p, i) => `${i + 1}. ${p}`).join(', ');
+                updateStatus(`Infer patterns${config.write_back ? ' (write-back on)' : ''}: ${list}`);
+            }
+            return;
+        }
+        
+        let patterns = config.patterns;
+        let writeBack = config.write_back;
+        if (arg === 'clear') {
+            patterns = [];
+        } else if (arg === 'write' || arg === 'nowrite') {
+            writeBack = arg === 'write';
+        } else if (!patterns.includes(arg)) {
+            patterns = [...patterns, arg];
+        }
+        
+        const inferredCount = await invoke('set_infer_config', { patterns, writeBack });
+        updateStatus(`${patterns.length} infer pattern${patterns.length !== 1 ? 's' : ''}, write-back ${writeBack ? 'on' : 'off'} — ${inferredCount} tracks with inferred tags`);
+        if (state.viewMode === 'artist') {
+            import('./views/artist.js').then(m => m.openArtistView());
+        }
+    } catch (err) {
+        updateStatus(`Error: ${err}`);
+    }
+}
+
+// :writeinferred — store inferred values of every listed track in its tags
+export async function writeInferredTags() {
+    const tracks = getTagTargets(true);
+    if (tracks.length === 0) {
+        updateStatus('No tracks loaded');
+        return;
+    }
+    try {
+        const result = await invoke('write_inferred_tags', { tracks });
+        reportTagResult(result, 'Wrote inferred');
+    } catch (err) {
+        updateStatus(`Error: ${err}`);
+    }
+}
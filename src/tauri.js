@@ -1,15 +1,17 @@
 // Tauri API initialization
-let invoke, open, listen;
+let invoke, open, listen, convertFileSrc;
 
 if (window.__TAURI__) {
     invoke = window.__TAURI__.tauri?.invoke || window.__TAURI__.invoke;
     open = window.__TAURI__.dialog?.open;
     listen = window.__TAURI__.event?.listen;
+    convertFileSrc = window.__TAURI__.tauri?.convertFileSrc;
 } else {
     console.warn('Tauri API not available');
     invoke = async () => { throw new Error('Tauri not available'); };
     open = async () => null;
     listen = async () => () => {};
+    convertFileSrc = (path) => path;
 }
 
-export { invoke, open, listen };
+export { invoke, open, listen, convertFileSrc };
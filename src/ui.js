@@ -1,5 +1,7 @@
 import { state, elements } from './state.js';
 import { formatDuration } from './utils.js';
+import { invoke, convertFileSrc } from './tauri.js';
+import { drawWaveform } from './waveform.js';
 
 export function updateNowPlaying(name) {
     elements.trackName.textContent = name;
@@ -10,6 +12,25 @@ export function updateNowPlaying(name) {
     }
 }
 
+// Show the track's cover thumbnail in the player bar, or the placeholder icon
+export async function updateTrackArt(path) {
+    elements.trackArt.classList.remove('has-cover');
+    elements.trackArtImage.removeAttribute('src');
+    if (!path) return;
+    
+    try {
+        const cover = await invoke('get_cover_art', { path });
+        // Another track may have started while the thumbnail was being made
+        const playingPath = state.playlist[state.playingIndex]?.path;
+        if (cover && playingPath === path) {
+            elements.trackArtImage.src = convertFileSrc(cover, 'cover');
+            elements.trackArt.classList.add('has-cover');
+        }
+    } catch (err) {
+        console.error('Failed to load cover art:', err);
+    }
+}
+
 export function updateStatus(status) {
     elements.trackStatus.textContent = status;
 }
@@ -59,6 +80,7 @@ export function updateProgressDisplay() {
     } else {
         elements.progressFill.style.width = '0%';
     }
+    drawWaveform();
 }
 
 export function resetProgressDisplay() {
@@ -109,20 +131,27 @@ export function closeModals() {
     updateModeIndicators();
 }
 
-export function updateSleepTimerDisplay(remainingMs) {
+// Shows the time left on the sleep timer, or what it waits for when that isn't known yet
+export function updateSleepTimerDisplay(sleep) {
     if (!elements.sleepTimerIndicator) return;
     
-    if (remainingMs <= 0) {
+    if (!sleep) {
         elements.sleepTimerIndicator.textContent = '';
         elements.sleepTimerIndicator.classList.remove('active');
         return;
     }
     
-    const totalSeconds = Math.ceil(remainingMs / 1000);
-    const minutes = Math.floor(totalSeconds / 60);
-    const seconds = totalSeconds % 60;
-    
-    elements.sleepTimerIndicator.textContent = `${minutes}:${seconds.toString().padStart(2, '0')}`;
+    let text;
+    if (sleep.remaining_secs !== null) {
+        const minutes = Math.floor(sleep.remaining_secs / 60);
+        const seconds = sleep.remaining_secs % 60;
+        text = `${minutes}:${seconds.toString().padStart(2, '0')}`;
+    } else if (sleep.mode === 'tracks') {
+        text = `${sleep.tracks_left} tracks`;
+    } else {
+        text = 'queue';
+    }
+    elements.sleepTimerIndicator.textContent = text;
     elements.sleepTimerIndicator.classList.add('active');
 }
 
@@ -84,9 +84,10 @@ function renderArtistList() {
         const classes = ['track-item'];
         if (isSelected) classes.push('selected');
         if (isMatch) classes.push('match');
+        if (artist.inferred) classes.push('inferred');
         
         return `
-            <div class="${classes.join(' ')}" data-index="${index}">
+            <div class="${classes.join(' ')}" data-index="${index}"${artist.inferred ? ' title="Inferred from file and folder names"' : ''}>
                 <span class="track-number">
                     <svg viewBox="0 0 24 24" fill="currentColor" width="16" height="16"><path d="M12 12c2.21 0 4-1.79 4-4s-1.79-4-4-4-4 1.79-4 4 1.79 4 4 4zm0 2c-2.67 0-8 1.34-8 4v2h16v-2c0-2.66-5.33-4-8-4z"/></svg>
                 </span>
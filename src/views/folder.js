@@ -22,6 +22,23 @@ export async function openFolder() {
     }
 }
 
+// Load a playlist file in place of a folder, without saving it
+export async function openPlaylistFile() {
+    try {
+        const selected = await open({
+            multiple: false,
+            title: 'Open Playlist File',
+            filters: [{ name: 'Playlists', extensions: ['m3u', 'm3u8', 'pls', 'xspf'] }]
+        });
+        
+        if (selected) {
+            await loadFolder(selected);
+        }
+    } catch (err) {
+        console.error('Failed to open playlist file:', err);
+    }
+}
+
 export async function loadFolder(path) {
     try {
         updateStatus('Loading...');
@@ -27,6 +27,7 @@ export function renderPlaylist() {
         if (isSelected) classes.push('selected');
         if (isInVisual) classes.push('visual-selected');
         if (isMatch) classes.push('match');
+        if (track.missing) classes.push('missing');
         
         let lineNum = '';
         if (state.settings.number || state.settings.relativenumber) {
@@ -47,6 +48,7 @@ export function renderPlaylist() {
                     <path d="M8 5v14l11-7z"/>
                 </svg>
             </div>
+            ${renderChapters(track, index)}
         `;
     }).join('');
     
@@ -62,6 +64,32 @@ export function renderPlaylist() {
             import('../playback.js').then(m => m.playTrack(index));
         });
     });
+    
+    elements.playlist.querySelectorAll('.chapter-item').forEach(item => {
+        item.addEventListener('dblclick', () => {
+            const index = parseInt(item.dataset.track);
+            const chapter = parseInt(item.dataset.chapter);
+            import('../chapters.js').then(m => m.playChapter(index, chapter));
+        });
+    });
+}
+
+// Chapter sub-entries for tracks expanded with za
+function renderChapters(track, index) {
+    const chapters = state.expandedChapters[track.path];
+    if (!chapters) return '';
+    
+    const isCurrentTrack = state.currentChapter.path === track.path;
+    return chapters.map((chapter, i) => {
+        const classes = ['chapter-item'];
+        if (isCurrentTrack && state.currentChapter.index === i) classes.push('playing');
+        return `
+            <div class="${classes.join(' ')}" data-track="${index}" data-chapter="${i}">
+                <span class="chapter-start">${formatDuration(Math.floor(chapter.start_ms / 1000))}</span>
+                <span class="chapter-title">${escapeHtml(chapter.title)}</span>
+            </div>
+        `;
+    }).join('');
 }
 
 function getVisualSelectionIndices() {
@@ -0,0 +1,69 @@
+import { invoke } from './tauri.js';
+import { state, elements } from './state.js';
+
+const WAVEFORM_BUCKETS = 400;
+
+// Fetch the waveform of the playing track for the seek bar; null clears it and stops any decode
+export async function loadWaveform(path) {
+    state.waveform = null;
+    elements.progressBar.classList.remove('has-waveform');
+    if (!path) {
+        invoke('cancel_waveform').catch(() => {});
+        return;
+    }
+    
+    try {
+        const waveform = await invoke('get_waveform', { path, buckets: WAVEFORM_BUCKETS });
+        // A newer request superseded this one, or another track started meanwhile
+        const playingPath = state.playlist[state.playingIndex]?.path;
+        if (!waveform || playingPath !== path) return;
+        
+        state.waveform = waveform;
+        elements.progressBar.classList.add('has-waveform');
+        drawWaveform();
+    } catch (err) {
+        console.error('Failed to load waveform:', err);
+    }
+}
+
+// Peaks in a dim shade with RMS on top, the played part in the accent color
+export function drawWaveform() {
+    const waveform = state.waveform;
+    if (!waveform) return;
+    
+    const canvas = elements.progressWaveform;
+    const ratio = window.devicePixelRatio || 1;
+    const width = Math.round(canvas.clientWidth * ratio);
+    const height = Math.round(canvas.clientHeight * ratio);
+    if (canvas.width !== width || canvas.height !== height) {
+        canvas.width = width;
+        canvas.height = height;
+    }
+    
+    const styles = getComputedStyle(document.documentElement);
+    const played = styles.getPropertyValue('--accent').trim();
+    const unplayed = styles.getPropertyValue('--text-muted').trim();
+    const playedX = state.duration > 0 ? Math.min(state.elapsed / state.duration, 1) * width : 0;
+    
+    const count = waveform.max.length;
+    const loudest = Math.max(...waveform.max, ...waveform.min.map(Math.abs), 0.01);
+    const step = width / count;
+    const barWidth = Math.max(1, step - ratio);
+    const middle = height / 2;
+    
+    const ctx = canvas.getContext('2d');
+    ctx.clearRect(0, 0, width, height);
+    for (let i = 0; i < count; i++) {
+        const x = i * step;
+        ctx.fillStyle = x < playedX ? played : unplayed;
+        
+        const top = middle - (waveform.max[i] / loudest) * middle;
+        const bottom = middle - (waveform.min[i] / loudest) * middle;
+        ctx.globalAlpha = 0.5;
+        ctx.fillRect(x, top, barWidth, Math.max(bottom - top, 1));
+        
+        const rms = (waveform.rms[i] / loudest) * middle;
+        ctx.globalAlpha = 1;
+        ctx.fillRect(x, middle - rms, barWidth, Math.max(rms * 2, 1));
+    }
+}